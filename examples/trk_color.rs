@@ -2,7 +2,7 @@ use anyhow::Result;
 use docopt::Docopt;
 use trk_io::{Header, Point, Reader, Writer};
 
-static USAGE: &'static str = "
+static USAGE: &str = "
 Color a TrackVis (.trk) file.
 
 This will add 3 scalars (color_x, color_y, color_z) per point. Please note that coloring by 'local'
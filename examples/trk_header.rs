@@ -3,9 +3,9 @@ use std::{fs::File, io::BufReader};
 use anyhow::{Context, Result};
 use docopt::Docopt;
 
-use trk_io::CHeader;
+use trk_io::{stats::TractogramStats, CHeader, Reader};
 
-static USAGE: &'static str = "
+static USAGE: &str = "
 Print a TrackVis (.trk) header in an readable form
 
 Usage:
@@ -15,6 +15,7 @@ Usage:
 
 Options:
   -a --all       Also print computed fields (endianness, affine, etc.)
+  -s --stats     Also print statistics on the streamlines (lengths, bounds, scalars, etc.)
   -h --help      Show this screen.
   -v --version   Show version.
 ";
@@ -25,6 +26,7 @@ fn main() -> Result<()> {
         .and_then(|dopt| dopt.version(Some(version)).parse())
         .unwrap_or_else(|e| e.exit());
     let print_all = args.get_bool("--all");
+    let print_stats = args.get_bool("--stats");
 
     let path = args.get_str("<input>");
     let mut reader =
@@ -75,5 +77,42 @@ fn main() -> Result<()> {
         print!("to to_trackvis {}", to_trackvis);
     }
 
+    if print_stats {
+        let stats = TractogramStats::from_reader(Reader::new(path)?, 10);
+        println!("\n---------- Statistics ----------");
+        println!(
+            "streamlines: {} (n_count: {})",
+            stats.nb_streamlines, stats.header_nb_streamlines
+        );
+        println!("points: {}", stats.nb_points);
+        println!("degenerate streamlines: {}", stats.nb_degenerate);
+        if let Some(length) = stats.length {
+            println!(
+                "length: min {} mean {} median {} max {}",
+                length.min, length.mean, length.median, length.max
+            );
+            for (edge, count) in length.bin_edges.iter().zip(&length.counts) {
+                println!("  >= {}: {}", edge, count);
+            }
+        }
+        if let Some(step) = stats.step_size {
+            println!("step size: min {} mean {} max {}", step.min, step.mean, step.max);
+        }
+        if let Some(bounds) = stats.bounds_rasmm {
+            println!("bounds (RAS+ mm): {:?} {:?}", bounds.min, bounds.max);
+        }
+        if let Some(bounds) = stats.bounds_vox {
+            println!("bounds (voxel): {:?} {:?}", bounds.min, bounds.max);
+        }
+        for (kind, values) in [("scalar", &stats.scalars), ("property", &stats.properties)] {
+            for (name, value) in values {
+                println!(
+                    "{} {}: min {} mean {} max {}",
+                    kind, name, value.min, value.mean, value.max
+                );
+            }
+        }
+    }
+
     Ok(())
 }
@@ -3,7 +3,7 @@ use docopt::Docopt;
 
 use trk_io::{Point, Reader};
 
-static USAGE: &'static str = "
+static USAGE: &str = "
 Print the first points of the first streamlines of a trk file.
 
 Usage:
@@ -32,7 +32,7 @@ fn main() -> Result<()> {
         println!("({:.*} {:.*} {:.*})", precision, p[0], precision, p[1], precision, p[2]);
     };
 
-    let upto = args.get_str("--upto").parse::<usize>().unwrap_or(std::usize::MAX);
+    let upto = args.get_str("--upto").parse::<usize>().unwrap_or(usize::MAX);
     let first_part = upto / 2;
 
     let reader = Reader::new(args.get_str("<input>"))?.into_streamlines_iter();
@@ -55,7 +55,7 @@ fn main() -> Result<()> {
             if i == nb {
                 break;
             }
-            println!("");
+            println!();
         }
     } else {
         let idx = args.get_str("<idx>").parse::<usize>()?;
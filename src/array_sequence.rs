@@ -1,6 +1,5 @@
 use std::{
-    mem,
-    ops::{Index, Range},
+    ops::{Index, IndexMut, Range},
     slice,
     vec::Vec,
 };
@@ -63,7 +62,7 @@ impl<'a, T> Iterator for ArraySequenceIteratorMut<'a, T> {
         let nb_elements = current_offset - self.last_offset;
         self.last_offset = current_offset;
 
-        let data = mem::replace(&mut self.data, &mut []);
+        let data = std::mem::take(&mut self.data);
         let (slice, remaining_data) = data.split_at_mut(nb_elements);
         self.data = remaining_data;
         Some(slice)
@@ -79,16 +78,68 @@ impl<'data, T> DoubleEndedIterator for ArraySequenceIterator<'data, T> {
     }
 }
 
+impl<T> ArraySequence<T> {
+    /// Consumes the sequence and returns an iterator on its arrays, as `Vec<T>`.
+    ///
+    /// This is not an `IntoIterator` impl because `seq.into_iter()` already yields `&[T]`, through
+    /// the impl for `&ArraySequence`, and changing it would break the existing code.
+    pub fn into_arrays(self) -> ArraySequenceIntoIter<T> {
+        let lengths = self.offsets.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+        ArraySequenceIntoIter { lengths: lengths.into_iter(), data: self.data.into_iter() }
+    }
+}
+
+/// Owning iterator on the arrays of an `ArraySequence`.
+pub struct ArraySequenceIntoIter<T> {
+    lengths: std::vec::IntoIter<usize>,
+    data: std::vec::IntoIter<T>,
+}
+
+impl<T> Iterator for ArraySequenceIntoIter<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let nb_elements = self.lengths.next()?;
+        Some(self.data.by_ref().take(nb_elements).collect())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lengths.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for ArraySequenceIntoIter<T> {}
+
+impl<T> FromIterator<Vec<T>> for ArraySequence<T> {
+    /// Build an `ArraySequence` from arrays. Unlike `extend`, the empty arrays are kept.
+    fn from_iter<I: IntoIterator<Item = Vec<T>>>(iter: I) -> Self {
+        let mut arr = ArraySequence::empty();
+        for array in iter {
+            arr.data.extend(array);
+            arr.offsets.push(arr.data.len());
+        }
+        arr
+    }
+}
+
 impl<T> Index<usize> for ArraySequence<T> {
     type Output = [T];
 
-    fn index<'a>(&'a self, i: usize) -> &'a Self::Output {
+    fn index(&self, i: usize) -> &Self::Output {
         let start = self.offsets[i];
         let end = self.offsets[i + 1];
         &self.data[start..end]
     }
 }
 
+impl<T> IndexMut<usize> for ArraySequence<T> {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        let start = self.offsets[i];
+        let end = self.offsets[i + 1];
+        &mut self.data[start..end]
+    }
+}
+
 impl<T> Default for ArraySequence<T> {
     fn default() -> Self {
         ArraySequence::empty()
@@ -127,7 +178,7 @@ impl<T> ArraySequence<T> {
             );
         }
 
-        ArraySequence { offsets, data: data }
+        ArraySequence { offsets, data }
     }
 
     pub fn push(&mut self, val: T) {
@@ -149,7 +200,7 @@ impl<T> ArraySequence<T> {
     ///
     /// The array will be considered non empty if there was one or more
     /// `push()`, even without an `end_push()`. Use `len()` instead to ignore
-    /// all pushed elements.
+    /// all pushed elements. An array containing only empty arrays is empty.
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
@@ -186,6 +237,87 @@ impl<T> ArraySequence<T> {
     pub fn iter_mut(&mut self) -> ArraySequenceIteratorMut<'_, T> {
         self.into_iter()
     }
+
+    /// Removes and returns the array at position `i`, shifting all arrays after it.
+    pub fn remove(&mut self, i: usize) -> Vec<T> {
+        let (start, end) = (self.offsets[i], self.offsets[i + 1]);
+        let removed = self.data.drain(start..end).collect();
+        self.offsets.remove(i + 1);
+        for offset in &mut self.offsets[i + 1..] {
+            *offset -= end - start;
+        }
+        removed
+    }
+
+    /// Inserts an array at position `i`, shifting all arrays after it. Unlike `extend`, an empty
+    /// array is inserted as a new array.
+    pub fn insert<I: IntoIterator<Item = T>>(&mut self, i: usize, array: I) {
+        let start = self.offsets[i];
+        let nb_before = self.data.len();
+        self.data.splice(start..start, array);
+        let nb_inserted = self.data.len() - nb_before;
+        self.offsets.insert(i + 1, start + nb_inserted);
+        for offset in &mut self.offsets[i + 2..] {
+            *offset += nb_inserted;
+        }
+    }
+
+    /// Replaces the array at position `i`, which may have a different length, and returns the old
+    /// array.
+    pub fn replace<I: IntoIterator<Item = T>>(&mut self, i: usize, array: I) -> Vec<T> {
+        let (start, end) = (self.offsets[i], self.offsets[i + 1]);
+        let nb_before = self.data.len();
+        let removed = self.data.splice(start..end, array).collect::<Vec<_>>();
+        let nb_after = self.data.len();
+        for offset in &mut self.offsets[i + 1..] {
+            *offset = *offset + nb_after - nb_before;
+        }
+        removed
+    }
+
+    /// Removes the array at position `i` and returns it. The last array takes its place.
+    ///
+    /// The order is not preserved, but, unlike `Vec::swap_remove`, the data after the array `i`
+    /// still needs to be moved if the last array doesn't have the same length.
+    pub fn swap_remove(&mut self, i: usize) -> Vec<T> {
+        let last = self.len() - 1;
+        if i == last {
+            return self.remove(i);
+        }
+        let last_array = self.data.split_off(self.offsets[last]);
+        self.offsets.pop();
+        self.replace(i, last_array)
+    }
+
+    /// Splits the sequence in two at the given index. Returns the arrays `[at, len)`, while `self`
+    /// keeps the arrays `[0, at)`.
+    pub fn split_off(&mut self, at: usize) -> ArraySequence<T> {
+        let start = self.offsets[at];
+        let data = self.data.split_off(start);
+        let mut offsets = self.offsets.split_off(at);
+        for offset in &mut offsets {
+            *offset -= start;
+        }
+        self.offsets.push(start);
+        ArraySequence { offsets, data }
+    }
+
+    /// Moves all arrays of `other` at the end of `self`, leaving `other` empty.
+    pub fn append(&mut self, other: &mut ArraySequence<T>) {
+        let start = self.data.len();
+        self.data.append(&mut other.data);
+        self.offsets.extend(other.offsets[1..].iter().map(|offset| offset + start));
+        other.offsets.truncate(1);
+    }
+
+    /// Keeps the first `len` arrays and drops the others. Does nothing if there are already less
+    /// arrays than that.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.data.truncate(self.offsets[len]);
+            self.offsets.truncate(len + 1);
+        }
+    }
 }
 
 impl<T> Extend<T> for ArraySequence<T> {
@@ -200,4 +332,224 @@ impl<T: Clone> ArraySequence<T> {
         self.data.extend_from_slice(other);
         self.end_push();
     }
+
+    /// Returns a copy of the arrays in `range`.
+    pub fn slice(&self, range: Range<usize>) -> ArraySequence<T> {
+        let start = self.offsets[range.start];
+        let offsets = self.offsets[range.start..=range.end].iter().map(|o| o - start).collect();
+        ArraySequence { offsets, data: self.data[start..self.offsets[range.end]].to_vec() }
+    }
+
+    /// Reorders the arrays so that the array at position `i` is the old array `permutation[i]`.
+    ///
+    /// Panics if `permutation` is not a permutation of `0..self.len()`.
+    pub fn permute(&mut self, permutation: &[usize]) {
+        let mut seen = vec![false; self.len()];
+        if permutation.len() != self.len()
+            || !permutation
+                .iter()
+                .all(|&i| i < seen.len() && !std::mem::replace(&mut seen[i], true))
+        {
+            panic!("The indices are not a permutation of 0..{}", self.len());
+        }
+
+        let mut offsets = Vec::with_capacity(self.offsets.len());
+        offsets.push(0);
+        let mut data = Vec::with_capacity(self.data.len());
+        for &i in permutation {
+            data.extend_from_slice(&self[i]);
+            offsets.push(data.len());
+        }
+        *self = ArraySequence { offsets, data };
+    }
+}
+
+/// Arrays of a parent, either a contiguous range or a list of indices.
+#[derive(Clone, Copy)]
+pub(crate) enum Selection<'a> {
+    Range { start: usize, end: usize },
+    Indices(&'a [usize]),
+}
+
+impl<'a> Selection<'a> {
+    pub(crate) fn new_range(range: Range<usize>, parent_len: usize) -> Selection<'a> {
+        if range.start > range.end || range.end > parent_len {
+            panic!("Range {:?} is out of bounds (len {})", range, parent_len);
+        }
+        Selection::Range { start: range.start, end: range.end }
+    }
+
+    pub(crate) fn new_indices(indices: &'a [usize], parent_len: usize) -> Selection<'a> {
+        if let Some(idx) = indices.iter().find(|&&idx| idx >= parent_len) {
+            panic!("Index {} is out of bounds (len {})", idx, parent_len);
+        }
+        Selection::Indices(indices)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Selection::Range { start, end } => end - start,
+            Selection::Indices(indices) => indices.len(),
+        }
+    }
+
+    /// Index in the parent of the `i`th selected array.
+    pub(crate) fn get(&self, i: usize) -> usize {
+        match self {
+            Selection::Range { start, end } => {
+                if start + i >= *end {
+                    panic!("Index {} is out of bounds (len {})", i, end - start);
+                }
+                start + i
+            }
+            Selection::Indices(indices) => indices[i],
+        }
+    }
+
+    pub(crate) fn sub_range(&self, range: Range<usize>) -> Selection<'a> {
+        let range = Selection::new_range(range, self.len());
+        match (self, range) {
+            (Selection::Range { start, .. }, Selection::Range { start: s, end: e }) => {
+                Selection::Range { start: start + s, end: start + e }
+            }
+            (Selection::Indices(indices), Selection::Range { start: s, end: e }) => {
+                Selection::Indices(&indices[s..e])
+            }
+            (_, Selection::Indices(_)) => unreachable!(),
+        }
+    }
+}
+
+/// Read-only view on some arrays of an `ArraySequence`, without copying its data.
+///
+/// Built with `ArraySequence::view` for a range of arrays, or `ArraySequence::view_indices` for
+/// any list of arrays.
+pub struct ArraySequenceView<'a, T> {
+    parent: &'a ArraySequence<T>,
+    selection: Selection<'a>,
+}
+
+impl<'a, T> Clone for ArraySequenceView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for ArraySequenceView<'a, T> {}
+
+impl<T> ArraySequence<T> {
+    /// Returns a view on the arrays in `range`. Use `slice()` to get an owned copy instead.
+    ///
+    /// This replaces `Index<Range<usize>>`, which can only return a reference to existing data,
+    /// i.e., a flat slice of the values that loses the boundaries of the arrays.
+    pub fn view(&self, range: Range<usize>) -> ArraySequenceView<'_, T> {
+        ArraySequenceView { parent: self, selection: Selection::new_range(range, self.len()) }
+    }
+
+    /// Returns a view on the arrays `indices`, in this order. The same array can be used many
+    /// times.
+    pub fn view_indices<'a>(&'a self, indices: &'a [usize]) -> ArraySequenceView<'a, T> {
+        ArraySequenceView { parent: self, selection: Selection::new_indices(indices, self.len()) }
+    }
+}
+
+impl<'a, T> ArraySequenceView<'a, T> {
+    /// Returns the number of arrays in the view.
+    pub fn len(&self) -> usize {
+        self.selection.len()
+    }
+
+    /// Returns `true` if the arrays of the view contain no elements, like
+    /// `ArraySequence::is_empty()`. A view on empty arrays is thus empty. Use `len()` instead to
+    /// count the arrays.
+    pub fn is_empty(&self) -> bool {
+        (0..self.len()).all(|i| self.length_of_array(i) == 0)
+    }
+
+    /// Same as view[i].len(), without building a slice
+    pub fn length_of_array(&self, i: usize) -> usize {
+        self.parent.length_of_array(self.selection.get(i))
+    }
+
+    /// Returns the array `i`, with the lifetime of the parent.
+    pub fn get(&self, i: usize) -> &'a [T] {
+        &self.parent[self.selection.get(i)]
+    }
+
+    /// Returns a view on the arrays in `range` of this view.
+    pub fn view(&self, range: Range<usize>) -> ArraySequenceView<'a, T> {
+        ArraySequenceView { parent: self.parent, selection: self.selection.sub_range(range) }
+    }
+
+    pub fn iter(&self) -> ArraySequenceViewIterator<'a, T> {
+        self.into_iter()
+    }
+
+    /// Copy the viewed arrays in a new `ArraySequence`.
+    pub fn to_array_sequence(&self) -> ArraySequence<T>
+    where
+        T: Clone,
+    {
+        let mut offsets = Vec::with_capacity(self.len() + 1);
+        offsets.push(0);
+        let mut data = vec![];
+        for array in self {
+            data.extend_from_slice(array);
+            offsets.push(data.len());
+        }
+        ArraySequence { offsets, data }
+    }
+}
+
+impl<T> Index<usize> for ArraySequenceView<'_, T> {
+    type Output = [T];
+
+    fn index(&self, i: usize) -> &Self::Output {
+        self.get(i)
+    }
+}
+
+impl<'a, T> IntoIterator for ArraySequenceView<'a, T> {
+    type Item = &'a [T];
+    type IntoIter = ArraySequenceViewIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ArraySequenceViewIterator { view: self, index: 0..self.len() }
+    }
+}
+
+impl<'a, T> IntoIterator for &ArraySequenceView<'a, T> {
+    type Item = &'a [T];
+    type IntoIter = ArraySequenceViewIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).into_iter()
+    }
+}
+
+pub struct ArraySequenceViewIterator<'a, T> {
+    view: ArraySequenceView<'a, T>,
+    index: Range<usize>,
+}
+
+impl<'a, T> Iterator for ArraySequenceViewIterator<'a, T> {
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.index.next()?;
+        Some(self.view.get(idx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.index.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for ArraySequenceViewIterator<'_, T> {}
+
+impl<T> DoubleEndedIterator for ArraySequenceViewIterator<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let idx = self.index.next_back()?;
+        Some(self.view.get(idx))
+    }
 }
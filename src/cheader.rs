@@ -7,9 +7,9 @@ use std::{
 
 use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use nalgebra::Vector4;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "nifti_images")]
-use crate::Affine;
 use crate::{
     orientation::{
         affine_to_axcodes, axcodes_to_orientations, inverse_orientations_affine,
@@ -33,19 +33,29 @@ impl fmt::Display for Endianness {
 }
 
 // http://www.trackvis.org/docs/?subsect=fileformat
+//
+// With the `serde` feature, the text fields are serialized as strings, without their trailing
+// zeros, and the names as a list of strings, one per non-empty slot. The grouped names keep their
+// encoding, e.g., "colors\u00003". `reserved` may hold any bytes, so it's serialized in base64.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct CHeader {
+    #[cfg_attr(feature = "serde", serde(with = "text_field"))]
     pub id_string: [u8; 6],
     pub dim: [i16; 3],
     pub voxel_size: [f32; 3],
     pub origin: [f32; 3],
     pub n_scalars: i16,
+    #[cfg_attr(feature = "serde", serde(with = "names_field"))]
     pub scalar_name: [u8; 200], // [10][20]
     pub n_properties: i16,
+    #[cfg_attr(feature = "serde", serde(with = "names_field"))]
     pub property_name: [u8; 200], // [10][20]
-    pub vox_to_ras: [f32; 16],    // [4][4]
+    pub vox_to_ras: [f32; 16], // [4][4]
+    #[cfg_attr(feature = "serde", serde(with = "bytes_field"))]
     pub reserved: [u8; 444],
+    #[cfg_attr(feature = "serde", serde(with = "text_field"))]
     pub voxel_order: [u8; 4],
     pub pad2: [u8; 4],
     pub image_orientation_patient: [f32; 6],
@@ -73,26 +83,26 @@ impl CHeader {
         srow_z: [f32; 4],
     ) -> CHeader {
         #[rustfmt::skip]
-        let affine = Affine::new(
-            srow_x[0], srow_x[1], srow_x[2],
-            srow_y[0], srow_y[1], srow_y[2],
-            srow_z[0], srow_z[1], srow_z[2],
-        );
-        #[rustfmt::skip]
-        let vox_to_ras = [
+        let affine = Affine4::new(
             srow_x[0], srow_x[1], srow_x[2], srow_x[3],
             srow_y[0], srow_y[1], srow_y[2], srow_y[3],
             srow_z[0], srow_z[1], srow_z[2], srow_z[3],
             0.0, 0.0, 0.0, 1.0,
-        ];
-        let vo = affine_to_axcodes(&affine).into_bytes();
-        CHeader {
-            dim: [dim[1] as i16, dim[2] as i16, dim[3] as i16],
-            voxel_size: [pixdim[1], pixdim[2], pixdim[3]],
-            vox_to_ras,
-            voxel_order: [vo[0], vo[1], vo[2], 0u8],
-            ..CHeader::default()
-        }
+        );
+        CHeader::from_affine(
+            &affine,
+            [dim[1] as i16, dim[2] as i16, dim[3] as i16],
+            [pixdim[1], pixdim[2], pixdim[3]],
+        )
+    }
+
+    /// Build a header for a reference image of shape `dim`, whose voxel indices are mapped to
+    /// RAS+ mm by `affine`.
+    pub fn from_affine(affine: &Affine4, dim: [i16; 3], voxel_size: [f32; 3]) -> CHeader {
+        let mut header = CHeader::default();
+        header.set_voxel_to_rasmm(affine);
+        let vo = affine_to_axcodes(&affine.fixed_view::<3, 3>(0, 0).into_owned()).into_bytes();
+        CHeader { dim, voxel_size, voxel_order: [vo[0], vo[1], vo[2], 0u8], ..header }
     }
 
     pub fn seek_n_count_field(f: &mut BufWriter<File>) -> Result<()> {
@@ -109,24 +119,24 @@ impl CHeader {
     }
 
     pub fn add_scalar(&mut self, name: &str) -> Result<()> {
-        if self.n_scalars > 10 {
-            Err(Error::new(ErrorKind::InvalidInput, "Trk header is already full of scalars (10)"))
-        } else if name.len() > 20 {
-            Err(Error::new(ErrorKind::InvalidInput, "New scalar name must be <= 20 characters."))
-        } else if !name.is_ascii() {
-            Err(Error::new(ErrorKind::InvalidInput, "New scalar name must be pure ascii."))
-        } else {
-            let pos = 20 * self.n_scalars as usize;
-            self.scalar_name[pos..pos + name.len()].clone_from_slice(name.as_bytes());
-            self.n_scalars += 1;
-            return Ok(());
-        }
+        self.add_scalar_n(name, 1)
+    }
+
+    /// Add a scalar of `n` values per point, encoded as `name\0n` as nibabel does, so that it
+    /// uses only one of the 10 available names.
+    pub fn add_scalar_n(&mut self, name: &str, n: usize) -> Result<()> {
+        add_name(&mut self.scalar_name, &mut self.n_scalars, name, n, "scalar")
     }
 
     pub fn get_scalars_name(&self) -> Vec<String> {
         read_names(&self.scalar_name, self.n_scalars as usize)
     }
 
+    /// Returns the name and the number of values of each scalar.
+    pub fn get_scalars_groups(&self) -> Vec<(String, usize)> {
+        read_groups(&self.scalar_name)
+    }
+
     pub fn clear_properties(&mut self) {
         self.n_properties = 0;
         for b in self.property_name.iter_mut() {
@@ -135,27 +145,37 @@ impl CHeader {
     }
 
     pub fn add_property(&mut self, name: &str) -> Result<()> {
-        if self.n_properties > 10 {
-            Err(Error::new(
-                ErrorKind::InvalidInput,
-                "Trk header is already full of properties (10)",
-            ))
-        } else if name.len() > 20 {
-            Err(Error::new(ErrorKind::InvalidInput, "New property name must be <= 20 characters."))
-        } else if !name.is_ascii() {
-            Err(Error::new(ErrorKind::InvalidInput, "New property name must be pure ascii."))
-        } else {
-            let pos = 20 * self.n_properties as usize;
-            self.property_name[pos..pos + name.len()].clone_from_slice(name.as_bytes());
-            self.n_properties += 1;
-            return Ok(());
-        }
+        self.add_property_n(name, 1)
+    }
+
+    /// Add a property of `n` values per streamline, encoded as `name\0n` as nibabel does, so that
+    /// it uses only one of the 10 available names.
+    pub fn add_property_n(&mut self, name: &str, n: usize) -> Result<()> {
+        add_name(&mut self.property_name, &mut self.n_properties, name, n, "property")
     }
 
     pub fn get_properties_name(&self) -> Vec<String> {
         read_names(&self.property_name, self.n_properties as usize)
     }
 
+    /// Returns the name and the number of values of each property.
+    pub fn get_properties_groups(&self) -> Vec<(String, usize)> {
+        read_groups(&self.property_name)
+    }
+
+    /// Get affine mapping the voxel indices of the reference image to RAS+ mm space, that is,
+    /// the `vox_to_ras` field.
+    pub fn get_voxel_to_rasmm(&self) -> Affine4 {
+        Affine4::from_iterator(self.vox_to_ras.iter().cloned()).transpose()
+    }
+
+    /// Set the `vox_to_ras` field. `voxel_order` is not modified.
+    pub fn set_voxel_to_rasmm(&mut self, affine: &Affine4) {
+        for (dst, src) in self.vox_to_ras.iter_mut().zip(affine.transpose().iter()) {
+            *dst = *src;
+        }
+    }
+
     /// Get affine mapping trackvis voxelmm space to RAS+ mm space
     ///
     /// The streamlines in a trackvis file are in 'voxelmm' space, where the coordinates refer to
@@ -183,7 +203,7 @@ impl CHeader {
         );
         affine = offset * affine;
 
-        let voxel_to_rasmm = Affine4::from_iterator(self.vox_to_ras.iter().cloned()).transpose();
+        let voxel_to_rasmm = self.get_voxel_to_rasmm();
 
         let header_ornt = axcodes_to_orientations(from_utf8(&self.voxel_order).unwrap());
         let affine_order = affine_to_axcodes(&voxel_to_rasmm.fixed_view::<3, 3>(0, 0).into_owned());
@@ -254,7 +274,7 @@ impl CHeader {
     }
 
     pub fn write<W: WriteBytesExt>(&self, writer: &mut W) -> Result<()> {
-        writer.write(&self.id_string)?;
+        writer.write_all(&self.id_string)?;
         for i in &self.dim {
             writer.write_i16::<TrkEndianness>(*i)?;
         }
@@ -265,19 +285,19 @@ impl CHeader {
             writer.write_f32::<TrkEndianness>(*f)?;
         }
         writer.write_i16::<TrkEndianness>(self.n_scalars)?;
-        writer.write(&self.scalar_name)?;
+        writer.write_all(&self.scalar_name)?;
         writer.write_i16::<TrkEndianness>(self.n_properties)?;
-        writer.write(&self.property_name)?;
+        writer.write_all(&self.property_name)?;
         for f in &self.vox_to_ras {
             writer.write_f32::<TrkEndianness>(*f)?;
         }
-        writer.write(&self.reserved)?;
-        writer.write(&self.voxel_order)?;
-        writer.write(&self.pad2)?;
+        writer.write_all(&self.reserved)?;
+        writer.write_all(&self.voxel_order)?;
+        writer.write_all(&self.pad2)?;
         for f in &self.image_orientation_patient {
             writer.write_f32::<TrkEndianness>(*f)?;
         }
-        writer.write(&self.pad1)?;
+        writer.write_all(&self.pad1)?;
         writer.write_u8(self.invert_x)?;
         writer.write_u8(self.invert_y)?;
         writer.write_u8(self.invert_z)?;
@@ -290,6 +310,63 @@ impl CHeader {
 
         Ok(())
     }
+
+    /// Fails if a field needed to compute the affine or the names is invalid, e.g., in a header
+    /// edited by hand.
+    ///
+    /// `voxel_order` must contain one letter per axis, e.g., "LPS". The dimensions and the voxel
+    /// sizes must be positive, and the names can't describe more values than `n_scalars` and
+    /// `n_properties`.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(Error::new(ErrorKind::InvalidData, msg));
+        if &self.id_string != b"TRACK\0" {
+            return invalid("Not a TrackVis header (wrong file signature)".to_string());
+        }
+        let axes = ["RL", "AP", "SI"];
+        let code = &self.voxel_order[..3];
+        let is_valid_order = self.voxel_order[3] == 0
+            && axes
+                .iter()
+                .all(|axis| code.iter().filter(|&&b| axis.as_bytes().contains(&b)).count() == 1);
+        if !is_valid_order {
+            let order = String::from_utf8_lossy(&self.voxel_order);
+            return invalid(format!("Invalid voxel_order {:?}", order.trim_end_matches('\0')));
+        }
+        if self.dim.iter().any(|&d| d <= 0) {
+            return invalid(format!("The dimensions must be positive: {:?}", self.dim));
+        }
+        if self.voxel_size.iter().any(|&s| !s.is_finite() || s <= 0.0) {
+            return invalid(format!("The voxel sizes must be positive: {:?}", self.voxel_size));
+        }
+        if self.n_count < 0 {
+            return invalid(format!("Invalid number of streamlines {}", self.n_count));
+        }
+        for (kind, count, names) in [
+            ("scalars", self.n_scalars, &self.scalar_name),
+            ("properties", self.n_properties, &self.property_name),
+        ] {
+            let nb_named = read_groups(names).iter().map(|(_, n)| n).sum::<usize>();
+            if count < 0 || nb_named > count as usize {
+                return invalid(format!("{} names for {} {}", nb_named, count, kind));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a trk header serialized by `to_json`, e.g., after editing it by hand. The header is
+    /// validated with `validate`.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<CHeader> {
+        let header: CHeader = serde_json::from_str(json)?;
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Serialize the trk header to a human-readable JSON string.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
 }
 
 impl Default for CHeader {
@@ -341,32 +418,176 @@ fn test_endianness(reader: &mut BufReader<File>) -> Result<Endianness> {
     Ok(endianness)
 }
 
-/// Returns the names from the [10][20] arrays of bytes.
+/// Write `name`, with its number of values `n`, in the first free slot of the [10][20] arrays of
+/// bytes, and add `n` to `count`.
+fn add_name(
+    names_bytes: &mut [u8; 200],
+    count: &mut i16,
+    name: &str,
+    n: usize,
+    kind: &str,
+) -> Result<()> {
+    let encoded = if n == 1 { name.to_string() } else { format!("{}\0{}", name, n) };
+    // Grouped names use only one slot for all their values
+    let nb_grouped = read_groups(names_bytes).iter().map(|(_, n)| n - 1).sum::<usize>();
+    let slot = (*count as usize).saturating_sub(nb_grouped);
+    if slot >= 10 {
+        let msg = format!("Trk header is already full of {} (10)", plural(kind));
+        Err(Error::new(ErrorKind::InvalidInput, msg))
+    } else if n == 0 || *count as usize + n > i16::MAX as usize {
+        let msg = format!("Invalid number of values for {} {:?}: {}", kind, name, n);
+        Err(Error::new(ErrorKind::InvalidInput, msg))
+    } else if encoded.len() > 20 {
+        let msg = if n == 1 {
+            format!("New {} name must be <= 20 characters.", kind)
+        } else {
+            format!("New {} name must be <= {} characters.", kind, 19 - n.to_string().len())
+        };
+        Err(Error::new(ErrorKind::InvalidInput, msg))
+    } else if !name.is_ascii() {
+        let msg = format!("New {} name must be pure ascii.", kind);
+        Err(Error::new(ErrorKind::InvalidInput, msg))
+    } else {
+        let pos = 20 * slot;
+        names_bytes[pos..pos + encoded.len()].clone_from_slice(encoded.as_bytes());
+        *count += n as i16;
+        Ok(())
+    }
+}
+
+fn plural(kind: &str) -> &str {
+    match kind {
+        "property" => "properties",
+        _ => "scalars",
+    }
+}
+
+/// Returns the names from the [10][20] arrays of bytes, repeated for their number of values.
+///
+/// There should be `nb` names, but some files don't fill the names, thus the missing names are
+/// returned as empty strings.
+fn read_names(names_bytes: &[u8], nb: usize) -> Vec<String> {
+    let mut names = vec![];
+    for (name, number) in read_groups(names_bytes) {
+        names.extend(std::iter::repeat_n(name, number));
+    }
+    names.resize(nb, String::new());
+    names
+}
+
+/// Returns the names from the [10][20] arrays of bytes, with their number of values.
 ///
 /// Normal case: name\0\0...
 /// Special case: name\0{number}\0\0...
-fn read_names(names_bytes: &[u8], nb: usize) -> Vec<String> {
-    let mut at = 0;
-    let mut names = vec![String::from(""); nb];
+fn read_groups(names_bytes: &[u8]) -> Vec<(String, usize)> {
+    let mut groups = vec![];
     for names_byte in names_bytes.chunks(20) {
         if names_byte[0] == 0u8 {
             break;
         }
 
         let idx = names_byte.iter().position(|&e| e == 0u8).unwrap_or(20);
-        let name = from_utf8(&names_byte[..idx]).unwrap().to_string();
-        if idx < 19 && names_byte[idx + 1] != 0u8 {
-            let number = names_byte[idx + 1] - 48;
-            for _ in 0..number {
-                names[at] = name.clone();
-                at += 1;
-            }
-        } else {
-            names[at] = name;
-            at += 1;
+        let name = String::from_utf8_lossy(&names_byte[..idx]).to_string();
+        let number = names_byte
+            .get(idx + 1..)
+            .map(|rest| &rest[..rest.iter().position(|&e| e == 0u8).unwrap_or(rest.len())])
+            .and_then(|digits| from_utf8(digits).ok())
+            .and_then(|digits| digits.parse::<usize>().ok())
+            .filter(|&number| number > 0)
+            .unwrap_or(1);
+        groups.push((name, number));
+    }
+    groups
+}
+
+/// Fixed-size byte arrays, serialized as strings without their trailing zeros.
+#[cfg(feature = "serde")]
+mod text_field {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&to_text(bytes))
+    }
+
+    pub fn deserialize<'de, D, const N: usize>(d: D) -> Result<[u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_text(&String::deserialize(d)?).map_err(D::Error::custom)
+    }
+
+    pub(super) fn to_text(bytes: &[u8]) -> String {
+        let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    }
+
+    pub(super) fn from_text<const N: usize>(text: &str) -> Result<[u8; N], String> {
+        if text.len() > N {
+            return Err(format!("{:?} is longer than {} bytes", text, N));
         }
+        let mut bytes = [0; N];
+        bytes[..text.len()].copy_from_slice(text.as_bytes());
+        Ok(bytes)
+    }
+}
+
+/// Fixed-size byte arrays, serialized in base64 without their trailing zeros.
+#[cfg(feature = "serde")]
+mod bytes_field {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        s.serialize_str(&STANDARD.encode(&bytes[..end]))
+    }
+
+    pub fn deserialize<'de, D, const N: usize>(d: D) -> Result<[u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let decoded = STANDARD.decode(String::deserialize(d)?).map_err(D::Error::custom)?;
+        if decoded.len() > N {
+            return Err(D::Error::custom(format!("The data is longer than {} bytes", N)));
+        }
+        let mut bytes = [0; N];
+        bytes[..decoded.len()].copy_from_slice(&decoded);
+        Ok(bytes)
+    }
+}
+
+/// The 10 slots of 20 bytes of the scalars and properties names, serialized as a list of strings.
+#[cfg(feature = "serde")]
+mod names_field {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::text_field::{from_text, to_text};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 200], s: S) -> Result<S::Ok, S::Error> {
+        let mut names = bytes.chunks(20).map(to_text).collect::<Vec<_>>();
+        while names.last().is_some_and(|name| name.is_empty()) {
+            names.pop();
+        }
+        s.collect_seq(names)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 200], D::Error> {
+        let names = Vec::<String>::deserialize(d)?;
+        if names.len() > 10 {
+            return Err(D::Error::custom("There can't be more than 10 names"));
+        }
+        let mut bytes = [0; 200];
+        for (slot, name) in bytes.chunks_mut(20).zip(&names) {
+            slot.copy_from_slice(&from_text::<20>(name).map_err(D::Error::custom)?);
+        }
+        Ok(bytes)
     }
-    names
 }
 
 #[cfg(test)]
@@ -389,10 +610,44 @@ mod tests {
     fn test_read_empty_names() {
         // N scalars/properties without a empty description should still return a vector of N
         // empty strings. It's not super practical, but that's the best we can do with such data.
-        let scalars = read_names(&vec![0; 80], 3);
+        let scalars = read_names(&[0; 80], 3);
         assert_eq!(scalars, vec![String::from(""), String::from(""), String::from("")]);
     }
 
+    #[test]
+    fn test_grouped_names() {
+        let mut header = CHeader::default();
+        header.add_scalar("fa").unwrap();
+        header.add_scalar_n("color", 3).unwrap();
+        header.add_scalar_n("tensor", 12).unwrap();
+        header.add_scalar("md").unwrap();
+        assert_eq!(header.n_scalars, 17);
+        assert_eq!(&header.scalar_name[20..27], b"color\x003");
+        assert_eq!(&header.scalar_name[40..50], b"tensor\x0012\0");
+        assert_eq!(
+            header.get_scalars_groups(),
+            vec![("fa".into(), 1), ("color".into(), 3), ("tensor".into(), 12), ("md".into(), 1)]
+        );
+
+        let names = header.get_scalars_name();
+        assert_eq!(names.len(), 17);
+        assert_eq!(names[1..4], ["color", "color", "color"]);
+        assert_eq!(names[16], "md");
+
+        assert!(header.add_scalar_n("a_very_long_name_19", 3).is_err());
+        assert!(header.add_scalar_n("zero", 0).is_err());
+    }
+
+    #[test]
+    fn test_full_names() {
+        let mut header = CHeader::default();
+        for i in 0..10 {
+            header.add_property_n(&format!("p{}", i), 2).unwrap();
+        }
+        assert_eq!(header.n_properties, 20);
+        assert!(header.add_property("p10").is_err());
+    }
+
     #[test]
     fn test_header_size() {
         assert_eq!(HEADER_SIZE, 1000);
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use ndarray::{Array2, Array3};
+
+use crate::{interpolation::nearest_voxel, streamline::length, Affine4, Header, Point, Tractogram};
+
+/// Options of `connectivity_matrix`.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectivityOptions<'a> {
+    /// Radius, in mm, in which to search for the closest labeled voxel when an endpoint falls in
+    /// an unlabeled voxel (e.g., in white matter). 0 disables the search.
+    pub search_radius: f32,
+
+    /// Name of a per-point scalar, or of a property, to average over the streamlines of each edge.
+    pub scalar: Option<&'a str>,
+
+    /// Keep the list of streamline indices of each edge in `Connectome::edges`.
+    pub return_edges: bool,
+}
+
+/// Structural connectivity matrices, indexed like `labels`.
+///
+/// All matrices are symmetric.
+#[derive(Clone, Debug)]
+pub struct Connectome {
+    /// Sorted non-zero labels of the parcellation. The label of row `i` is `labels[i]`.
+    pub labels: Vec<i32>,
+
+    /// Number of streamlines connecting each pair of labels.
+    pub count: Array2<f32>,
+
+    /// Mean length of the streamlines connecting each pair of labels. 0 if there's none.
+    pub mean_length: Array2<f32>,
+
+    /// Mean of the requested scalar over the streamlines connecting each pair of labels. Only
+    /// computed if `ConnectivityOptions::scalar` is set.
+    pub mean_scalar: Option<Array2<f32>>,
+
+    /// Indices of the streamlines connecting each pair `(i, j)` of labels, with `i <= j`. Only
+    /// computed if `ConnectivityOptions::return_edges` is set.
+    pub edges: Option<BTreeMap<(usize, usize), Vec<usize>>>,
+
+    /// Number of streamlines that could not be assigned to a pair of labels.
+    pub nb_unassigned: usize,
+}
+
+/// Build the structural connectivity matrices of `tractogram`, using the labels of the
+/// `parcellation` found at the endpoints of the streamlines.
+///
+/// `affine` maps the voxel indices of `parcellation` to RAS+ mm, like `NiftiHeader::affine`, and
+/// the streamlines are expected to be in RAS+ mm, as returned by `Reader`. Label 0 is considered
+/// as the background.
+pub fn connectivity_matrix(
+    tractogram: &Tractogram,
+    header: &Header,
+    parcellation: &Array3<i32>,
+    affine: &Affine4,
+    options: &ConnectivityOptions,
+) -> Result<Connectome> {
+    let to_voxel = match affine.try_inverse() {
+        Some(to_voxel) => to_voxel,
+        None => bail!("The affine of the parcellation is not invertible."),
+    };
+    let values = match options.scalar {
+        Some(name) => Some(streamline_values(tractogram, header, name)?),
+        None => None,
+    };
+
+    let mut labels = parcellation.iter().cloned().filter(|&l| l != 0).collect::<Vec<_>>();
+    labels.sort_unstable();
+    labels.dedup();
+    let n = labels.len();
+
+    let mut count = Array2::zeros((n, n));
+    let mut mean_length = Array2::zeros((n, n));
+    let mut mean_scalar = values.as_ref().map(|_| Array2::zeros((n, n)));
+    let mut edges = BTreeMap::new();
+    let mut nb_unassigned = 0;
+
+    let search = EndpointSearch::new(parcellation, affine, &to_voxel, options.search_radius);
+    for (idx, streamline) in tractogram.streamlines.iter().enumerate() {
+        let endpoints = match (streamline.first(), streamline.last()) {
+            (Some(first), Some(last)) => (search.label(first), search.label(last)),
+            _ => (None, None),
+        };
+        let (i, j) = match endpoints {
+            (Some(a), Some(b)) => {
+                let i = labels.binary_search(&a).unwrap();
+                let j = labels.binary_search(&b).unwrap();
+                (i.min(j), i.max(j))
+            }
+            _ => {
+                nb_unassigned += 1;
+                continue;
+            }
+        };
+
+        count[(i, j)] += 1.0;
+        mean_length[(i, j)] += length(streamline);
+        if let (Some(mean_scalar), Some(values)) = (&mut mean_scalar, &values) {
+            mean_scalar[(i, j)] += values[idx];
+        }
+        if options.return_edges {
+            edges.entry((i, j)).or_insert_with(Vec::new).push(idx);
+        }
+    }
+
+    for i in 0..n {
+        for j in i..n {
+            let nb = count[(i, j)];
+            if nb > 0.0 {
+                mean_length[(i, j)] /= nb;
+                if let Some(mean_scalar) = &mut mean_scalar {
+                    mean_scalar[(i, j)] /= nb;
+                }
+            }
+
+            count[(j, i)] = count[(i, j)];
+            mean_length[(j, i)] = mean_length[(i, j)];
+            if let Some(mean_scalar) = &mut mean_scalar {
+                mean_scalar[(j, i)] = mean_scalar[(i, j)];
+            }
+        }
+    }
+
+    let edges = if options.return_edges { Some(edges) } else { None };
+    Ok(Connectome { labels, count, mean_length, mean_scalar, edges, nb_unassigned })
+}
+
+/// Returns one value per streamline: the mean of the per-point scalar `name`, or the property
+/// `name`.
+fn streamline_values(tractogram: &Tractogram, header: &Header, name: &str) -> Result<Vec<f32>> {
+    let nb_streamlines = tractogram.streamlines.len();
+    if let Some(column) = header.scalars_name.iter().position(|n| n == name) {
+        let nb_scalars = header.scalars_name.len();
+        if tractogram.scalars.len() != nb_streamlines
+            || tractogram.scalars.data.len() != tractogram.streamlines.data.len() * nb_scalars
+        {
+            bail!("The scalars of the tractogram don't match the header.");
+        }
+        Ok(tractogram
+            .scalars
+            .iter()
+            .map(|scalars| {
+                let values = scalars.iter().skip(column).step_by(nb_scalars);
+                values.sum::<f32>() / (scalars.len() / nb_scalars).max(1) as f32
+            })
+            .collect())
+    } else if let Some(column) = header.properties_name.iter().position(|n| n == name) {
+        let nb_properties = header.properties_name.len();
+        if tractogram.properties.len() != nb_streamlines
+            || tractogram.properties.data.len() != nb_streamlines * nb_properties
+        {
+            bail!("The properties of the tractogram don't match the header.");
+        }
+        Ok(tractogram.properties.iter().map(|properties| properties[column]).collect())
+    } else {
+        bail!("There's no scalar or property named {:?} in the header.", name)
+    }
+}
+
+/// Find the label of an endpoint, searching in its neighborhood if it's in the background.
+struct EndpointSearch<'a> {
+    parcellation: &'a Array3<i32>,
+    affine: &'a Affine4,
+    to_voxel: &'a Affine4,
+    radius: f32,
+    extent: [isize; 3],
+}
+
+impl<'a> EndpointSearch<'a> {
+    fn new(
+        parcellation: &'a Array3<i32>,
+        affine: &'a Affine4,
+        to_voxel: &'a Affine4,
+        radius: f32,
+    ) -> EndpointSearch<'a> {
+        let mut extent = [0; 3];
+        for (i, e) in extent.iter_mut().enumerate() {
+            let voxel_size = affine.fixed_view::<3, 1>(0, i).norm();
+            *e = (radius / voxel_size).ceil() as isize;
+        }
+        EndpointSearch { parcellation, affine, to_voxel, radius, extent }
+    }
+
+    fn label(&self, p: &Point) -> Option<i32> {
+        let shape = self.parcellation.shape();
+        let voxel = self.to_voxel.transform_point(p);
+        if let Some(idx) = nearest_voxel(shape, &voxel) {
+            let label = self.parcellation[idx];
+            if label != 0 {
+                return Some(label);
+            }
+        }
+        if self.radius <= 0.0 {
+            return None;
+        }
+
+        // Closest labeled voxel center in the search radius
+        let center = voxel.map(|c| c.round() as isize);
+        let mut best: Option<(f32, i32)> = None;
+        for x in center.x - self.extent[0]..=center.x + self.extent[0] {
+            for y in center.y - self.extent[1]..=center.y + self.extent[1] {
+                for z in center.z - self.extent[2]..=center.z + self.extent[2] {
+                    if x < 0 || y < 0 || z < 0 {
+                        continue;
+                    }
+                    let idx = [x as usize, y as usize, z as usize];
+                    let label = match self.parcellation.get(idx) {
+                        Some(&label) if label != 0 => label,
+                        _ => continue,
+                    };
+                    let world =
+                        self.affine.transform_point(&Point::new(x as f32, y as f32, z as f32));
+                    let distance = (world - p).norm();
+                    if distance <= self.radius && best.is_none_or(|(d, _)| distance < d) {
+                        best = Some((distance, label));
+                    }
+                }
+            }
+        }
+        best.map(|(_, label)| label)
+    }
+}
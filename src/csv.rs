@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{streamline::length, ArraySequence, Header, Point, Streamlines, Tractogram};
+
+/// Columns written by `CsvWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvLayout {
+    /// Long format, one row per point: `streamline_id,point_id,x,y,z`, then one column per scalar.
+    /// The columns of a multi-valued scalar are named `name_0`, `name_1`, ...
+    Points,
+
+    /// One row per streamline: `streamline_id,length,n_points`, then one column per property.
+    Streamlines,
+}
+
+/// Write streamlines as a CSV table, e.g., to be loaded in R or pandas.
+///
+/// The streamlines are written one at a time, thus it can be fed by `Reader` to export whole files
+/// without loading them.
+///
+/// ```no_run
+/// # use trk_io::{csv::{CsvLayout, CsvWriter}, Reader};
+/// let reader = Reader::new("full_brain.trk").unwrap();
+/// let mut writer = CsvWriter::new("points.csv", &reader.header, CsvLayout::Points).unwrap();
+/// for (streamline, scalars, properties) in reader {
+///     writer.write(&streamline, &scalars.data, &properties).unwrap();
+/// }
+/// ```
+pub struct CsvWriter {
+    writer: BufWriter<File>,
+    layout: CsvLayout,
+    nb_scalars: usize,
+    next_id: usize,
+}
+
+impl CsvWriter {
+    /// Create a CSV file and write its header line, using the scalars or properties names of
+    /// `header`.
+    pub fn new<P: AsRef<Path>>(path: P, header: &Header, layout: CsvLayout) -> Result<CsvWriter> {
+        let path = path.as_ref();
+        let f = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+        let mut writer = BufWriter::new(f);
+        let (columns, groups) = match layout {
+            CsvLayout::Points => {
+                (["streamline_id", "point_id", "x", "y", "z"].as_slice(), header.scalars_groups())
+            }
+            CsvLayout::Streamlines => {
+                (["streamline_id", "length", "n_points"].as_slice(), header.properties_groups())
+            }
+        };
+        // The columns of the multi-valued scalars and properties are numbered
+        let names = groups.into_iter().flat_map(|(name, range)| match range.len() {
+            1 => vec![quote(&name)],
+            n => (0..n).map(|c| quote(&format!("{}_{}", name, c))).collect(),
+        });
+        let line = columns.iter().map(|c| c.to_string()).chain(names).collect::<Vec<_>>();
+        writeln!(writer, "{}", line.join(","))?;
+        Ok(CsvWriter { writer, layout, nb_scalars: header.scalars_name.len(), next_id: 0 })
+    }
+
+    /// Write a streamline, along with its interleaved per-point `scalars` and its `properties`.
+    /// The streamlines are numbered in the order they are written, starting at 0.
+    pub fn write(
+        &mut self,
+        streamline: &[Point],
+        scalars: &[f32],
+        properties: &[f32],
+    ) -> Result<()> {
+        let id = self.next_id;
+        self.next_id += 1;
+        match self.layout {
+            CsvLayout::Points => {
+                let mut scalars = scalars.chunks(self.nb_scalars.max(1));
+                for (point_id, p) in streamline.iter().enumerate() {
+                    write!(self.writer, "{},{},{},{},{}", id, point_id, p.x, p.y, p.z)?;
+                    if self.nb_scalars > 0 {
+                        match scalars.next() {
+                            Some(values) => write_values(&mut self.writer, values)?,
+                            None => bail!("Missing scalars for streamline {}", id),
+                        }
+                    }
+                    writeln!(self.writer)?;
+                }
+            }
+            CsvLayout::Streamlines => {
+                write!(self.writer, "{},{},{}", id, length(streamline), streamline.len())?;
+                write_values(&mut self.writer, properties)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write all streamlines of `tractogram`.
+    pub fn write_tractogram(&mut self, tractogram: &Tractogram) -> Result<()> {
+        for (streamline, scalars, properties) in tractogram {
+            self.write(streamline, scalars, properties)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+fn write_values<W: Write>(writer: &mut W, values: &[f32]) -> Result<()> {
+    for value in values {
+        write!(writer, ",{}", value)?;
+    }
+    Ok(())
+}
+
+/// Quote a name if it contains a special character.
+fn quote(name: &str) -> String {
+    if name.contains([',', '"', '\n']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+/// Split a CSV line, handling quoted fields.
+fn split_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Group the consecutive columns `name_0`, `name_1`, ..., as written for multi-valued scalars.
+fn group_columns(columns: &[String]) -> Vec<(String, usize)> {
+    let mut groups = vec![];
+    let mut i = 0;
+    while i < columns.len() {
+        let n = match columns[i].strip_suffix("_0") {
+            Some(name) => {
+                let is_next = |c: usize| columns.get(i + c) == Some(&format!("{}_{}", name, c));
+                (1..).find(|&c| !is_next(c)).unwrap()
+            }
+            None => 1,
+        };
+        let name =
+            if n == 1 { columns[i].clone() } else { columns[i][..columns[i].len() - 2].into() };
+        groups.push((name, n));
+        i += n;
+    }
+    groups
+}
+
+/// Read a long format CSV file, as written by `CsvWriter` with `CsvLayout::Points`.
+///
+/// The first columns must be `streamline_id,point_id,x,y,z`; all other columns are read as
+/// per-point scalars. Consecutive columns named `name_0`, `name_1`, ... are read as a single
+/// multi-valued scalar. The rows can be in any order: the streamlines are sorted by order of first
+/// appearance and their points by `point_id`.
+pub fn read_csv<P: AsRef<Path>>(path: P) -> Result<(Header, Tractogram)> {
+    let path = path.as_ref();
+    let text = read_to_string(path).with_context(|| format!("Failed to load {:?}", path))?;
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    let columns = match lines.next() {
+        Some((_, line)) => split_line(line),
+        None => bail!("{:?} is empty", path),
+    };
+    if columns.len() < 5 || columns[..5] != ["streamline_id", "point_id", "x", "y", "z"] {
+        bail!("The first columns of {:?} must be streamline_id,point_id,x,y,z", path);
+    }
+    let mut header = Header::default();
+    for (name, n) in group_columns(&columns[5..]) {
+        header.add_scalar_n(&name, n).with_context(|| format!("Can't add scalar {:?}", name))?;
+    }
+
+    // Points of each streamline, with their point_id and scalars
+    let mut ids = HashMap::new();
+    let mut rows: Vec<Vec<(usize, Point, Vec<f32>)>> = vec![];
+    for (line_number, line) in lines {
+        let error = || format!("Can't parse line {} of {:?}", line_number + 1, path);
+        let fields = line.split(',').map(|f| f.trim()).collect::<Vec<_>>();
+        if fields.len() != columns.len() {
+            bail!("Line {} of {:?} has {} columns", line_number + 1, path, fields.len());
+        }
+        let streamline_id = fields[0].parse::<usize>().with_context(error)?;
+        let point_id = fields[1].parse::<usize>().with_context(error)?;
+        let values = fields[2..]
+            .iter()
+            .map(|f| f.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(error)?;
+        let idx = *ids.entry(streamline_id).or_insert_with(|| {
+            rows.push(vec![]);
+            rows.len() - 1
+        });
+        rows[idx].push((
+            point_id,
+            Point::new(values[0], values[1], values[2]),
+            values[3..].to_vec(),
+        ));
+    }
+
+    let nb_points = rows.iter().map(|r| r.len()).sum();
+    let mut streamlines = Streamlines::with_capacity(nb_points);
+    let mut scalars = ArraySequence::with_capacity(nb_points * header.scalars_name.len());
+    for mut points in rows {
+        points.sort_by_key(|(point_id, _, _)| *point_id);
+        streamlines.extend(points.iter().map(|(_, p, _)| *p));
+        if !header.scalars_name.is_empty() {
+            scalars.extend(points.into_iter().flat_map(|(_, _, values)| values));
+        }
+    }
+    header.nb_streamlines = streamlines.len();
+    Ok((header, Tractogram::new(streamlines, scalars, ArraySequence::empty())))
+}
@@ -0,0 +1,125 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    streamline::{mdf, resample},
+    Point, Points, Streamlines, Tractogram,
+};
+
+/// Options of `unique_indices` and `deduplicate`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DedupOptions {
+    /// Also remove the streamlines whose MDF distance to a kept streamline is below this threshold,
+    /// in mm. Only the exact duplicates are removed if `None`. Must be positive.
+    pub mdf_threshold: Option<f32>,
+
+    /// Number of points used to compute the MDF distances. Must be at least 2.
+    pub nb_points: usize,
+}
+
+impl Default for DedupOptions {
+    fn default() -> Self {
+        DedupOptions { mdf_threshold: None, nb_points: 12 }
+    }
+}
+
+/// Returns the indices of the streamlines to keep, in order, that is, the first occurrence of each
+/// group of duplicates.
+///
+/// Exact duplicates have the same points, bit for bit, in the same order. Near-duplicates are
+/// only searched among the streamlines whose first point is close to an endpoint of a kept
+/// streamline, in the neighboring cells of a grid of `mdf_threshold` mm.
+///
+/// Fails if `options` is invalid.
+pub fn unique_indices(streamlines: &Streamlines, options: &DedupOptions) -> Result<Vec<usize>> {
+    if options.nb_points < 2 {
+        bail!("The MDF distances need at least 2 points. Got {}", options.nb_points);
+    }
+    if let Some(threshold) = options.mdf_threshold
+        && (threshold.is_nan() || threshold <= 0.0)
+    {
+        bail!("The MDF threshold must be positive. Got {}", threshold);
+    }
+
+    let mut kept = vec![];
+    let mut hashes: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (idx, streamline) in streamlines.iter().enumerate() {
+        let bits = streamline.iter().flat_map(|p| [p.x, p.y, p.z]).map(f32::to_bits);
+        let bits = bits.collect::<Vec<_>>();
+        let mut hasher = DefaultHasher::new();
+        bits.hash(&mut hasher);
+        let same = hashes.entry(hasher.finish()).or_default();
+        if !same.iter().any(|&other| same_bits(&streamlines[other], &bits)) {
+            same.push(idx);
+            kept.push(idx);
+        }
+    }
+
+    Ok(match options.mdf_threshold {
+        Some(threshold) => remove_near_duplicates(streamlines, kept, threshold, options.nb_points),
+        None => kept,
+    })
+}
+
+/// Returns a new tractogram without the duplicated streamlines, along with the indices of the kept
+/// streamlines. See `unique_indices`.
+pub fn deduplicate(
+    tractogram: &Tractogram,
+    options: &DedupOptions,
+) -> Result<(Tractogram, Vec<usize>)> {
+    let indices = unique_indices(&tractogram.streamlines, options)?;
+    Ok((tractogram.select(&indices), indices))
+}
+
+fn same_bits(streamline: &[Point], bits: &[u32]) -> bool {
+    streamline.len() * 3 == bits.len()
+        && streamline
+            .iter()
+            .flat_map(|p| [p.x, p.y, p.z])
+            .map(f32::to_bits)
+            .eq(bits.iter().cloned())
+}
+
+fn remove_near_duplicates(
+    streamlines: &Streamlines,
+    candidates: Vec<usize>,
+    threshold: f32,
+    nb_points: usize,
+) -> Vec<usize> {
+    let cell = |p: &Point| (p.coords / threshold).map(|c| c.floor() as i32);
+    let mut kept = vec![];
+    let mut resampled: Vec<Points> = vec![];
+    let mut grid: HashMap<[i32; 3], Vec<usize>> = HashMap::new();
+    for idx in candidates {
+        let streamline = &streamlines[idx];
+        if streamline.is_empty() {
+            kept.push(idx);
+            continue;
+        }
+        let points = resample(streamline, nb_points);
+
+        // A near-duplicate may be flipped, thus its first point is close to either endpoint
+        let center = cell(&points[0]);
+        let mut neighbors: Vec<usize> = vec![];
+        for offset in (0..27).map(|i| [i % 3 - 1, i / 3 % 3 - 1, i / 9 - 1]) {
+            let key = [center.x + offset[0], center.y + offset[1], center.z + offset[2]];
+            neighbors.extend(grid.get(&key).into_iter().flatten());
+        }
+        if neighbors.iter().any(|&other| mdf(&points, &resampled[other]) < threshold) {
+            continue;
+        }
+
+        let (first, last) = (cell(&points[0]), cell(&points[nb_points - 1]));
+        grid.entry([first.x, first.y, first.z]).or_default().push(resampled.len());
+        if last != first {
+            grid.entry([last.x, last.y, last.z]).or_default().push(resampled.len());
+        }
+        resampled.push(points);
+        kept.push(idx);
+    }
+    kept
+}
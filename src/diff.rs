@@ -0,0 +1,378 @@
+use std::{collections::HashMap, ops::Range, path::Path};
+
+use anyhow::Result;
+
+use crate::{tractogram::RefTractogramItem, CHeader, Header, Point, Reader, Tractogram};
+
+/// Options of `diff_trk` and `diff_tractograms`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Maximal distance between two corresponding points, in mm.
+    pub distance_tolerance: f32,
+
+    /// Maximal absolute difference between two scalars, properties or float header fields.
+    pub value_tolerance: f32,
+
+    /// If `true`, the streamline `i` is compared to the streamline `i`. Otherwise, each streamline
+    /// is matched to any equivalent streamline of the other tractogram.
+    pub ordered: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions { distance_tolerance: 1e-4, value_tolerance: 1e-4, ordered: true }
+    }
+}
+
+/// A difference between two tractograms. `left` and `right` are the indices of the streamlines.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    /// A field of the trk headers, formatted with `Debug`.
+    Header {
+        field: &'static str,
+        left: String,
+        right: String,
+    },
+
+    /// The names or the number of values of the scalars or properties.
+    Names {
+        kind: &'static str,
+        left: Vec<String>,
+        right: Vec<String>,
+    },
+
+    NbStreamlines {
+        left: usize,
+        right: usize,
+    },
+    NbPoints {
+        left: usize,
+        right: usize,
+        nb_left: usize,
+        nb_right: usize,
+    },
+
+    /// The maximal distance between two corresponding points is above the tolerance.
+    Points {
+        left: usize,
+        right: usize,
+        max_distance: f32,
+    },
+
+    /// The maximal difference of the values of a scalar is above the tolerance.
+    Scalar {
+        left: usize,
+        right: usize,
+        name: String,
+        max_difference: f32,
+    },
+
+    /// The maximal difference of the values of a property is above the tolerance.
+    Property {
+        left: usize,
+        right: usize,
+        name: String,
+        max_difference: f32,
+    },
+
+    /// In unordered mode, the left streamline has no equivalent in the right tractogram.
+    MissingInRight {
+        left: usize,
+    },
+
+    /// In unordered mode, the right streamline has no equivalent in the left tractogram.
+    MissingInLeft {
+        right: usize,
+    },
+}
+
+/// All differences found between two tractograms, in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiffReport {
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl DiffReport {
+    pub fn is_identical(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&Mismatch> {
+        self.mismatches.first()
+    }
+}
+
+/// Compare the headers, field by field, and the streamlines of two trk files.
+pub fn diff_trk<P: AsRef<Path>, Q: AsRef<Path>>(
+    left: P,
+    right: Q,
+    options: &DiffOptions,
+) -> Result<DiffReport> {
+    let mut left = Reader::new(left)?;
+    let mut right = Reader::new(right)?;
+    let (left_tractogram, right_tractogram) = (left.tractogram(), right.tractogram());
+
+    let mut report = DiffReport {
+        mismatches: diff_headers(
+            &left.header.raw_header(),
+            &right.header.raw_header(),
+            options.value_tolerance,
+        ),
+    };
+    let comparer = Comparer::new(&left.header, &right.header, options);
+    comparer.diff(&left_tractogram, &right_tractogram, &mut report);
+    Ok(report)
+}
+
+/// Compare two tractograms and the names of their scalars and properties.
+///
+/// Only the scalars and properties present on both sides, with the same number of values, are
+/// compared. The others are reported as a `Mismatch::Names`.
+pub fn diff_tractograms(
+    left_header: &Header,
+    left: &Tractogram,
+    right_header: &Header,
+    right: &Tractogram,
+    options: &DiffOptions,
+) -> DiffReport {
+    let mut report = DiffReport::default();
+    let groups = [
+        ("scalars", left_header.scalars_groups(), right_header.scalars_groups()),
+        ("properties", left_header.properties_groups(), right_header.properties_groups()),
+    ];
+    for (kind, left_groups, right_groups) in groups {
+        if left_groups != right_groups {
+            let names = |groups: Vec<(String, Range<usize>)>| {
+                groups.into_iter().map(|(name, range)| format!("{}[{}]", name, range.len()))
+            };
+            report.mismatches.push(Mismatch::Names {
+                kind,
+                left: names(left_groups).collect(),
+                right: names(right_groups).collect(),
+            });
+        }
+    }
+    Comparer::new(left_header, right_header, options).diff(left, right, &mut report);
+    report
+}
+
+/// Compare two trk headers field by field. The float fields are compared with `tolerance`.
+pub fn diff_headers(left: &CHeader, right: &CHeader, tolerance: f32) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    let mut exact = |field, l: String, r: String| {
+        if l != r {
+            mismatches.push(Mismatch::Header { field, left: l, right: r });
+        }
+    };
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string();
+    exact("id_string", text(&left.id_string), text(&right.id_string));
+    exact("dim", format!("{:?}", left.dim), format!("{:?}", right.dim));
+    exact("n_scalars", format!("{:?}", left.n_scalars), format!("{:?}", right.n_scalars));
+    exact(
+        "scalar_name",
+        format!("{:?}", left.get_scalars_groups()),
+        format!("{:?}", right.get_scalars_groups()),
+    );
+    exact("n_properties", format!("{:?}", left.n_properties), format!("{:?}", right.n_properties));
+    exact(
+        "property_name",
+        format!("{:?}", left.get_properties_groups()),
+        format!("{:?}", right.get_properties_groups()),
+    );
+    exact("reserved", format!("{:?}", left.reserved), format!("{:?}", right.reserved));
+    exact("voxel_order", text(&left.voxel_order), text(&right.voxel_order));
+    exact("pad2", format!("{:?}", left.pad2), format!("{:?}", right.pad2));
+    exact("pad1", format!("{:?}", left.pad1), format!("{:?}", right.pad1));
+    let invert = |c: &CHeader| format!("{:?}", [c.invert_x, c.invert_y, c.invert_z]);
+    exact("invert", invert(left), invert(right));
+    let swap = |c: &CHeader| format!("{:?}", [c.swap_x, c.swap_y, c.swap_z]);
+    exact("swap", swap(left), swap(right));
+    exact("n_count", format!("{:?}", left.n_count), format!("{:?}", right.n_count));
+    exact("version", format!("{:?}", left.version), format!("{:?}", right.version));
+    exact("hdr_size", format!("{:?}", left.hdr_size), format!("{:?}", right.hdr_size));
+
+    let floats = [
+        ("voxel_size", &left.voxel_size[..], &right.voxel_size[..]),
+        ("origin", &left.origin[..], &right.origin[..]),
+        ("vox_to_ras", &left.vox_to_ras[..], &right.vox_to_ras[..]),
+        (
+            "image_orientation_patient",
+            &left.image_orientation_patient[..],
+            &right.image_orientation_patient[..],
+        ),
+    ];
+    for (field, l, r) in floats {
+        if l.iter().zip(r).any(|(&l, &r)| difference(l, r) > tolerance) {
+            let (left, right) = (format!("{:?}", l), format!("{:?}", r));
+            mismatches.push(Mismatch::Header { field, left, right });
+        }
+    }
+    mismatches
+}
+
+/// Absolute difference of two values, where two NaN are equal.
+fn difference(a: f32, b: f32) -> f32 {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => 0.0,
+        (false, false) => (a - b).abs(),
+        _ => f32::INFINITY,
+    }
+}
+
+/// Groups present on both sides, as `(name, left start, right start, number of values)`.
+struct Columns {
+    groups: Vec<(String, usize, usize, usize)>,
+    left_stride: usize,
+    right_stride: usize,
+}
+
+impl Columns {
+    fn new(left: Vec<(String, Range<usize>)>, right: Vec<(String, Range<usize>)>) -> Columns {
+        let left_stride = left.last().map_or(0, |(_, range)| range.end);
+        let right_stride = right.last().map_or(0, |(_, range)| range.end);
+        let groups = left
+            .into_iter()
+            .filter_map(|(name, l)| {
+                let (_, r) = right.iter().find(|(n, r)| *n == name && r.len() == l.len())?;
+                Some((name, l.start, r.start, l.len()))
+            })
+            .collect();
+        Columns { groups, left_stride, right_stride }
+    }
+
+    /// Returns the name and maximal difference of the groups above `tolerance`. NaN are equal.
+    fn diff(
+        &self,
+        left: &[f32],
+        right: &[f32],
+        nb_tuples: usize,
+        tolerance: f32,
+    ) -> Vec<(String, f32)> {
+        let mut mismatches = vec![];
+        for (name, left_start, right_start, nb) in &self.groups {
+            let mut max_difference = 0.0f32;
+            for tuple in 0..nb_tuples {
+                let l = &left[tuple * self.left_stride + left_start..][..*nb];
+                let r = &right[tuple * self.right_stride + right_start..][..*nb];
+                for (&l, &r) in l.iter().zip(r) {
+                    max_difference = max_difference.max(difference(l, r));
+                }
+            }
+            if max_difference > tolerance {
+                mismatches.push((name.clone(), max_difference));
+            }
+        }
+        mismatches
+    }
+}
+
+struct Comparer<'a> {
+    scalars: Columns,
+    properties: Columns,
+    options: &'a DiffOptions,
+}
+
+impl<'a> Comparer<'a> {
+    fn new(left: &Header, right: &Header, options: &'a DiffOptions) -> Comparer<'a> {
+        Comparer {
+            scalars: Columns::new(left.scalars_groups(), right.scalars_groups()),
+            properties: Columns::new(left.properties_groups(), right.properties_groups()),
+            options,
+        }
+    }
+
+    fn diff(&self, left: &Tractogram, right: &Tractogram, report: &mut DiffReport) {
+        let (nb_left, nb_right) = (left.streamlines.len(), right.streamlines.len());
+        if nb_left != nb_right {
+            report.mismatches.push(Mismatch::NbStreamlines { left: nb_left, right: nb_right });
+        }
+
+        if self.options.ordered {
+            for idx in 0..nb_left.min(nb_right) {
+                let mismatches = self.diff_items(idx, left.item(idx), idx, right.item(idx));
+                report.mismatches.extend(mismatches);
+            }
+        } else {
+            self.match_items(left, right, report);
+        }
+    }
+
+    /// Match each left streamline to the first unmatched equivalent right streamline.
+    fn match_items(&self, left: &Tractogram, right: &Tractogram, report: &mut DiffReport) {
+        // Candidates are grouped by number of points and sorted by the x of their first point
+        let first_x = |streamline: &[Point]| streamline.first().map_or(0.0, |p| p.x);
+        let mut candidates: HashMap<usize, Vec<(f32, usize)>> = HashMap::new();
+        for (idx, streamline) in right.streamlines.iter().enumerate() {
+            candidates.entry(streamline.len()).or_default().push((first_x(streamline), idx));
+        }
+        for same_length in candidates.values_mut() {
+            same_length.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+
+        let tolerance = self.options.distance_tolerance;
+        let mut matched = vec![false; right.streamlines.len()];
+        for (idx, item) in left.into_iter().enumerate() {
+            let x = first_x(item.0);
+            let found = candidates.get(&item.0.len()).and_then(|same_length| {
+                let start = same_length.partition_point(|&(other, _)| other < x - tolerance);
+                same_length[start..]
+                    .iter()
+                    .take_while(|&&(other, _)| other <= x + tolerance)
+                    .map(|&(_, other)| other)
+                    .find(|&other| {
+                        !matched[other]
+                            && self.diff_items(idx, item, other, right.item(other)).is_empty()
+                    })
+            });
+            match found {
+                Some(other) => matched[other] = true,
+                None => report.mismatches.push(Mismatch::MissingInRight { left: idx }),
+            }
+        }
+        for idx in (0..matched.len()).filter(|&idx| !matched[idx]) {
+            report.mismatches.push(Mismatch::MissingInLeft { right: idx });
+        }
+    }
+
+    fn diff_items(
+        &self,
+        left: usize,
+        (left_points, left_scalars, left_properties): RefTractogramItem,
+        right: usize,
+        (right_points, right_scalars, right_properties): RefTractogramItem,
+    ) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+        let (nb_left, nb_right) = (left_points.len(), right_points.len());
+        if nb_left != nb_right {
+            mismatches.push(Mismatch::NbPoints { left, right, nb_left, nb_right });
+        } else {
+            let max_distance =
+                left_points.iter().zip(right_points).map(|(l, r)| (l - r).norm()).fold(
+                    0.0,
+                    |max, distance| {
+                        if distance > max || distance.is_nan() {
+                            distance
+                        } else {
+                            max
+                        }
+                    },
+                );
+            if max_distance.is_nan() || max_distance > self.options.distance_tolerance {
+                mismatches.push(Mismatch::Points { left, right, max_distance });
+            }
+
+            let tolerance = self.options.value_tolerance;
+            let scalars = self.scalars.diff(left_scalars, right_scalars, nb_left, tolerance);
+            for (name, max_difference) in scalars {
+                mismatches.push(Mismatch::Scalar { left, right, name, max_difference });
+            }
+        }
+
+        let tolerance = self.options.value_tolerance;
+        let properties = self.properties.diff(left_properties, right_properties, 1, tolerance);
+        for (name, max_difference) in properties {
+            mismatches.push(Mismatch::Property { left, right, name, max_difference });
+        }
+        mismatches
+    }
+}
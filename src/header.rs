@@ -1,9 +1,11 @@
-use std::{fs::File, io::BufReader, path::Path};
+use std::{fs::File, io::BufReader, ops::Range, path::Path};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use byteorder::WriteBytesExt;
 #[cfg(feature = "nifti_images")]
 use nifti::NiftiHeader;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::{
     affine::get_affine_and_translation,
@@ -11,7 +13,11 @@ use crate::{
     Affine, Affine4, Translation,
 };
 
+/// With the `serde` feature, a `Header` is serialized as its `CHeader`, with `n_count` set to
+/// `nb_streamlines`. The other fields are computed from it when deserializing, after checking it
+/// with `CHeader::validate`.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(into = "CHeader"))]
 pub struct Header {
     c_header: CHeader,
     pub affine4_to_rasmm: Affine4,
@@ -28,6 +34,16 @@ impl Header {
     /// Build a trk header using the affine from a Nifti header.
     pub fn from_nifti(h: &NiftiHeader) -> Header {
         let c_header = CHeader::from_nifti(h.dim, h.pixdim, h.srow_x, h.srow_y, h.srow_z);
+        Header::from_c_header(c_header)
+    }
+
+    /// Build a trk header for a reference image of shape `dim`, whose voxel indices are mapped to
+    /// RAS+ mm by `affine`.
+    pub fn from_affine(affine: &Affine4, dim: [i16; 3], voxel_size: [f32; 3]) -> Header {
+        Header::from_c_header(CHeader::from_affine(affine, dim, voxel_size))
+    }
+
+    fn from_c_header(c_header: CHeader) -> Header {
         let affine4 = c_header.get_affine_to_rasmm();
         let (affine, translation) = get_affine_and_translation(&affine4);
         Header {
@@ -59,22 +75,7 @@ impl Header {
     /// that the `reader` is currently at the start of the trk header.
     pub fn read(reader: &mut BufReader<File>) -> Result<(Header, Endianness)> {
         let (c_header, endianness) = CHeader::read(reader)?;
-        let affine4 = c_header.get_affine_to_rasmm();
-        let (affine, translation) = get_affine_and_translation(&affine4);
-        let nb_streamlines = c_header.n_count as usize;
-        let scalars_name = c_header.get_scalars_name();
-        let properties_name = c_header.get_properties_name();
-
-        let header = Header {
-            c_header,
-            affine4_to_rasmm: affine4,
-            affine_to_rasmm: affine,
-            translation,
-            nb_streamlines,
-            scalars_name,
-            properties_name,
-        };
-        Ok((header, endianness))
+        Ok((Header::from(c_header), endianness))
     }
 
     /// Clear all scalars and properties from `self`.
@@ -101,39 +102,148 @@ impl Header {
         self.copy_properties(rhs);
     }
 
-    /// Clear all scalars from `self` and copy scalars from `rhs`.
+    /// Clear all scalars from `self` and copy scalars from `rhs`, keeping their grouping.
     pub fn copy_scalars(&mut self, rhs: &Self) {
         self.clear_scalars();
-        for scalar in &rhs.scalars_name {
-            self.add_scalar(scalar).unwrap(); // Can't fail
+        for (scalar, range) in rhs.scalars_groups() {
+            self.add_scalar_n(&scalar, range.len()).unwrap(); // Can't fail
         }
     }
 
-    /// Clear all properties from `self` and copy properties from `rhs`.
+    /// Clear all properties from `self` and copy properties from `rhs`, keeping their grouping.
     pub fn copy_properties(&mut self, rhs: &Self) {
         self.clear_properties();
-        for property in &rhs.properties_name {
-            self.add_property(property).unwrap(); // Can't fail
+        for (property, range) in rhs.properties_groups() {
+            self.add_property_n(&property, range.len()).unwrap(); // Can't fail
         }
     }
 
     pub fn add_scalar(&mut self, name: &str) -> Result<()> {
-        self.c_header.add_scalar(name)?;
-        self.scalars_name.push(name.to_string());
+        self.add_scalar_n(name, 1)
+    }
+
+    /// Add a scalar of `n` values per point, e.g., a RGB color. It uses only one of the 10 names
+    /// available in the trk header, but `n` columns in `scalars_name`.
+    pub fn add_scalar_n(&mut self, name: &str, n: usize) -> Result<()> {
+        self.c_header.add_scalar_n(name, n)?;
+        self.scalars_name.extend(std::iter::repeat_n(name.to_string(), n));
         Ok(())
     }
 
     pub fn add_property(&mut self, name: &str) -> Result<()> {
-        self.c_header.add_property(name)?;
-        self.properties_name.push(name.to_string());
+        self.add_property_n(name, 1)
+    }
+
+    /// Add a property of `n` values per streamline. It uses only one of the 10 names available in
+    /// the trk header, but `n` columns in `properties_name`.
+    pub fn add_property_n(&mut self, name: &str, n: usize) -> Result<()> {
+        self.c_header.add_property_n(name, n)?;
+        self.properties_name.extend(std::iter::repeat_n(name.to_string(), n));
         Ok(())
     }
 
+    /// Remove the scalar `name`, with all its columns, and returns the range of the removed columns.
+    pub fn remove_scalar(&mut self, name: &str) -> Result<Range<usize>> {
+        let groups = self.scalars_groups();
+        let Some(idx) = groups.iter().position(|(n, _)| n == name) else {
+            bail!("There's no scalar named {:?} in the header.", name);
+        };
+        self.clear_scalars();
+        for (i, (scalar, range)) in groups.iter().enumerate() {
+            if i != idx {
+                self.add_scalar_n(scalar, range.len()).unwrap(); // Can't fail
+            }
+        }
+        Ok(groups[idx].1.clone())
+    }
+
+    /// Remove the property `name`, with all its columns, and returns the range of the removed
+    /// columns.
+    pub fn remove_property(&mut self, name: &str) -> Result<Range<usize>> {
+        let groups = self.properties_groups();
+        let Some(idx) = groups.iter().position(|(n, _)| n == name) else {
+            bail!("There's no property named {:?} in the header.", name);
+        };
+        self.clear_properties();
+        for (i, (property, range)) in groups.iter().enumerate() {
+            if i != idx {
+                self.add_property_n(property, range.len()).unwrap(); // Can't fail
+            }
+        }
+        Ok(groups[idx].1.clone())
+    }
+
+    /// Returns the name of each scalar, with the range of its columns in the interleaved scalars.
+    pub fn scalars_groups(&self) -> Vec<(String, Range<usize>)> {
+        groups(self.c_header.get_scalars_groups(), &self.scalars_name)
+    }
+
+    /// Returns the name of each property, with the range of its columns in the properties.
+    pub fn properties_groups(&self) -> Vec<(String, Range<usize>)> {
+        groups(self.c_header.get_properties_groups(), &self.properties_name)
+    }
+
     pub fn write<W: WriteBytesExt>(&self, writer: &mut W) -> Result<()> {
         Ok(self.c_header.write(writer)?)
     }
 }
 
+/// Convert the `(name, nb_values)` groups to column ranges. The columns that are not described by
+/// the groups, e.g., because the names are empty in the trk header, are returned one by one.
+fn groups(groups: Vec<(String, usize)>, names: &[String]) -> Vec<(String, Range<usize>)> {
+    let mut start = 0;
+    let mut ranges = vec![];
+    for (name, n) in groups {
+        if start + n > names.len() {
+            break;
+        }
+        ranges.push((name, start..start + n));
+        start += n;
+    }
+    for (column, name) in names.iter().enumerate().skip(start) {
+        ranges.push((name.clone(), column..column + 1));
+    }
+    ranges
+}
+
+impl From<CHeader> for Header {
+    fn from(c_header: CHeader) -> Header {
+        let affine4 = c_header.get_affine_to_rasmm();
+        let (affine, translation) = get_affine_and_translation(&affine4);
+        let nb_streamlines = c_header.n_count as usize;
+        let scalars_name = c_header.get_scalars_name();
+        let properties_name = c_header.get_properties_name();
+
+        Header {
+            c_header,
+            affine4_to_rasmm: affine4,
+            affine_to_rasmm: affine,
+            translation,
+            nb_streamlines,
+            scalars_name,
+            properties_name,
+        }
+    }
+}
+
+// `serde(try_from)` can't be used because `From<CHeader>` is already implemented
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Header {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Header, D::Error> {
+        let c_header = CHeader::deserialize(d)?;
+        c_header.validate().map_err(serde::de::Error::custom)?;
+        Ok(Header::from(c_header))
+    }
+}
+
+impl From<Header> for CHeader {
+    fn from(header: Header) -> CHeader {
+        let mut c_header = header.c_header;
+        c_header.n_count = header.nb_streamlines as i32;
+        c_header
+    }
+}
+
 impl Default for Header {
     fn default() -> Header {
         Header {
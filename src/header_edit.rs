@@ -0,0 +1,123 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{cheader::Endianness, CHeader, Header, Reader, Writer};
+
+/// How the points are treated when the header is modified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditMode {
+    /// The points are copied as they are on disk, thus their position in the world changes if the
+    /// affine to RAS+ mm changes. Use it to fix a wrong header.
+    Reinterpret,
+
+    /// The points are transformed to keep the same RAS+ mm coordinates in the new header.
+    PreserveWorld,
+}
+
+/// Copy the trk file `input` to `output` with the header modified by `edit`, and returns the new
+/// header.
+///
+/// `edit` receives the header as read on disk. It can't modify the number of scalars or properties
+/// because the data is not modified, but it can rename them. `output` must be a different file than
+/// `input`; use `edit_trk_in_place` to modify a file.
+///
+/// ```no_run
+/// # use trk_io::header_edit::{edit_trk, EditMode};
+/// // The dimensions were saved in the wrong order
+/// edit_trk("wrong.trk", "fixed.trk", EditMode::Reinterpret, |header| header.dim.swap(0, 2))
+///     .unwrap();
+/// ```
+pub fn edit_trk<P, Q, F>(input: P, output: Q, mode: EditMode, edit: F) -> Result<Header>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnOnce(&mut CHeader),
+{
+    // The writer would truncate the input before it's read
+    if let (Ok(input), Ok(output)) = (input.as_ref().canonicalize(), output.as_ref().canonicalize())
+        && input == output
+    {
+        bail!("Can't write the edited file over its input {:?}, use edit_trk_in_place", input);
+    }
+
+    let reader = Reader::new(&input)?;
+    let mut c_header = reader.header.raw_header();
+    edit(&mut c_header);
+    check_edit(&reader.header.raw_header(), &c_header)?;
+    let mut header = Header::from(c_header);
+    if mode == EditMode::PreserveWorld && header.affine4_to_rasmm.try_inverse().is_none() {
+        bail!("The affine to RAS+ mm of the new header is not invertible");
+    }
+
+    let mut nb_streamlines = 0;
+    match mode {
+        EditMode::Reinterpret => {
+            let mut writer = Writer::new(&output, Some(&header))?.raw();
+            for item in reader.raw() {
+                writer.write(item);
+                nb_streamlines += 1;
+            }
+        }
+        EditMode::PreserveWorld => {
+            let mut writer = Writer::new(&output, Some(&header))?;
+            for item in reader {
+                writer.write(item);
+                nb_streamlines += 1;
+            }
+        }
+    }
+    header.nb_streamlines = nb_streamlines;
+    Ok(header)
+}
+
+/// Modify the header of the trk file `path` with `edit`, without touching the points, and returns
+/// the new header. Only the first 1000 bytes of the file are written.
+///
+/// This is the in-place version of `EditMode::Reinterpret`. Big endian files are not supported.
+pub fn edit_trk_in_place<P, F>(path: P, edit: F) -> Result<CHeader>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut CHeader),
+{
+    let path = path.as_ref();
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to load {:?}", path))?;
+    let mut reader = BufReader::new(file);
+    let (old, endianness) = CHeader::read(&mut reader)?;
+    if let Endianness::Big = endianness {
+        bail!("Can't modify the header of a big endian file in place: {:?}", path);
+    }
+    let mut c_header = old.clone();
+    edit(&mut c_header);
+    check_edit(&old, &c_header)?;
+
+    let mut bytes = vec![];
+    c_header.write(&mut bytes)?;
+    let mut file: File = reader.into_inner();
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&bytes).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(c_header)
+}
+
+/// Fails if `new` can't describe the same data as `old`.
+fn check_edit(old: &CHeader, new: &CHeader) -> Result<()> {
+    new.validate().context("Invalid edited header")?;
+    if new.hdr_size != old.hdr_size {
+        bail!("Can't modify hdr_size");
+    }
+    if new.n_scalars != old.n_scalars || new.n_properties != old.n_properties {
+        bail!("Can't modify the number of scalars or properties without modifying the data");
+    }
+    if new.version != 1 && new.version != 2 {
+        bail!("The version must be 1 or 2, not {}", new.version);
+    }
+    Ok(())
+}
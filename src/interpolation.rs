@@ -0,0 +1,49 @@
+use ndarray::{ArrayBase, Data, Ix3};
+
+use crate::Point;
+
+/// Trilinear interpolation of `data` at the voxel coordinates `p`.
+///
+/// Voxel coordinates refer to the center of the voxels, as in NIfTI. Points outside of the volume
+/// are clamped to its border.
+pub fn trilinear<S>(data: &ArrayBase<S, Ix3>, p: &Point) -> f32
+where
+    S: Data<Elem = f32>,
+{
+    let shape = data.shape();
+    let mut low = [0usize; 3];
+    let mut high = [0usize; 3];
+    let mut ratio = [0.0f32; 3];
+    for i in 0..3 {
+        let max = (shape[i] - 1) as f32;
+        let c = p[i].clamp(0.0, max);
+        let floor = c.floor();
+        low[i] = floor as usize;
+        high[i] = (low[i] + 1).min(shape[i] - 1);
+        ratio[i] = c - floor;
+    }
+
+    let mut value = 0.0;
+    for (dx, x) in [(1.0 - ratio[0], low[0]), (ratio[0], high[0])] {
+        for (dy, y) in [(1.0 - ratio[1], low[1]), (ratio[1], high[1])] {
+            for (dz, z) in [(1.0 - ratio[2], low[2]), (ratio[2], high[2])] {
+                value += dx * dy * dz * data[(x, y, z)];
+            }
+        }
+    }
+    value
+}
+
+/// Returns the index of the voxel containing the voxel coordinates `p`, or `None` if `p` is
+/// outside of a volume of shape `shape`.
+pub fn nearest_voxel(shape: &[usize], p: &Point) -> Option<[usize; 3]> {
+    let mut idx = [0; 3];
+    for i in 0..3 {
+        let c = p[i].round();
+        if !(c >= 0.0 && c < shape[i] as f32) {
+            return None;
+        }
+        idx[i] = c as usize;
+    }
+    Some(idx)
+}
@@ -1,21 +1,50 @@
 pub mod affine;
 mod array_sequence;
 mod cheader;
+#[cfg(feature = "nifti_images")]
+pub mod connectivity;
+pub mod csv;
+pub mod dedup;
+pub mod diff;
 mod header;
+pub mod header_edit;
+#[cfg(feature = "nifti_images")]
+pub mod interpolation;
+mod mat4;
+pub mod merge;
+pub mod mesh;
 pub mod orientation;
+pub mod profile;
 mod reader;
+pub mod smoothing;
+pub mod split;
+mod stateful_tractogram;
+pub mod stats;
+pub mod streamline;
+#[cfg(feature = "subsample")]
+pub mod subsample;
 mod tractogram;
+pub mod transform;
+pub mod tt;
+pub mod validation;
 mod vs_reader;
+pub mod vtk;
+pub mod vtp;
+#[cfg(feature = "nifti_images")]
+pub mod warp;
 mod writer;
 
 use byteorder::LittleEndian;
 use nalgebra::{Matrix3, Matrix4, Vector3};
 
-pub use array_sequence::ArraySequence;
+pub use array_sequence::{ArraySequence, ArraySequenceView};
 pub use cheader::CHeader;
 pub use header::Header;
 pub use reader::{Reader, StreamlinesIter};
-pub use tractogram::{Point, Points, Streamlines, Tractogram, TractogramItem};
+pub use stateful_tractogram::{Origin, Space, StatefulTractogram};
+pub use tractogram::{
+    Point, Points, Streamlines, StridedView, Tractogram, TractogramItem, TractogramView,
+};
 pub use vs_reader::VoxelSpaceReader;
 pub use writer::Writer;
 
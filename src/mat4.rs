@@ -0,0 +1,228 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+
+/// Data of a MATLAB v4 variable, in column major order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MatData {
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+    I32(Vec<i32>),
+    I16(Vec<i16>),
+    U16(Vec<u16>),
+    U8(Vec<u8>),
+}
+
+impl MatData {
+    pub fn to_f64(&self) -> Vec<f64> {
+        match self {
+            MatData::F64(v) => v.clone(),
+            MatData::F32(v) => v.iter().map(|&e| e as f64).collect(),
+            MatData::I32(v) => v.iter().map(|&e| e as f64).collect(),
+            MatData::I16(v) => v.iter().map(|&e| e as f64).collect(),
+            MatData::U16(v) => v.iter().map(|&e| e as f64).collect(),
+            MatData::U8(v) => v.iter().map(|&e| e as f64).collect(),
+        }
+    }
+}
+
+/// A variable of a MATLAB v4 (.mat) file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatVariable {
+    pub name: String,
+    pub rows: usize,
+    pub cols: usize,
+    pub data: MatData,
+}
+
+/// Read all variables of a MATLAB v4 file.
+///
+/// Each variable can be stored in little or big endian. Imaginary parts are ignored.
+pub fn read_mat4<R: Read>(reader: &mut R) -> Result<Vec<MatVariable>> {
+    let mut variables = vec![];
+    loop {
+        let mut header = [0u8; 20];
+        match reader.read_exact(&mut header[..4]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && !variables.is_empty() => break,
+            Err(e) => return Err(e),
+        }
+        reader.read_exact(&mut header[4..])?;
+
+        // The type is < 5000 in both endianness, but it's a much bigger number when read in the
+        // wrong one.
+        let variable = if (0..5000).contains(&LittleEndian::read_i32(&header)) {
+            read_variable::<LittleEndian, R>(reader, &header)?
+        } else {
+            read_variable::<BigEndian, R>(reader, &header)?
+        };
+        variables.push(variable);
+    }
+    Ok(variables)
+}
+
+/// Write all `variables` as a little endian MATLAB v4 file.
+pub fn write_mat4<W: Write>(writer: &mut W, variables: &[MatVariable]) -> Result<()> {
+    for variable in variables {
+        let precision = match variable.data {
+            MatData::F64(_) => 0,
+            MatData::F32(_) => 1,
+            MatData::I32(_) => 2,
+            MatData::I16(_) => 3,
+            MatData::U16(_) => 4,
+            MatData::U8(_) => 5,
+        };
+        writer.write_i32::<LittleEndian>(precision * 10)?;
+        writer.write_i32::<LittleEndian>(variable.rows as i32)?;
+        writer.write_i32::<LittleEndian>(variable.cols as i32)?;
+        writer.write_i32::<LittleEndian>(0)?;
+        writer.write_i32::<LittleEndian>(variable.name.len() as i32 + 1)?;
+        writer.write_all(variable.name.as_bytes())?;
+        writer.write_u8(0)?;
+        match &variable.data {
+            MatData::F64(v) => v.iter().try_for_each(|&e| writer.write_f64::<LittleEndian>(e))?,
+            MatData::F32(v) => v.iter().try_for_each(|&e| writer.write_f32::<LittleEndian>(e))?,
+            MatData::I32(v) => v.iter().try_for_each(|&e| writer.write_i32::<LittleEndian>(e))?,
+            MatData::I16(v) => v.iter().try_for_each(|&e| writer.write_i16::<LittleEndian>(e))?,
+            MatData::U16(v) => v.iter().try_for_each(|&e| writer.write_u16::<LittleEndian>(e))?,
+            MatData::U8(v) => writer.write_all(v)?,
+        }
+    }
+    Ok(())
+}
+
+fn read_variable<E: ByteOrder, R: Read>(reader: &mut R, header: &[u8]) -> Result<MatVariable> {
+    let type_ = E::read_i32(&header[0..]);
+    let rows = E::read_i32(&header[4..]);
+    let cols = E::read_i32(&header[8..]);
+    let imaginary = E::read_i32(&header[12..]);
+    let name_length = E::read_i32(&header[16..]);
+    if rows < 0 || cols < 0 || name_length <= 0 || type_ % 10 > 1 {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a valid MATLAB v4 variable"));
+    }
+
+    let name = read_bytes(reader, name_length as usize)?;
+    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    let name = String::from_utf8_lossy(&name[..end]).to_string();
+
+    let (rows, cols) = (rows as usize, cols as usize);
+    let size = match (type_ / 10) % 10 {
+        0 => 8,
+        1 | 2 => 4,
+        3 | 4 => 2,
+        5 => 1,
+        _ => return Err(Error::new(ErrorKind::InvalidData, "Unknown MATLAB v4 data type")),
+    };
+    let nb_bytes = rows
+        .checked_mul(cols)
+        .and_then(|nb| nb.checked_mul(if imaginary != 0 { 2 } else { 1 }))
+        .and_then(|nb| nb.checked_mul(size))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "MATLAB v4 variable is too big"))?;
+    let bytes = read_bytes(reader, nb_bytes)?;
+    let nb = nb_bytes / size;
+    let mut data = match (type_ / 10) % 10 {
+        0 => {
+            let mut v = vec![0.0; nb];
+            E::read_f64_into(&bytes, &mut v);
+            MatData::F64(v)
+        }
+        1 => {
+            let mut v = vec![0.0; nb];
+            E::read_f32_into(&bytes, &mut v);
+            MatData::F32(v)
+        }
+        2 => {
+            let mut v = vec![0; nb];
+            E::read_i32_into(&bytes, &mut v);
+            MatData::I32(v)
+        }
+        3 => {
+            let mut v = vec![0; nb];
+            E::read_i16_into(&bytes, &mut v);
+            MatData::I16(v)
+        }
+        4 => {
+            let mut v = vec![0; nb];
+            E::read_u16_into(&bytes, &mut v);
+            MatData::U16(v)
+        }
+        _ => MatData::U8(bytes),
+    };
+
+    // Drop the imaginary part
+    let nb = rows * cols;
+    match &mut data {
+        MatData::F64(v) => v.truncate(nb),
+        MatData::F32(v) => v.truncate(nb),
+        MatData::I32(v) => v.truncate(nb),
+        MatData::I16(v) => v.truncate(nb),
+        MatData::U16(v) => v.truncate(nb),
+        MatData::U8(v) => v.truncate(nb),
+    }
+    Ok(MatVariable { name, rows, cols, data })
+}
+
+/// Read `nb_bytes` bytes. The buffer grows with the data actually read, so a size coming from a
+/// corrupted header can't trigger a huge allocation.
+fn read_bytes<R: Read>(reader: &mut R, nb_bytes: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    reader.take(nb_bytes as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < nb_bytes {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "MATLAB v4 variable is larger than the file",
+        ));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_big_endian() {
+        let mut bytes = vec![];
+        for i in [1010, 1, 2, 0, 2] {
+            bytes.extend_from_slice(&i32::to_be_bytes(i));
+        }
+        bytes.extend_from_slice(b"a\0");
+        bytes.extend_from_slice(&f32::to_be_bytes(1.5));
+        bytes.extend_from_slice(&f32::to_be_bytes(-2.0));
+
+        let variables = read_mat4(&mut bytes.as_slice()).unwrap();
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].name, "a");
+        assert_eq!((variables[0].rows, variables[0].cols), (1, 2));
+        assert_eq!(variables[0].data, MatData::F32(vec![1.5, -2.0]));
+    }
+
+    #[test]
+    fn test_write_read() {
+        let variables = vec![
+            MatVariable { name: "a".into(), rows: 1, cols: 3, data: MatData::I32(vec![1, -2, 3]) },
+            MatVariable { name: "bc".into(), rows: 2, cols: 1, data: MatData::U8(vec![0, 255]) },
+            MatVariable { name: "d".into(), rows: 1, cols: 1, data: MatData::F64(vec![0.25]) },
+        ];
+        let mut bytes = vec![];
+        write_mat4(&mut bytes, &variables).unwrap();
+        assert_eq!(read_mat4(&mut bytes.as_slice()).unwrap(), variables);
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(read_mat4(&mut &b""[..]).is_err());
+        assert!(read_mat4(&mut &b"TRACK\0 not a mat file"[..]).is_err());
+
+        // Sizes that don't fit in the file, or in memory
+        for (rows, cols) in [(1, 2), (i32::MAX, i32::MAX)] {
+            let mut bytes = vec![];
+            for i in [0, rows, cols, 1, 1] {
+                bytes.extend_from_slice(&i32::to_le_bytes(i));
+            }
+            bytes.extend_from_slice(b"\0abcd");
+            let err = read_mat4(&mut bytes.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
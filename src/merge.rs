@@ -0,0 +1,272 @@
+use std::{ops::Range, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{ArraySequence, Header, Reader, Streamlines, Tractogram, Writer};
+
+/// How to reconcile the scalars and properties of the merged tractograms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// All tractograms must have the same scalars and properties, in any order.
+    Error,
+
+    /// Keep only the scalars and properties present in all tractograms.
+    Intersect,
+
+    /// Keep all scalars and properties. The missing values are filled with NaN.
+    Fill,
+}
+
+/// Options of `Tractogram::concat` and `merge_trk`.
+#[derive(Clone, Debug)]
+pub struct MergeOptions<'a> {
+    pub policy: MergePolicy,
+
+    /// Name of a property to add, containing the index of the source of each streamline.
+    pub source_property: Option<&'a str>,
+
+    /// Tolerance used to compare the affines and voxel sizes of the headers.
+    pub tolerance: f32,
+}
+
+impl Default for MergeOptions<'_> {
+    fn default() -> Self {
+        MergeOptions { policy: MergePolicy::Error, source_property: None, tolerance: 1e-4 }
+    }
+}
+
+/// Fails if `a` and `b` don't describe the same reference space, that is, the same affine to RAS+
+/// mm, dimensions, voxel size and voxel order.
+pub fn check_compatible(a: &Header, b: &Header, tolerance: f32) -> Result<()> {
+    let (c_a, c_b) = (a.raw_header(), b.raw_header());
+    if (a.affine4_to_rasmm - b.affine4_to_rasmm).abs().max() > tolerance {
+        bail!(
+            "The affines to RAS+ mm are different: {} != {}",
+            a.affine4_to_rasmm,
+            b.affine4_to_rasmm
+        );
+    }
+    if c_a.dim != c_b.dim {
+        bail!("The dimensions are different: {:?} != {:?}", c_a.dim, c_b.dim);
+    }
+    if c_a.voxel_size.iter().zip(&c_b.voxel_size).any(|(a, b)| (a - b).abs() > tolerance) {
+        bail!("The voxel sizes are different: {:?} != {:?}", c_a.voxel_size, c_b.voxel_size);
+    }
+    if c_a.voxel_order != c_b.voxel_order {
+        let order = |o: [u8; 4]| String::from_utf8_lossy(&o[..3]).to_string();
+        bail!(
+            "The voxel orders are different: {} != {}",
+            order(c_a.voxel_order),
+            order(c_b.voxel_order)
+        );
+    }
+    Ok(())
+}
+
+/// Where to find each column of the merged scalars or properties in a source.
+struct ColumnMap {
+    /// Start of the columns in the source, or `None` if they must be filled with NaN, and number
+    /// of columns.
+    columns: Vec<(Option<usize>, usize)>,
+    source_stride: usize,
+}
+
+impl ColumnMap {
+    /// Append the remapped values of the `nb_tuples` tuples of `values` to `out`.
+    fn apply(&self, values: &[f32], nb_tuples: usize, out: &mut Vec<f32>) {
+        for tuple in 0..nb_tuples {
+            let tuple = &values[tuple * self.source_stride..(tuple + 1) * self.source_stride];
+            for &(start, nb) in &self.columns {
+                match start {
+                    Some(start) => out.extend_from_slice(&tuple[start..start + nb]),
+                    None => out.extend(std::iter::repeat_n(f32::NAN, nb)),
+                }
+            }
+        }
+    }
+}
+
+/// The merged header and, for each source, the maps of its scalars and properties.
+struct MergePlan {
+    header: Header,
+    scalars: Vec<ColumnMap>,
+    properties: Vec<ColumnMap>,
+    add_source: bool,
+}
+
+impl MergePlan {
+    fn new(headers: &[&Header], options: &MergeOptions) -> Result<MergePlan> {
+        let Some(first) = headers.first() else {
+            bail!("There's nothing to merge.");
+        };
+        for (idx, header) in headers.iter().enumerate().skip(1) {
+            check_compatible(first, header, options.tolerance).with_context(|| {
+                format!("Tractogram {} is not compatible with tractogram 0", idx)
+            })?;
+        }
+
+        let scalars = headers.iter().map(|h| h.scalars_groups()).collect::<Vec<_>>();
+        let properties = headers.iter().map(|h| h.properties_groups()).collect::<Vec<_>>();
+        let merged_scalars = merge_groups(&scalars, options.policy, "scalar")?;
+        let merged_properties = merge_groups(&properties, options.policy, "property")?;
+
+        let mut header = (*first).clone();
+        header.clear_scalars_and_properties();
+        for (name, nb) in &merged_scalars {
+            header.add_scalar_n(name, *nb)?;
+        }
+        for (name, nb) in &merged_properties {
+            header.add_property_n(name, *nb)?;
+        }
+        if let Some(name) = options.source_property {
+            if merged_properties.iter().any(|(n, _)| n == name) {
+                bail!("There's already a property named {:?}", name);
+            }
+            header.add_property(name)?;
+        }
+        header.nb_streamlines = headers.iter().map(|h| h.nb_streamlines).sum();
+
+        Ok(MergePlan {
+            header,
+            scalars: scalars.iter().map(|groups| column_map(&merged_scalars, groups)).collect(),
+            properties: properties
+                .iter()
+                .map(|groups| column_map(&merged_properties, groups))
+                .collect(),
+            add_source: options.source_property.is_some(),
+        })
+    }
+
+    /// Remapped scalars of a streamline of `nb_points` points of the source `idx`.
+    fn apply_scalars(&self, idx: usize, scalars: &[f32], nb_points: usize, out: &mut Vec<f32>) {
+        self.scalars[idx].apply(scalars, nb_points, out);
+    }
+
+    /// Remapped properties of a streamline of the source `idx`, with the source index, if asked.
+    fn apply_properties(&self, idx: usize, properties: &[f32], out: &mut Vec<f32>) {
+        self.properties[idx].apply(properties, 1, out);
+        if self.add_source {
+            out.push(idx as f32);
+        }
+    }
+}
+
+/// Returns the merged `(name, nb_values)` groups, in order of first appearance.
+fn merge_groups(
+    groups: &[Vec<(String, Range<usize>)>],
+    policy: MergePolicy,
+    kind: &str,
+) -> Result<Vec<(String, usize)>> {
+    let mut merged: Vec<(String, usize)> = vec![];
+    for source in groups {
+        for (name, range) in source {
+            match merged.iter().find(|(n, _)| n == name) {
+                Some((_, nb)) if *nb != range.len() => {
+                    bail!("The {} {:?} has {} and {} values", kind, name, nb, range.len())
+                }
+                Some(_) => {}
+                None => merged.push((name.clone(), range.len())),
+            }
+        }
+    }
+
+    let in_all = |name: &str| groups.iter().all(|source| source.iter().any(|(n, _)| n == name));
+    match policy {
+        MergePolicy::Error => {
+            if let Some((name, _)) = merged.iter().find(|(name, _)| !in_all(name)) {
+                bail!("The {} {:?} is not in all tractograms", kind, name);
+            }
+        }
+        MergePolicy::Intersect => merged.retain(|(name, _)| in_all(name)),
+        MergePolicy::Fill => {}
+    }
+    Ok(merged)
+}
+
+fn column_map(merged: &[(String, usize)], source: &[(String, Range<usize>)]) -> ColumnMap {
+    let columns = merged
+        .iter()
+        .map(|(name, nb)| {
+            let start = source.iter().find(|(n, _)| n == name).map(|(_, range)| range.start);
+            (start, *nb)
+        })
+        .collect();
+    let source_stride = source.last().map_or(0, |(_, range)| range.end);
+    ColumnMap { columns, source_stride }
+}
+
+/// See `Tractogram::concat`.
+pub(crate) fn concat(
+    parts: &[(&Header, &Tractogram)],
+    options: &MergeOptions,
+) -> Result<(Header, Tractogram)> {
+    let headers = parts.iter().map(|(header, _)| *header).collect::<Vec<_>>();
+    let plan = MergePlan::new(&headers, options)?;
+    let nb_points = parts.iter().map(|(_, t)| t.streamlines.data.len()).sum();
+    let mut lengths = vec![];
+    let mut points = Vec::with_capacity(nb_points);
+    let (mut scalars_lengths, mut scalars) = (vec![], vec![]);
+    let (mut properties_lengths, mut properties) = (vec![], vec![]);
+    for (idx, (header, tractogram)) in parts.iter().enumerate() {
+        let nb_scalars = header.scalars_name.len() * tractogram.streamlines.data.len();
+        let nb_properties = header.properties_name.len() * tractogram.streamlines.len();
+        if tractogram.scalars.data.len() != nb_scalars
+            || tractogram.properties.data.len() != nb_properties
+        {
+            bail!("The scalars or properties of tractogram {} don't match its header", idx);
+        }
+        for (streamline, streamline_scalars, streamline_properties) in *tractogram {
+            lengths.push(streamline.len());
+            points.extend_from_slice(streamline);
+            let start = scalars.len();
+            plan.apply_scalars(idx, streamline_scalars, streamline.len(), &mut scalars);
+            scalars_lengths.push(scalars.len() - start);
+            let start = properties.len();
+            plan.apply_properties(idx, streamline_properties, &mut properties);
+            properties_lengths.push(properties.len() - start);
+        }
+    }
+
+    let mut header = plan.header;
+    header.nb_streamlines = lengths.len();
+    let scalars = if scalars.is_empty() {
+        ArraySequence::empty()
+    } else {
+        ArraySequence::new(scalars_lengths, scalars)
+    };
+    let properties = if properties.is_empty() {
+        ArraySequence::empty()
+    } else {
+        ArraySequence::new(properties_lengths, properties)
+    };
+    let streamlines = Streamlines::new(lengths, points);
+    Ok((header, Tractogram::new(streamlines, scalars, properties)))
+}
+
+/// Merge the trk files `inputs` in a single trk file `output`, one streamline at a time, and
+/// returns the header of `output`.
+pub fn merge_trk<P: AsRef<Path>, Q: AsRef<Path>>(
+    inputs: &[P],
+    output: Q,
+    options: &MergeOptions,
+) -> Result<Header> {
+    let headers = inputs.iter().map(Header::from_trk).collect::<Result<Vec<_>>>()?;
+    let plan = MergePlan::new(&headers.iter().collect::<Vec<_>>(), options)?;
+
+    let mut writer = Writer::new(output, Some(&plan.header))?;
+    let (mut scalars, mut properties) = (vec![], vec![]);
+    let mut nb_streamlines = 0;
+    for (idx, input) in inputs.iter().enumerate() {
+        for (streamline, streamline_scalars, streamline_properties) in Reader::new(input)? {
+            nb_streamlines += 1;
+            scalars.clear();
+            properties.clear();
+            plan.apply_scalars(idx, &streamline_scalars.data, streamline.len(), &mut scalars);
+            plan.apply_properties(idx, &streamline_properties, &mut properties);
+            writer.write((streamline.as_slice(), scalars.as_slice(), properties.as_slice()));
+        }
+    }
+    let mut header = plan.header;
+    header.nb_streamlines = nb_streamlines;
+    Ok(header)
+}
@@ -0,0 +1,340 @@
+use std::{
+    f32::consts::PI,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{LittleEndian, WriteBytesExt};
+use nalgebra::Vector3;
+
+use crate::{Header, Point, Tractogram};
+
+/// File format of the exported mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshFormat {
+    /// Binary little endian PLY. Polylines are written as edges.
+    Ply,
+
+    /// Wavefront OBJ. Polylines are written as lines and the colors are appended to the vertices,
+    /// as supported by Blender and MeshLab.
+    Obj,
+
+    /// Binary STL. Only tubes can be written and the colors are ignored.
+    Stl,
+}
+
+/// Geometry used to represent the streamlines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Geometry {
+    /// The points of the streamlines, connected by line segments.
+    Polyline,
+
+    /// Closed tubes of `radius` mm around the streamlines, with `nb_sides` sides.
+    Tube { radius: f32, nb_sides: usize },
+}
+
+/// Options of `write_mesh`.
+#[derive(Clone, Debug)]
+pub struct MeshOptions<'a> {
+    pub geometry: Geometry,
+
+    /// Names of the per-point scalars holding the red, green and blue components, in [0, 255],
+    /// e.g., `["color_x", "color_y", "color_z"]`, as written by the `trk_color` example.
+    pub colors: Option<[&'a str; 3]>,
+}
+
+impl Default for MeshOptions<'_> {
+    fn default() -> Self {
+        MeshOptions { geometry: Geometry::Polyline, colors: None }
+    }
+}
+
+/// A mesh, made of either polylines or triangles.
+#[derive(Default)]
+struct Mesh {
+    vertices: Vec<Point>,
+    colors: Vec<[u8; 3]>,
+    polylines: Vec<Vec<u32>>,
+    triangles: Vec<[u32; 3]>,
+}
+
+/// Export the streamlines of `tractogram` as a mesh.
+///
+/// The points are written as they are, thus they should be in RAS+ mm. Streamlines with less than
+/// 2 points are ignored because they have no segment. Fails if the mesh has more vertices than its
+/// indices can address.
+pub fn write_mesh<P: AsRef<Path>>(
+    path: P,
+    header: &Header,
+    tractogram: &Tractogram,
+    format: MeshFormat,
+    options: &MeshOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+    let colors = match options.colors {
+        Some(names) => Some(point_colors(header, tractogram, names)?),
+        None => None,
+    };
+    // The vertices are indexed with u32, and with i32 in PLY files
+    let nb_vertices = match options.geometry {
+        Geometry::Polyline => tractogram.streamlines.data.len(),
+        Geometry::Tube { nb_sides, .. } => tractogram
+            .streamlines
+            .iter()
+            .filter(|s| s.len() >= 2)
+            .map(|s| s.len().saturating_mul(nb_sides).saturating_add(2))
+            .fold(0usize, usize::saturating_add),
+    };
+    let max_vertices =
+        if format == MeshFormat::Ply { i32::MAX as usize } else { u32::MAX as usize };
+    if nb_vertices > max_vertices {
+        bail!(
+            "The mesh would have {} vertices, more than the maximum of {}",
+            nb_vertices,
+            max_vertices
+        );
+    }
+
+    let mesh = match options.geometry {
+        Geometry::Polyline => {
+            if format == MeshFormat::Stl {
+                bail!("STL files can only contain triangles. Use Geometry::Tube.");
+            }
+            polylines(tractogram, colors)
+        }
+        Geometry::Tube { radius, nb_sides } => {
+            if nb_sides < 3 || radius <= 0.0 {
+                bail!("Tubes need a positive radius and at least 3 sides.");
+            }
+            tubes(tractogram, colors, radius, nb_sides)
+        }
+    };
+
+    let f = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut writer = BufWriter::new(f);
+    match format {
+        MeshFormat::Ply => write_ply(&mut writer, &mesh)?,
+        MeshFormat::Obj => write_obj(&mut writer, &mesh)?,
+        MeshFormat::Stl => write_stl(&mut writer, &mesh)?,
+    }
+    Ok(())
+}
+
+/// Returns the color of each point of `tractogram`, using the scalars `names`.
+fn point_colors(
+    header: &Header,
+    tractogram: &Tractogram,
+    names: [&str; 3],
+) -> Result<Vec<[u8; 3]>> {
+    let mut columns = [0; 3];
+    for (column, name) in columns.iter_mut().zip(names) {
+        *column = match header.scalars_name.iter().position(|n| n == name) {
+            Some(column) => column,
+            None => bail!("There's no scalar named {:?} in the header.", name),
+        };
+    }
+    let nb_scalars = header.scalars_name.len();
+    if tractogram.scalars.data.len() != nb_scalars * tractogram.streamlines.data.len() {
+        bail!("The tractogram doesn't have {} scalars per point.", nb_scalars);
+    }
+    let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    Ok(tractogram
+        .scalars
+        .data
+        .chunks(nb_scalars)
+        .map(|scalars| columns.map(|column| to_u8(scalars[column])))
+        .collect())
+}
+
+fn polylines(tractogram: &Tractogram, colors: Option<Vec<[u8; 3]>>) -> Mesh {
+    let streamlines = &tractogram.streamlines;
+    Mesh {
+        vertices: streamlines.data.clone(),
+        colors: colors.unwrap_or_default(),
+        polylines: streamlines
+            .iter()
+            .zip(&streamlines.offsets)
+            .filter(|(streamline, _)| streamline.len() >= 2)
+            .map(|(streamline, &start)| (start..start + streamline.len()).map(|i| i as u32))
+            .map(|indices| indices.collect())
+            .collect(),
+        triangles: vec![],
+    }
+}
+
+fn tubes(
+    tractogram: &Tractogram,
+    colors: Option<Vec<[u8; 3]>>,
+    radius: f32,
+    nb_sides: usize,
+) -> Mesh {
+    let mut mesh = Mesh::default();
+    let streamlines = &tractogram.streamlines;
+    for (streamline, &offset) in streamlines.iter().zip(&streamlines.offsets) {
+        if streamline.len() < 2 {
+            continue;
+        }
+        let color = |i: usize| colors.as_ref().map(|colors| colors[offset + i]);
+        let push = |mesh: &mut Mesh, p: Point, color: Option<[u8; 3]>| {
+            mesh.vertices.push(p);
+            if let Some(color) = color {
+                mesh.colors.push(color);
+            }
+            mesh.vertices.len() as u32 - 1
+        };
+
+        // Rings of vertices around each point, using a parallel transport frame to avoid twists
+        let tangents = tangents(streamline);
+        let mut normal = any_orthogonal(&tangents[0]);
+        let first_ring = mesh.vertices.len() as u32;
+        for (i, (p, t)) in streamline.iter().zip(&tangents).enumerate() {
+            let projected = normal - t * normal.dot(t);
+            if projected.norm() > 1e-6 {
+                normal = projected.normalize();
+            }
+            let binormal = t.cross(&normal);
+            for k in 0..nb_sides {
+                let angle = 2.0 * PI * k as f32 / nb_sides as f32;
+                let offset = (normal * angle.cos() + binormal * angle.sin()) * radius;
+                push(&mut mesh, p + offset, color(i));
+            }
+        }
+
+        let ring = |i: usize, k: usize| first_ring + (i * nb_sides + k % nb_sides) as u32;
+        for i in 0..streamline.len() - 1 {
+            for k in 0..nb_sides {
+                let (a, b, c, d) = (ring(i, k), ring(i, k + 1), ring(i + 1, k), ring(i + 1, k + 1));
+                mesh.triangles.push([a, b, d]);
+                mesh.triangles.push([a, d, c]);
+            }
+        }
+
+        // Caps, so that the tubes are closed
+        let last = streamline.len() - 1;
+        let start = push(&mut mesh, streamline[0], color(0));
+        let end = push(&mut mesh, streamline[last], color(last));
+        for k in 0..nb_sides {
+            mesh.triangles.push([start, ring(0, k + 1), ring(0, k)]);
+            mesh.triangles.push([end, ring(last, k), ring(last, k + 1)]);
+        }
+    }
+    mesh
+}
+
+/// Unit tangent at each point of `streamline`, which must have at least 2 points.
+fn tangents(streamline: &[Point]) -> Vec<Vector3<f32>> {
+    let n = streamline.len();
+    let mut previous = Vector3::x();
+    (0..n)
+        .map(|i| {
+            let direction = streamline[(i + 1).min(n - 1)] - streamline[i.saturating_sub(1)];
+            if direction.norm() > 1e-6 {
+                previous = direction.normalize();
+            }
+            previous
+        })
+        .collect()
+}
+
+fn any_orthogonal(v: &Vector3<f32>) -> Vector3<f32> {
+    let other = if v.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+    v.cross(&other).normalize()
+}
+
+fn write_ply<W: Write>(writer: &mut W, mesh: &Mesh) -> Result<()> {
+    let has_colors = !mesh.colors.is_empty();
+    let nb_edges = mesh.polylines.iter().map(|p| p.len().saturating_sub(1)).sum::<usize>();
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format binary_little_endian 1.0")?;
+    writeln!(writer, "comment Streamlines exported by trk-io")?;
+    writeln!(writer, "element vertex {}", mesh.vertices.len())?;
+    writeln!(writer, "property float x\nproperty float y\nproperty float z")?;
+    if has_colors {
+        writeln!(writer, "property uchar red\nproperty uchar green\nproperty uchar blue")?;
+    }
+    if mesh.triangles.is_empty() {
+        writeln!(writer, "element edge {}", nb_edges)?;
+        writeln!(writer, "property int vertex1\nproperty int vertex2")?;
+    } else {
+        writeln!(writer, "element face {}", mesh.triangles.len())?;
+        writeln!(writer, "property list uchar int vertex_indices")?;
+    }
+    writeln!(writer, "end_header")?;
+
+    for (i, p) in mesh.vertices.iter().enumerate() {
+        write_point(writer, p)?;
+        if has_colors {
+            writer.write_all(&mesh.colors[i])?;
+        }
+    }
+    for polyline in &mesh.polylines {
+        for edge in polyline.windows(2) {
+            writer.write_i32::<LittleEndian>(edge[0] as i32)?;
+            writer.write_i32::<LittleEndian>(edge[1] as i32)?;
+        }
+    }
+    for triangle in &mesh.triangles {
+        writer.write_u8(3)?;
+        for &v in triangle {
+            writer.write_i32::<LittleEndian>(v as i32)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_obj<W: Write>(writer: &mut W, mesh: &Mesh) -> Result<()> {
+    writeln!(writer, "# Streamlines exported by trk-io")?;
+    for (i, p) in mesh.vertices.iter().enumerate() {
+        match mesh.colors.get(i) {
+            Some(c) => {
+                let [r, g, b] = c.map(|c| c as f32 / 255.0);
+                writeln!(writer, "v {} {} {} {} {} {}", p.x, p.y, p.z, r, g, b)?
+            }
+            None => writeln!(writer, "v {} {} {}", p.x, p.y, p.z)?,
+        }
+    }
+    // OBJ indices start at 1
+    for polyline in &mesh.polylines {
+        write!(writer, "l")?;
+        for v in polyline {
+            write!(writer, " {}", v + 1)?;
+        }
+        writeln!(writer)?;
+    }
+    for [a, b, c] in &mesh.triangles {
+        writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+    }
+    Ok(())
+}
+
+fn write_stl<W: Write>(writer: &mut W, mesh: &Mesh) -> Result<()> {
+    let mut header = [0u8; 80];
+    let comment = b"Streamlines exported by trk-io";
+    header[..comment.len()].copy_from_slice(comment);
+    writer.write_all(&header)?;
+    let nb_triangles = match u32::try_from(mesh.triangles.len()) {
+        Ok(nb_triangles) => nb_triangles,
+        Err(_) => bail!("STL files can't contain {} triangles", mesh.triangles.len()),
+    };
+    writer.write_u32::<LittleEndian>(nb_triangles)?;
+    for triangle in &mesh.triangles {
+        let [a, b, c] = triangle.map(|v| mesh.vertices[v as usize]);
+        let normal = (b - a).cross(&(c - a));
+        let normal = if normal.norm() > 0.0 { normal.normalize() } else { normal };
+        write_point(writer, &Point::from(normal))?;
+        for p in [a, b, c] {
+            write_point(writer, &p)?;
+        }
+        writer.write_u16::<LittleEndian>(0)?;
+    }
+    Ok(())
+}
+
+fn write_point<W: Write>(writer: &mut W, p: &Point) -> Result<()> {
+    writer.write_f32::<LittleEndian>(p.x)?;
+    writer.write_f32::<LittleEndian>(p.y)?;
+    writer.write_f32::<LittleEndian>(p.z)?;
+    Ok(())
+}
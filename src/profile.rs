@@ -0,0 +1,207 @@
+use std::{fs::File, io::BufWriter, io::Write, path::Path};
+
+use anyhow::{bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+#[cfg(feature = "nifti_images")]
+use ndarray::Array3;
+
+#[cfg(feature = "nifti_images")]
+use crate::{interpolation::trilinear, streamline::resample, Affine4};
+use crate::{
+    streamline::{direct_flip_distances, flip, resample_with_scalars},
+    ArraySequence, Header, Streamlines, Tractogram,
+};
+
+/// Where to find the scalar values that will be profiled along the tract.
+pub enum ScalarSource<'a> {
+    /// Use the per-point scalar with this name in `Header::scalars_name`.
+    Name(&'a str),
+
+    /// Sample a scalar map using trilinear interpolation.
+    ///
+    /// `affine` maps the voxel indices of `data` to RAS+ mm, like `NiftiHeader::affine`.
+    #[cfg(feature = "nifti_images")]
+    Volume { data: &'a Array3<f32>, affine: &'a Affine4 },
+}
+
+/// Weighted mean and standard deviation of a scalar at each node of a tract.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TractProfile {
+    pub mean: Vec<f32>,
+    pub std: Vec<f32>,
+}
+
+impl TractProfile {
+    /// Number of nodes in the profile.
+    pub fn len(&self) -> usize {
+        self.mean.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mean.is_empty()
+    }
+
+    /// Write the profile to a CSV file with the columns `node,mean,std`.
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let f = File::create(path.as_ref())
+            .with_context(|| format!("Failed to create {:?}", path.as_ref()))?;
+        let mut writer = BufWriter::new(f);
+        writeln!(writer, "node,mean,std")?;
+        for (node, (mean, std)) in self.mean.iter().zip(&self.std).enumerate() {
+            writeln!(writer, "{},{},{}", node, mean, std)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compute the along-tract profile of a scalar for a bundle, as done in AFQ.
+///
+/// Each streamline of `tractogram` is resampled to `nb_nodes` points and oriented in the same
+/// direction as the first streamline. At each node, the scalar values are averaged with the
+/// weights returned by `gaussian_weights`, so that the streamlines far from the core of the bundle
+/// count less than the others.
+///
+/// The streamlines are expected to be in RAS+ mm, as returned by `Reader`. `nb_nodes` must be at
+/// least 2. The empty streamlines are ignored because they have no value to profile.
+pub fn tract_profile(
+    tractogram: &Tractogram,
+    header: &Header,
+    source: ScalarSource,
+    nb_nodes: usize,
+) -> Result<TractProfile> {
+    if tractogram.streamlines.is_empty() {
+        bail!("Can't compute the profile of an empty tractogram.");
+    }
+    if nb_nodes < 2 {
+        bail!("A profile needs at least 2 nodes. Got {}", nb_nodes);
+    }
+
+    let (mut bundle, mut values) = match source {
+        ScalarSource::Name(name) => resample_named_scalar(tractogram, header, name, nb_nodes)?,
+        #[cfg(feature = "nifti_images")]
+        ScalarSource::Volume { data, affine } => {
+            resample_volume(&tractogram.streamlines, data, affine, nb_nodes)?
+        }
+    };
+
+    if bundle.is_empty() {
+        bail!("Can't compute the profile of a tractogram of empty streamlines.");
+    }
+
+    // Orient all streamlines like the first one
+    let reference = bundle[0].to_vec();
+    for (streamline, values) in bundle.iter_mut().zip(values.iter_mut()) {
+        let (direct, flipped) = direct_flip_distances(&reference, streamline);
+        if flipped < direct {
+            flip(streamline, values);
+        }
+    }
+
+    let weights = gaussian_weights(&bundle);
+    let mut mean = vec![0.0; nb_nodes];
+    let mut std = vec![0.0; nb_nodes];
+    for node in 0..nb_nodes {
+        for (w, v) in weights.iter().zip(&values) {
+            mean[node] += w[node] * v[node];
+        }
+        for (w, v) in weights.iter().zip(&values) {
+            std[node] += w[node] * (v[node] - mean[node]).powi(2);
+        }
+        std[node] = std[node].sqrt();
+    }
+    Ok(TractProfile { mean, std })
+}
+
+/// Weight of each node of each streamline of `bundle`, based on its Mahalanobis distance to the
+/// core of the bundle.
+///
+/// All streamlines must have the same number of points and be oriented in the same direction. At
+/// each node, the weights are `exp(-d² / 2)`, normalized to sum to 1 over all streamlines. If all
+/// the weights of a node underflow to 0, the streamlines are weighted equally at this node.
+pub fn gaussian_weights(bundle: &Streamlines) -> ArraySequence<f32> {
+    let nb_streamlines = bundle.len();
+    if nb_streamlines == 0 {
+        return ArraySequence::empty();
+    }
+    let nb_nodes = bundle.length_of_array(0);
+
+    let mut weights = vec![0.0; nb_streamlines * nb_nodes];
+    for node in 0..nb_nodes {
+        let mean =
+            bundle.iter().map(|s| s[node].coords).sum::<Vector3<f32>>() / nb_streamlines as f32;
+        let covariance = bundle
+            .iter()
+            .map(|s| {
+                let d = s[node].coords - mean;
+                d * d.transpose()
+            })
+            .sum::<Matrix3<f32>>()
+            / nb_streamlines as f32;
+        let inverse = covariance
+            .try_inverse()
+            .or_else(|| covariance.pseudo_inverse(1e-6).ok())
+            .unwrap_or_else(Matrix3::zeros);
+
+        let mut sum = 0.0;
+        for (i, streamline) in bundle.iter().enumerate() {
+            let d = streamline[node].coords - mean;
+            let distance2 = (d.transpose() * inverse * d)[0].max(0.0);
+            let w = (-0.5 * distance2).exp();
+            weights[i * nb_nodes + node] = w;
+            sum += w;
+        }
+        for i in 0..nb_streamlines {
+            weights[i * nb_nodes + node] = if sum > 0.0 && sum.is_finite() {
+                weights[i * nb_nodes + node] / sum
+            } else {
+                1.0 / nb_streamlines as f32
+            };
+        }
+    }
+    ArraySequence::new(vec![nb_nodes; nb_streamlines], weights)
+}
+
+fn resample_named_scalar(
+    tractogram: &Tractogram,
+    header: &Header,
+    name: &str,
+    nb_nodes: usize,
+) -> Result<(Streamlines, ArraySequence<f32>)> {
+    let nb_scalars = header.scalars_name.len();
+    let column = match header.scalars_name.iter().position(|n| n == name) {
+        Some(column) => column,
+        None => bail!("There's no scalar named {:?} in the header.", name),
+    };
+
+    let mut bundle = Streamlines::with_capacity(tractogram.streamlines.len() * nb_nodes);
+    let mut values = ArraySequence::with_capacity(tractogram.streamlines.len() * nb_nodes);
+    for (streamline, scalars, _) in tractogram {
+        let scalar = scalars.iter().skip(column).step_by(nb_scalars).cloned().collect::<Vec<_>>();
+        let (points, scalar) = resample_with_scalars(streamline, &scalar, nb_nodes);
+        bundle.extend(points);
+        values.extend(scalar);
+    }
+    Ok((bundle, values))
+}
+
+#[cfg(feature = "nifti_images")]
+fn resample_volume(
+    streamlines: &Streamlines,
+    data: &Array3<f32>,
+    affine: &Affine4,
+    nb_nodes: usize,
+) -> Result<(Streamlines, ArraySequence<f32>)> {
+    let to_voxel = match affine.try_inverse() {
+        Some(to_voxel) => to_voxel,
+        None => bail!("The affine of the scalar map is not invertible."),
+    };
+
+    let mut bundle = Streamlines::with_capacity(streamlines.len() * nb_nodes);
+    let mut values = ArraySequence::with_capacity(streamlines.len() * nb_nodes);
+    for streamline in streamlines {
+        let points = resample(streamline, nb_nodes);
+        values.extend(points.iter().map(|p| trilinear(data, &to_voxel.transform_point(p))));
+        bundle.extend(points);
+    }
+    Ok((bundle, values))
+}
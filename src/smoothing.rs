@@ -0,0 +1,249 @@
+use anyhow::{bail, Result};
+use nalgebra::{DMatrix, Vector3};
+
+use crate::{
+    streamline::{arc_lengths, resample_with_scalars},
+    ArraySequence, Point, Points, Streamlines, Tractogram, TractogramItem,
+};
+
+/// Maximum number of control points of the B-spline fitted on a streamline.
+const MAX_CONTROL_POINTS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    /// Replace each point by the mean of itself and its `radius` neighbors on each side. The window
+    /// is truncated near the endpoints.
+    MovingAverage { radius: usize },
+
+    /// Replace each point by the mean of its neighbors, weighted by a gaussian kernel of standard
+    /// deviation `sigma` (in mm, along the streamline).
+    Gaussian { sigma: f32 },
+
+    /// Fit a cubic B-spline on the points, penalizing its curvature with `smoothing`. 0 gives a
+    /// simple least square fit; the higher, the smoother.
+    ///
+    /// The spline is evaluated at the position of the original points, or at `nb_points`
+    /// positions equally spaced along the streamline, if requested. In the later case, the
+    /// per-point scalars are resampled at the same positions, and `nb_points` must be at least 2.
+    BSpline { smoothing: f32, nb_points: Option<usize> },
+}
+
+/// Smooth streamlines, along with their per-point scalars.
+///
+/// Works on a single streamline at a time, thus it can be used on the items of a `Reader` to
+/// smooth whole files without loading them.
+///
+/// ```no_run
+/// # use trk_io::{smoothing::{Smoother, Smoothing}, Reader};
+/// let reader = Reader::new("full_brain.trk").unwrap();
+/// let mut writer = reader.build_writer("smooth.trk").unwrap();
+/// let smoother = Smoother::new(Smoothing::Gaussian { sigma: 1.0 }).unwrap().keep_endpoints(true);
+/// for item in reader {
+///     writer.write(smoother.smooth_item(item));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Smoother {
+    method: Smoothing,
+    keep_endpoints: bool,
+}
+
+impl Smoother {
+    pub fn new(method: Smoothing) -> Result<Smoother> {
+        if let Smoothing::BSpline { nb_points: Some(nb_points), .. } = method
+            && nb_points < 2
+        {
+            bail!("Can't resample the streamlines to less than 2 points. Got {}", nb_points);
+        }
+        Ok(Smoother { method, keep_endpoints: false })
+    }
+
+    /// Do not move the first and last points of the streamlines.
+    pub fn keep_endpoints(mut self, keep_endpoints: bool) -> Self {
+        self.keep_endpoints = keep_endpoints;
+        self
+    }
+
+    /// Returns the smoothed `streamline` and its per-point `scalars`.
+    ///
+    /// `scalars` is interleaved, like in a `TractogramItem`, so it must contain the same number of
+    /// values for each point. It can be empty.
+    pub fn smooth(&self, streamline: &[Point], scalars: &[f32]) -> (Points, Vec<f32>) {
+        let (mut points, scalars) = match self.method {
+            Smoothing::MovingAverage { radius } => {
+                (moving_average(streamline, radius), scalars.to_vec())
+            }
+            Smoothing::Gaussian { sigma } => (gaussian(streamline, sigma), scalars.to_vec()),
+            Smoothing::BSpline { smoothing, nb_points } => {
+                let (streamline, scalars) = match nb_points {
+                    Some(nb_points) => resample_with_scalars(streamline, scalars, nb_points),
+                    None => (streamline.to_vec(), scalars.to_vec()),
+                };
+                (bspline(&streamline, smoothing), scalars)
+            }
+        };
+
+        if self.keep_endpoints && !streamline.is_empty() {
+            let last = points.len() - 1;
+            points[0] = streamline[0];
+            points[last] = streamline[streamline.len() - 1];
+        }
+        (points, scalars)
+    }
+
+    /// Smooth a streamline read by `Reader`. The properties are not modified.
+    pub fn smooth_item(&self, item: TractogramItem) -> TractogramItem {
+        let (streamline, scalars, properties) = item;
+        let (streamline, data) = self.smooth(&streamline, &scalars.data);
+        let mut scalars = ArraySequence::with_capacity(data.len());
+        scalars.extend(data);
+        (streamline, scalars, properties)
+    }
+
+    /// Smooth all streamlines of `tractogram`. The empty streamlines are kept.
+    pub fn smooth_tractogram(&self, tractogram: &Tractogram) -> Tractogram {
+        let mut lengths = Vec::with_capacity(tractogram.streamlines.len());
+        let mut points = Vec::with_capacity(tractogram.streamlines.data.len());
+        let (mut scalars_lengths, mut scalars) = (vec![], vec![]);
+        for (streamline, streamline_scalars, _) in tractogram {
+            let (new_points, new_scalars) = self.smooth(streamline, streamline_scalars);
+            lengths.push(new_points.len());
+            points.extend(new_points);
+            scalars_lengths.push(new_scalars.len());
+            scalars.extend(new_scalars);
+        }
+
+        let scalars = if tractogram.scalars.is_empty() {
+            ArraySequence::empty()
+        } else {
+            ArraySequence::new(scalars_lengths, scalars)
+        };
+        let streamlines = Streamlines::new(lengths, points);
+        Tractogram::new(streamlines, scalars, tractogram.properties.clone())
+    }
+}
+
+fn moving_average(streamline: &[Point], radius: usize) -> Points {
+    let n = streamline.len();
+    (0..n)
+        .map(|i| {
+            let window = &streamline[i.saturating_sub(radius)..(i + radius + 1).min(n)];
+            let sum = window.iter().map(|p| p.coords).sum::<Vector3<f32>>();
+            Point::from(sum / window.len() as f32)
+        })
+        .collect()
+}
+
+fn gaussian(streamline: &[Point], sigma: f32) -> Points {
+    if sigma <= 0.0 {
+        return streamline.to_vec();
+    }
+
+    let arc = arc_lengths(streamline);
+    let cutoff = 3.0 * sigma;
+    let mut points = Vec::with_capacity(streamline.len());
+    for &s in &arc {
+        let mut sum = Vector3::zeros();
+        let mut sum_weights = 0.0;
+        for (p, &t) in streamline.iter().zip(&arc) {
+            let d = t - s;
+            if d.abs() <= cutoff {
+                let w = (-0.5 * (d / sigma).powi(2)).exp();
+                sum += p.coords * w;
+                sum_weights += w;
+            }
+        }
+        points.push(Point::from(sum / sum_weights));
+    }
+    points
+}
+
+/// Penalized least square fit of a clamped cubic B-spline, evaluated at the chord-length
+/// parameters of the original points.
+fn bspline(streamline: &[Point], smoothing: f32) -> Points {
+    let n = streamline.len();
+    if n < 4 {
+        return streamline.to_vec();
+    }
+
+    let arc = arc_lengths(streamline);
+    let total = arc[n - 1];
+    if total == 0.0 {
+        return streamline.to_vec();
+    }
+    let params = arc.iter().map(|&s| (s / total) as f64).collect::<Vec<_>>();
+
+    let nb_control = (n / 3 + 4).min(n).min(MAX_CONTROL_POINTS);
+    let knots = clamped_knots(nb_control);
+    let mut basis = DMatrix::<f64>::zeros(n, nb_control);
+    for (i, &u) in params.iter().enumerate() {
+        for (j, b) in cubic_basis(u, &knots).into_iter().enumerate() {
+            basis[(i, j)] = b;
+        }
+    }
+
+    // Second order differences of the control points
+    let mut differences = DMatrix::<f64>::zeros(nb_control - 2, nb_control);
+    for i in 0..nb_control - 2 {
+        differences[(i, i)] = 1.0;
+        differences[(i, i + 1)] = -2.0;
+        differences[(i, i + 2)] = 1.0;
+    }
+
+    let basis_t = basis.transpose();
+    let system = &basis_t * &basis + differences.transpose() * &differences * smoothing as f64;
+    let coords = DMatrix::<f64>::from_fn(n, 3, |i, j| streamline[i][j] as f64);
+    let control = match system.cholesky() {
+        Some(cholesky) => cholesky.solve(&(&basis_t * coords)),
+        None => return streamline.to_vec(),
+    };
+
+    let fitted = basis * control;
+    (0..n)
+        .map(|i| Point::new(fitted[(i, 0)] as f32, fitted[(i, 1)] as f32, fitted[(i, 2)] as f32))
+        .collect()
+}
+
+/// Knot vector of a clamped cubic B-spline with `nb_control` uniformly spaced control points.
+fn clamped_knots(nb_control: usize) -> Vec<f64> {
+    let nb_spans = nb_control - 3;
+    let mut knots = vec![0.0; 4];
+    knots.extend((1..nb_spans).map(|i| i as f64 / nb_spans as f64));
+    knots.extend([1.0; 4]);
+    knots
+}
+
+/// Values of all cubic B-spline basis functions at `u`, using the Cox-de Boor recursion.
+fn cubic_basis(u: f64, knots: &[f64]) -> Vec<f64> {
+    let nb_control = knots.len() - 4;
+
+    // Degree 0. The last span is closed to include u == 1.
+    let mut basis = (0..knots.len() - 1)
+        .map(|i| {
+            let last_span = knots[i + 1] == 1.0 && knots[i] < 1.0;
+            let inside = knots[i] <= u && (u < knots[i + 1] || (last_span && u == 1.0));
+            if inside {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for degree in 1..=3 {
+        for i in 0..knots.len() - 1 - degree {
+            let left = knots[i + degree] - knots[i];
+            let right = knots[i + degree + 1] - knots[i + 1];
+            let mut value = 0.0;
+            if left > 0.0 {
+                value += (u - knots[i]) / left * basis[i];
+            }
+            if right > 0.0 {
+                value += (knots[i + degree + 1] - u) / right * basis[i + 1];
+            }
+            basis[i] = value;
+        }
+    }
+    basis.truncate(nb_control);
+    basis
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    iter::Peekable,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{Header, Reader, TractogramItem, Writer};
+
+/// What should be balanced between the chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Balance {
+    Streamlines,
+    Points,
+}
+
+/// Split the trk file `input` in `nb_chunks` trk files of roughly the same number of streamlines
+/// or points, and returns their paths, as given by `output`.
+///
+/// The chunks contain consecutive streamlines, thus concatenating them in order, e.g., with
+/// `reassemble_trk`, gives back the original file. The points are copied as they are on disk.
+pub fn split_into_chunks<P, F>(
+    input: P,
+    nb_chunks: usize,
+    balance: Balance,
+    mut output: F,
+) -> Result<Vec<PathBuf>>
+where
+    P: AsRef<Path>,
+    F: FnMut(usize) -> PathBuf,
+{
+    let input = input.as_ref();
+    if nb_chunks == 0 {
+        bail!("Can't split in 0 chunks");
+    }
+
+    // A first pass is needed to know the number of points, and `n_count` is optional anyway
+    let weights = Reader::new(input)?
+        .into_streamlines_iter()
+        .map(|streamline| match balance {
+            Balance::Streamlines => 1,
+            Balance::Points => streamline.len(),
+        })
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<usize>().max(1);
+
+    let reader = Reader::new(input)?.raw();
+    let paths = (0..nb_chunks).map(&mut output).collect::<Vec<_>>();
+    let mut writers =
+        paths.iter().map(|path| reader.build_writer(path)).collect::<Result<Vec<_>>>()?;
+    let mut done = 0;
+    for (item, weight) in reader.zip(weights) {
+        // Assign the streamline to the chunk containing its middle
+        let chunk = ((2 * done + weight) * nb_chunks / (2 * total)).min(nb_chunks - 1);
+        writers[chunk].write(item);
+        done += weight;
+    }
+    Ok(paths)
+}
+
+/// Split the trk file `input` in one trk file per value of the property `property`, e.g., a
+/// cluster id, and returns the values with the paths of their file, as given by `output`.
+///
+/// If `index_property` is given, it's added to the output files and contains the index of each
+/// streamline in `input`, so that `reassemble_trk` can restore the original order. The indices are
+/// stored as `f32`, thus they are exact up to 2^24 streamlines.
+pub fn split_by_property<P, F>(
+    input: P,
+    property: &str,
+    index_property: Option<&str>,
+    mut output: F,
+) -> Result<Vec<(f32, PathBuf)>>
+where
+    P: AsRef<Path>,
+    F: FnMut(f32) -> PathBuf,
+{
+    let input = input.as_ref();
+    let reader = Reader::new(input)?.raw();
+    let column = match reader.header.properties_groups().into_iter().find(|(n, _)| n == property) {
+        Some((_, range)) if range.len() == 1 => range.start,
+        Some(_) => bail!("The property {:?} must have a single value", property),
+        None => bail!("There's no property named {:?} in {:?}", property, input),
+    };
+    let mut header = reader.header.clone();
+    if let Some(name) = index_property {
+        header.add_property(name).with_context(|| format!("Can't add property {:?}", name))?;
+    }
+
+    // Sorted by value, to return the files in a stable order
+    let mut writers: BTreeMap<u32, (f32, PathBuf, Writer)> = BTreeMap::new();
+    for (idx, (streamline, scalars, mut properties)) in reader.enumerate() {
+        let value = properties[column];
+        if value.is_nan() {
+            bail!("Streamline {} has no value for {:?}", idx, property);
+        }
+        let (_, _, writer) = match writers.entry(sortable_key(value)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = output(value);
+                let writer = Writer::new(&path, Some(&header))?.raw();
+                entry.insert((value, path, writer))
+            }
+        };
+        if index_property.is_some() {
+            properties.push(idx as f32);
+        }
+        writer.write((streamline, scalars, properties));
+    }
+    Ok(writers.into_values().map(|(value, path, _)| (value, path)).collect())
+}
+
+/// Map a `f32` to a `u32` with the same order, to be used as a key.
+fn sortable_key(value: f32) -> u32 {
+    let bits = (value + 0.0).to_bits();
+    if bits >> 31 == 1 {
+        !bits
+    } else {
+        bits | (1 << 31)
+    }
+}
+
+/// Reassemble trk files written by `split_into_chunks` or `split_by_property` in a single trk
+/// file `output`, one streamline at a time, and returns its header.
+///
+/// If `index_property` is given, the streamlines are written in the order of this property, which
+/// is then removed. Otherwise, the files are concatenated in order. All files must have the same
+/// header.
+pub fn reassemble_trk<P: AsRef<Path>, Q: AsRef<Path>>(
+    inputs: &[P],
+    output: Q,
+    index_property: Option<&str>,
+) -> Result<Header> {
+    let readers = inputs.iter().map(|p| Ok(Reader::new(p)?.raw())).collect::<Result<Vec<_>>>()?;
+    let Some(first) = readers.first() else {
+        bail!("There's nothing to reassemble.");
+    };
+    let mut header = first.header.clone();
+    for (reader, input) in readers.iter().zip(inputs) {
+        if reader.header.raw_header().vox_to_ras != header.raw_header().vox_to_ras
+            || reader.header.scalars_groups() != header.scalars_groups()
+            || reader.header.properties_groups() != header.properties_groups()
+        {
+            bail!("The header of {:?} is not the same as the others", input.as_ref());
+        }
+    }
+    let column = match index_property {
+        Some(name) => Some(header.remove_property(name)?.start),
+        None => None,
+    };
+
+    let mut writer = Writer::new(&output, Some(&header))?.raw();
+    let mut nb_streamlines = 0;
+    match column {
+        None => {
+            for reader in readers {
+                for item in reader {
+                    writer.write(item);
+                    nb_streamlines += 1;
+                }
+            }
+        }
+        Some(column) => {
+            // Each file is already sorted, thus it's a k-way merge
+            let mut iters = readers.into_iter().map(|r| r.peekable()).collect::<Vec<_>>();
+            while let Some(next) = next_by_index(&mut iters, column) {
+                let (streamline, scalars, mut properties) = next;
+                properties.remove(column);
+                writer.write((streamline, scalars, properties));
+                nb_streamlines += 1;
+            }
+        }
+    }
+    header.nb_streamlines = nb_streamlines;
+    Ok(header)
+}
+
+/// Returns the next item with the smallest index in `column`.
+fn next_by_index(iters: &mut [Peekable<Reader>], column: usize) -> Option<TractogramItem> {
+    let mut best: Option<(usize, f32)> = None;
+    for (i, iter) in iters.iter_mut().enumerate() {
+        if let Some((_, _, properties)) = iter.peek() {
+            let index = properties[column];
+            if best.is_none_or(|(_, best)| index < best) {
+                best = Some((i, index));
+            }
+        }
+    }
+    best.and_then(|(i, _)| iters[i].next())
+}
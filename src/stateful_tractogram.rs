@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use nalgebra::{Vector3, Vector4};
+
+use crate::{Affine4, ArraySequence, Header, Reader, StridedView, Tractogram, Writer};
+
+/// Coordinate space of the points of a `StatefulTractogram`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Space {
+    /// World coordinates, in RAS+ mm. This is the space returned by `Reader::new`.
+    RasMm,
+
+    /// Voxel coordinates scaled by the voxel size, in mm.
+    VoxMm,
+
+    /// Voxel coordinates (indices) of the reference image.
+    Vox,
+}
+
+/// Position of the origin of the voxel grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// The voxel coordinates refer to the center of the voxels, like NIfTI, MRtrix and Dipy.
+    Center,
+
+    /// The voxel coordinates refer to the corner of the voxels, like TrackVis.
+    Corner,
+}
+
+/// A tractogram that knows in which space its points are.
+///
+/// The spaces are all defined relative to the voxel grid of the reference image, as described by
+/// the `vox_to_ras` field of the header. Whatever the current space and origin, `save` always
+/// writes a valid trk file.
+#[derive(Clone)]
+pub struct StatefulTractogram {
+    tractogram: Tractogram,
+    header: Header,
+    space: Space,
+    origin: Origin,
+}
+
+impl StatefulTractogram {
+    /// Wrap a `tractogram` whose points are in `space`, with `origin`, relative to the reference
+    /// image described by `header`.
+    ///
+    /// Fails if the `vox_to_ras` affine of `header` is not invertible.
+    pub fn new(
+        tractogram: Tractogram,
+        header: Header,
+        space: Space,
+        origin: Origin,
+    ) -> Result<StatefulTractogram> {
+        if header.raw_header().get_voxel_to_rasmm().try_inverse().is_none() {
+            bail!("The voxel to RAS+ mm affine of the header is not invertible.");
+        }
+        Ok(StatefulTractogram { tractogram, header, space, origin })
+    }
+
+    /// Load a trk file. The points are in `Space::RasMm`, with `Origin::Center`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<StatefulTractogram> {
+        let mut reader = Reader::new(path)?;
+        let tractogram = reader.tractogram();
+        StatefulTractogram::new(tractogram, reader.header, Space::RasMm, Origin::Center)
+    }
+
+    /// Save to a trk file, whatever the current space and origin.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = Writer::new(path, Some(&self.header))?;
+        writer.apply_affine(&self.affine_to_rasmm());
+        for item in &self.tractogram {
+            writer.write(item);
+        }
+        Ok(())
+    }
+
+    pub fn tractogram(&self) -> &Tractogram {
+        &self.tractogram
+    }
+
+    /// Mutable access to the tractogram. The points are expected to stay in the current space.
+    pub fn tractogram_mut(&mut self) -> &mut Tractogram {
+        &mut self.tractogram
+    }
+
+    pub fn into_tractogram(self) -> Tractogram {
+        self.tractogram
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn space(&self) -> Space {
+        self.space
+    }
+
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// See `Tractogram::scalar`.
+    pub fn scalar(&self, name: &str) -> Result<StridedView<'_>> {
+        self.tractogram.scalar(&self.header, name)
+    }
+
+    /// See `Tractogram::set_scalar`.
+    pub fn set_scalar(&mut self, name: &str, values: &ArraySequence<f32>) -> Result<()> {
+        self.tractogram.set_scalar(&self.header, name, values)
+    }
+
+    /// See `Tractogram::add_scalar`.
+    pub fn add_scalar(&mut self, name: &str, values: ArraySequence<f32>) -> Result<()> {
+        self.tractogram.add_scalar(&mut self.header, name, values)
+    }
+
+    /// See `Tractogram::remove_scalar`.
+    pub fn remove_scalar(&mut self, name: &str) -> Result<()> {
+        self.tractogram.remove_scalar(&mut self.header, name)
+    }
+
+    /// See `Tractogram::property`.
+    pub fn property(&self, name: &str) -> Result<StridedView<'_>> {
+        self.tractogram.property(&self.header, name)
+    }
+
+    /// See `Tractogram::set_property`.
+    pub fn set_property(&mut self, name: &str, values: &ArraySequence<f32>) -> Result<()> {
+        self.tractogram.set_property(&self.header, name, values)
+    }
+
+    /// See `Tractogram::add_property`.
+    pub fn add_property(&mut self, name: &str, values: ArraySequence<f32>) -> Result<()> {
+        self.tractogram.add_property(&mut self.header, name, values)
+    }
+
+    /// See `Tractogram::remove_property`.
+    pub fn remove_property(&mut self, name: &str) -> Result<()> {
+        self.tractogram.remove_property(&mut self.header, name)
+    }
+
+    /// Affine mapping the points, in their current space and origin, to RAS+ mm with the origin at
+    /// the center of the voxels.
+    pub fn affine_to_rasmm(&self) -> Affine4 {
+        self.header.raw_header().get_voxel_to_rasmm() * self.to_voxel(self.space, self.origin)
+    }
+
+    pub fn to_rasmm(&mut self) {
+        self.to_space(Space::RasMm);
+    }
+
+    pub fn to_voxmm(&mut self) {
+        self.to_space(Space::VoxMm);
+    }
+
+    pub fn to_vox(&mut self) {
+        self.to_space(Space::Vox);
+    }
+
+    pub fn to_center(&mut self) {
+        self.to_origin(Origin::Center);
+    }
+
+    pub fn to_corner(&mut self) {
+        self.to_origin(Origin::Corner);
+    }
+
+    /// Move all points to `space`, keeping the current origin.
+    pub fn to_space(&mut self, space: Space) {
+        self.convert(space, self.origin);
+    }
+
+    /// Move all points to `origin`, keeping the current space.
+    pub fn to_origin(&mut self, origin: Origin) {
+        self.convert(self.space, origin);
+    }
+
+    fn convert(&mut self, space: Space, origin: Origin) {
+        if space == self.space && origin == self.origin {
+            return;
+        }
+        let affine = self.to_voxel(space, origin).try_inverse().unwrap() // Checked in `new`
+            * self.to_voxel(self.space, self.origin);
+        self.tractogram.apply_affine(&affine);
+        self.space = space;
+        self.origin = origin;
+    }
+
+    /// Affine mapping the points in `space` and `origin` to the voxel indices of the reference
+    /// image (voxel centers).
+    fn to_voxel(&self, space: Space, origin: Origin) -> Affine4 {
+        let voxel_to_rasmm = self.header.raw_header().get_voxel_to_rasmm();
+        let to_voxel = match space {
+            Space::RasMm => voxel_to_rasmm.try_inverse().unwrap(), // Checked in `new`
+            Space::VoxMm => {
+                let voxel_size = |i| voxel_to_rasmm.fixed_view::<3, 1>(0, i).norm();
+                Affine4::from_diagonal(&Vector4::new(
+                    1.0 / voxel_size(0),
+                    1.0 / voxel_size(1),
+                    1.0 / voxel_size(2),
+                    1.0,
+                ))
+            }
+            Space::Vox => Affine4::identity(),
+        };
+        match origin {
+            Origin::Center => to_voxel,
+            Origin::Corner => Affine4::new_translation(&Vector3::repeat(-0.5)) * to_voxel,
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Affine4, Header, Point, Reader, Tractogram};
+
+/// Minimum, mean and maximum of a set of values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValueStats {
+    pub count: usize,
+    pub min: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+/// Statistics of the lengths of the streamlines, in mm.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LengthStats {
+    pub min: f32,
+    pub mean: f32,
+    pub median: f32,
+    pub max: f32,
+
+    /// Edges of the bins of the histogram, from `min` to `max`. There's one more edge than counts.
+    pub bin_edges: Vec<f32>,
+    pub counts: Vec<usize>,
+}
+
+/// Axis-aligned bounding box of the points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+/// Summary of a tractogram, for quality control.
+///
+/// The multi-valued scalars and properties have one entry per value, named `name_0`, `name_1`, ...
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TractogramStats {
+    pub nb_streamlines: usize,
+
+    /// Number of streamlines declared in the header (`n_count`). It may be 0 if it's unknown.
+    pub header_nb_streamlines: usize,
+    pub nb_points: usize,
+
+    /// Number of streamlines with 0 or 1 point.
+    pub nb_degenerate: usize,
+
+    /// `None` if there's no streamline.
+    pub length: Option<LengthStats>,
+
+    /// Distances between consecutive points, in mm. `None` if there's no segment.
+    pub step_size: Option<ValueStats>,
+
+    /// Bounding box in RAS+ mm. `None` if there's no point.
+    pub bounds_rasmm: Option<Bounds>,
+
+    /// Bounding box in voxel coordinates of the reference image, with the origin at the center of
+    /// the voxels. `None` if there's no point or if the `vox_to_ras` affine is not invertible.
+    pub bounds_vox: Option<Bounds>,
+
+    pub scalars: BTreeMap<String, ValueStats>,
+    pub properties: BTreeMap<String, ValueStats>,
+}
+
+impl TractogramStats {
+    /// Compute the statistics of a trk file in a single pass, without loading it. The histogram of
+    /// the lengths has `nb_bins` bins.
+    pub fn from_reader(reader: Reader, nb_bins: usize) -> TractogramStats {
+        let mut builder = StatsBuilder::new(&reader.header);
+        for (streamline, scalars, properties) in reader {
+            builder.add(&streamline, &scalars.data, &properties);
+        }
+        builder.finish(nb_bins)
+    }
+
+    /// Compute the statistics of a tractogram, whose points are in RAS+ mm.
+    pub fn from_tractogram(header: &Header, tractogram: &Tractogram, nb_bins: usize) -> Self {
+        let mut builder = StatsBuilder::new(header);
+        for (streamline, scalars, properties) in tractogram {
+            builder.add(streamline, scalars, properties);
+        }
+        builder.finish(nb_bins)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    min: f32,
+    max: f32,
+}
+
+impl Accumulator {
+    fn new() -> Accumulator {
+        Accumulator { count: 0, sum: 0.0, min: f32::INFINITY, max: f32::NEG_INFINITY }
+    }
+
+    fn add(&mut self, value: f32) {
+        if value.is_finite() {
+            self.count += 1;
+            self.sum += value as f64;
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+    }
+
+    fn finish(&self) -> Option<ValueStats> {
+        let mean = (self.sum / self.count as f64) as f32;
+        (self.count > 0).then_some(ValueStats {
+            count: self.count,
+            min: self.min,
+            mean,
+            max: self.max,
+        })
+    }
+}
+
+struct BoundsAccumulator {
+    min: Point,
+    max: Point,
+}
+
+impl BoundsAccumulator {
+    fn new() -> BoundsAccumulator {
+        BoundsAccumulator {
+            min: Point::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    fn add(&mut self, p: &Point) {
+        self.min = self.min.inf(p);
+        self.max = self.max.sup(p);
+    }
+
+    fn finish(&self) -> Option<Bounds> {
+        (self.min.x <= self.max.x)
+            .then_some(Bounds { min: self.min.coords.into(), max: self.max.coords.into() })
+    }
+}
+
+struct StatsBuilder {
+    header_nb_streamlines: usize,
+    nb_points: usize,
+    nb_degenerate: usize,
+    lengths: Vec<f32>,
+    step_size: Accumulator,
+    bounds_rasmm: BoundsAccumulator,
+    to_voxel: Option<Affine4>,
+    bounds_vox: BoundsAccumulator,
+    scalars: Vec<(String, Accumulator)>,
+    properties: Vec<(String, Accumulator)>,
+}
+
+impl StatsBuilder {
+    fn new(header: &Header) -> StatsBuilder {
+        let columns = |groups: Vec<(String, std::ops::Range<usize>)>| {
+            groups
+                .into_iter()
+                .flat_map(|(name, range)| match range.len() {
+                    1 => vec![(name, Accumulator::new())],
+                    n => (0..n).map(|c| (format!("{}_{}", name, c), Accumulator::new())).collect(),
+                })
+                .collect()
+        };
+        StatsBuilder {
+            header_nb_streamlines: header.nb_streamlines,
+            nb_points: 0,
+            nb_degenerate: 0,
+            lengths: vec![],
+            step_size: Accumulator::new(),
+            bounds_rasmm: BoundsAccumulator::new(),
+            to_voxel: header.raw_header().get_voxel_to_rasmm().try_inverse(),
+            bounds_vox: BoundsAccumulator::new(),
+            scalars: columns(header.scalars_groups()),
+            properties: columns(header.properties_groups()),
+        }
+    }
+
+    fn add(&mut self, streamline: &[Point], scalars: &[f32], properties: &[f32]) {
+        self.nb_points += streamline.len();
+        if streamline.len() < 2 {
+            self.nb_degenerate += 1;
+        }
+
+        let mut length = 0.0;
+        for segment in streamline.windows(2) {
+            let step = (segment[1] - segment[0]).norm();
+            self.step_size.add(step);
+            length += step;
+        }
+        self.lengths.push(length);
+
+        for p in streamline {
+            self.bounds_rasmm.add(p);
+            if let Some(to_voxel) = &self.to_voxel {
+                self.bounds_vox.add(&to_voxel.transform_point(p));
+            }
+        }
+
+        if !self.scalars.is_empty() {
+            for values in scalars.chunks(self.scalars.len()) {
+                for ((_, accumulator), &value) in self.scalars.iter_mut().zip(values) {
+                    accumulator.add(value);
+                }
+            }
+        }
+        for ((_, accumulator), &value) in self.properties.iter_mut().zip(properties) {
+            accumulator.add(value);
+        }
+    }
+
+    fn finish(mut self, nb_bins: usize) -> TractogramStats {
+        let stats = |columns: Vec<(String, Accumulator)>| {
+            columns
+                .into_iter()
+                .filter_map(|(name, accumulator)| Some((name, accumulator.finish()?)))
+                .collect()
+        };
+        TractogramStats {
+            nb_streamlines: self.lengths.len(),
+            header_nb_streamlines: self.header_nb_streamlines,
+            nb_points: self.nb_points,
+            nb_degenerate: self.nb_degenerate,
+            length: length_stats(&mut self.lengths, nb_bins),
+            step_size: self.step_size.finish(),
+            bounds_rasmm: self.bounds_rasmm.finish(),
+            bounds_vox: self.bounds_vox.finish(),
+            scalars: stats(self.scalars),
+            properties: stats(self.properties),
+        }
+    }
+}
+
+fn length_stats(lengths: &mut [f32], nb_bins: usize) -> Option<LengthStats> {
+    if lengths.is_empty() {
+        return None;
+    }
+    lengths.sort_by(f32::total_cmp);
+    let n = lengths.len();
+    let (min, max) = (lengths[0], lengths[n - 1]);
+    let mean = (lengths.iter().map(|&l| l as f64).sum::<f64>() / n as f64) as f32;
+    let median =
+        if n % 2 == 1 { lengths[n / 2] } else { (lengths[n / 2 - 1] + lengths[n / 2]) / 2.0 };
+
+    let nb_bins = nb_bins.max(1);
+    let width = (max - min) / nb_bins as f32;
+    let bin_edges = (0..=nb_bins).map(|i| min + width * i as f32).collect();
+    let mut counts = vec![0; nb_bins];
+    for &length in lengths.iter() {
+        let bin = if width > 0.0 { ((length - min) / width) as usize } else { 0 };
+        counts[bin.min(nb_bins - 1)] += 1;
+    }
+    Some(LengthStats { min, mean, median, max, bin_edges, counts })
+}
@@ -0,0 +1,173 @@
+use crate::{Point, Points, Streamlines};
+
+/// Number of points used to compare the direction of two streamlines.
+const NB_POINTS_ORIENTATION: usize = 20;
+
+/// Returns the length of `streamline`, that is, the sum of the length of all its segments.
+pub fn length(streamline: &[Point]) -> f32 {
+    streamline.windows(2).map(|p| (p[1] - p[0]).norm()).sum()
+}
+
+/// Returns the cumulative length of `streamline` at each of its points.
+///
+/// The first element is always 0 and the last one is the length of the streamline.
+pub fn arc_lengths(streamline: &[Point]) -> Vec<f32> {
+    let mut lengths = Vec::with_capacity(streamline.len());
+    let mut total = 0.0;
+    if !streamline.is_empty() {
+        lengths.push(total);
+    }
+    for p in streamline.windows(2) {
+        total += (p[1] - p[0]).norm();
+        lengths.push(total);
+    }
+    lengths
+}
+
+/// Resample `streamline` to `nb_points` points equally spaced along its length.
+///
+/// The first and last points are kept as they are. Panics if `nb_points` < 2.
+pub fn resample(streamline: &[Point], nb_points: usize) -> Points {
+    resample_with_scalars(streamline, &[], nb_points).0
+}
+
+/// Resample `streamline` to `nb_points` points equally spaced along its length, linearly
+/// interpolating the per-point `scalars` at the same positions.
+///
+/// `scalars` is interleaved, like in a `TractogramItem`, so it must contain the same number of
+/// values for each point. It can be empty. Panics if `nb_points` < 2.
+pub fn resample_with_scalars(
+    streamline: &[Point],
+    scalars: &[f32],
+    nb_points: usize,
+) -> (Points, Vec<f32>) {
+    if nb_points < 2 {
+        panic!("Can't resample a streamline to less than 2 points.");
+    }
+    if streamline.is_empty() {
+        return (vec![], vec![]);
+    }
+
+    let nb_scalars = scalars.len() / streamline.len();
+    let arc = arc_lengths(streamline);
+    let total = *arc.last().unwrap();
+
+    let mut points = Vec::with_capacity(nb_points);
+    let mut new_scalars = Vec::with_capacity(nb_points * nb_scalars);
+    if streamline.len() == 1 || total == 0.0 {
+        for _ in 0..nb_points {
+            points.push(streamline[0]);
+            new_scalars.extend_from_slice(&scalars[..nb_scalars]);
+        }
+        return (points, new_scalars);
+    }
+
+    let mut segment = 0;
+    for i in 0..nb_points {
+        let target = total * i as f32 / (nb_points - 1) as f32;
+        while segment + 2 < streamline.len() && arc[segment + 1] < target {
+            segment += 1;
+        }
+
+        let segment_length = arc[segment + 1] - arc[segment];
+        let ratio = if i == nb_points - 1 {
+            1.0
+        } else if segment_length > 0.0 {
+            ((target - arc[segment]) / segment_length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let (a, b) = (streamline[segment], streamline[segment + 1]);
+        points.push(a + (b - a) * ratio);
+        for s in 0..nb_scalars {
+            let sa = scalars[segment * nb_scalars + s];
+            let sb = scalars[(segment + 1) * nb_scalars + s];
+            new_scalars.push(sa + (sb - sa) * ratio);
+        }
+    }
+    (points, new_scalars)
+}
+
+/// Returns the mean distance between the corresponding points of `a` and `b` (direct), and
+/// between the points of `a` and the points of `b` in reverse order (flipped).
+///
+/// Panics if `a` and `b` don't have the same number of points.
+pub fn direct_flip_distances(a: &[Point], b: &[Point]) -> (f32, f32) {
+    if a.len() != b.len() {
+        panic!("Streamlines must have the same number of points ({} != {}).", a.len(), b.len());
+    }
+    if a.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let nb = a.len() as f32;
+    let direct = a.iter().zip(b).map(|(p1, p2)| (p1 - p2).norm()).sum::<f32>() / nb;
+    let flipped = a.iter().zip(b.iter().rev()).map(|(p1, p2)| (p1 - p2).norm()).sum::<f32>() / nb;
+    (direct, flipped)
+}
+
+/// Minimum average Direct-Flip (MDF) distance between `a` and `b`.
+///
+/// Panics if `a` and `b` don't have the same number of points.
+pub fn mdf(a: &[Point], b: &[Point]) -> f32 {
+    let (direct, flipped) = direct_flip_distances(a, b);
+    direct.min(flipped)
+}
+
+/// Reverse the order of the points of `streamline` and of their per-point `scalars`, in place.
+///
+/// `scalars` is interleaved, like in a `TractogramItem`, so it must contain the same number of
+/// values for each point. It can be empty.
+pub fn flip(streamline: &mut [Point], scalars: &mut [f32]) {
+    streamline.reverse();
+    if !scalars.is_empty() {
+        let nb_scalars = scalars.len() / streamline.len();
+        scalars.reverse();
+        for point_scalars in scalars.chunks_mut(nb_scalars) {
+            point_scalars.reverse();
+        }
+    }
+}
+
+/// Returns `true` if `streamline` is closer to `reference` when its points are reversed.
+///
+/// Both streamlines are resampled to the same number of points before being compared, thus they
+/// don't need to have the same number of points.
+pub fn is_flipped(streamline: &[Point], reference: &[Point]) -> bool {
+    if streamline.is_empty() || reference.is_empty() {
+        return false;
+    }
+    let (direct, flipped) = direct_flip_distances(
+        &resample(reference, NB_POINTS_ORIENTATION),
+        &resample(streamline, NB_POINTS_ORIENTATION),
+    );
+    flipped < direct
+}
+
+/// Mean streamline of `streamlines`, once they are all resampled to `nb_points` points and
+/// oriented in the same direction as the first one.
+///
+/// Returns an empty vector if there's no streamlines.
+pub fn centroid(streamlines: &Streamlines, nb_points: usize) -> Points {
+    let mut iter = streamlines.iter().filter(|s| !s.is_empty());
+    let reference = match iter.next() {
+        Some(first) => resample(first, nb_points),
+        None => return vec![],
+    };
+
+    let mut sum = reference.iter().map(|p| p.coords).collect::<Vec<_>>();
+    let mut nb = 1.0;
+    for streamline in iter {
+        let mut streamline = resample(streamline, nb_points);
+        let (direct, flipped) = direct_flip_distances(&reference, &streamline);
+        if flipped < direct {
+            streamline.reverse();
+        }
+        for (s, p) in sum.iter_mut().zip(&streamline) {
+            *s += p.coords;
+        }
+        nb += 1.0;
+    }
+    sum.into_iter().map(|s| Point::from(s / nb)).collect()
+}
@@ -0,0 +1,111 @@
+use anyhow::{bail, Result};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::{Reader, TractogramItem};
+
+/// How the streamlines are selected by `Subsample`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling<'a> {
+    /// Keep exactly this number of streamlines, or all of them if there's not enough.
+    Number(usize),
+
+    /// Keep each streamline with this probability, in percent.
+    Percent(f32),
+
+    /// Keep each streamline with a probability of `scale` times the value of `property`, clamped
+    /// to [0, 1].
+    Weighted { property: &'a str, scale: f32 },
+}
+
+/// Iterator on a random subset of the streamlines of a `Reader`, in their original order.
+///
+/// The selection only depends on `seed`, thus it's reproducible. `Sampling::Number` needs to read
+/// the whole file and to keep the selected streamlines in memory (reservoir sampling) before
+/// returning the first one. The other samplings return the streamlines as they are read.
+///
+/// ```no_run
+/// # use trk_io::{subsample::{Sampling, Subsample}, Reader};
+/// let reader = Reader::new("full_brain.trk").unwrap();
+/// let mut writer = reader.build_writer("subsampled.trk").unwrap();
+/// for item in Subsample::new(reader, Sampling::Number(1000), 42).unwrap() {
+///     writer.write(item);
+/// }
+/// ```
+pub struct Subsample {
+    reader: Reader,
+    rng: SmallRng,
+    selection: Selection,
+}
+
+enum Selection {
+    Reservoir(std::vec::IntoIter<TractogramItem>),
+    Probability(f32),
+    Weighted { column: usize, scale: f32 },
+}
+
+impl Subsample {
+    pub fn new(mut reader: Reader, sampling: Sampling, seed: u64) -> Result<Subsample> {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let selection = match sampling {
+            Sampling::Number(number) => {
+                Selection::Reservoir(reservoir(&mut reader, number, &mut rng).into_iter())
+            }
+            Sampling::Percent(percent) => {
+                if !(0.0..=100.0).contains(&percent) {
+                    bail!("The percentage must be in [0, 100]. Got {}", percent);
+                }
+                Selection::Probability(percent / 100.0)
+            }
+            Sampling::Weighted { property, scale } => {
+                let groups = reader.header.properties_groups();
+                let column = match groups.into_iter().find(|(name, _)| name == property) {
+                    Some((_, range)) if range.len() == 1 => range.start,
+                    Some(_) => bail!("The property {:?} must have a single value", property),
+                    None => bail!("There's no property named {:?}", property),
+                };
+                Selection::Weighted { column, scale }
+            }
+        };
+        Ok(Subsample { reader, rng, selection })
+    }
+}
+
+/// Returns `number` randomly chosen items of `reader`, in their original order.
+fn reservoir(reader: &mut Reader, number: usize, rng: &mut SmallRng) -> Vec<TractogramItem> {
+    // `nb_streamlines` may be 0 when unknown, so it's only used to bound the allocation
+    let capacity = number.min(reader.header.nb_streamlines);
+    let mut reservoir: Vec<(usize, TractogramItem)> = Vec::with_capacity(capacity);
+    for (idx, item) in reader.enumerate() {
+        if idx < number {
+            reservoir.push((idx, item));
+        } else {
+            let j = rng.random_range(0..=idx);
+            if j < number {
+                reservoir[j] = (idx, item);
+            }
+        }
+    }
+    reservoir.sort_by_key(|(idx, _)| *idx);
+    reservoir.into_iter().map(|(_, item)| item).collect()
+}
+
+impl Iterator for Subsample {
+    type Item = TractogramItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Selection::Reservoir(items) = &mut self.selection {
+            return items.next();
+        }
+        for item in self.reader.by_ref() {
+            let probability = match self.selection {
+                Selection::Probability(probability) => probability,
+                Selection::Weighted { column, scale } => (item.2[column] * scale).clamp(0.0, 1.0),
+                Selection::Reservoir(_) => unreachable!(),
+            };
+            if self.rng.random::<f32>() < probability {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
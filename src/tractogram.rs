@@ -1,6 +1,14 @@
+use std::ops::Range;
+
+use anyhow::{bail, Result};
 use nalgebra::Point3;
 
-use crate::ArraySequence;
+use crate::{
+    array_sequence::Selection,
+    merge::{concat, MergeOptions},
+    streamline::{flip, is_flipped},
+    Affine4, ArraySequence, ArraySequenceView, Header,
+};
 
 pub type Point = Point3<f32>;
 pub type Points = Vec<Point>;
@@ -32,6 +40,496 @@ impl Tractogram {
         let properties = if self.properties.is_empty() { &[] } else { &self.properties[idx] };
         (&self.streamlines[idx], scalars, properties)
     }
+
+    /// Returns a new tractogram containing the streamlines `indices`, in this order, along with
+    /// their scalars and properties.
+    pub fn select(&self, indices: &[usize]) -> Tractogram {
+        let (mut lengths, mut points) = (Vec::with_capacity(indices.len()), vec![]);
+        let (mut scalars_lengths, mut scalars) = (vec![], vec![]);
+        let (mut properties_lengths, mut properties) = (vec![], vec![]);
+        for &idx in indices {
+            let (streamline, streamline_scalars, streamline_properties) = self.item(idx);
+            lengths.push(streamline.len());
+            points.extend_from_slice(streamline);
+            scalars_lengths.push(streamline_scalars.len());
+            scalars.extend_from_slice(streamline_scalars);
+            properties_lengths.push(streamline_properties.len());
+            properties.extend_from_slice(streamline_properties);
+        }
+
+        // Empty arrays are kept, so that all streamlines keep their scalars and properties
+        let scalars = if self.scalars.is_empty() {
+            ArraySequence::empty()
+        } else {
+            ArraySequence::new(scalars_lengths, scalars)
+        };
+        let properties = if self.properties.is_empty() {
+            ArraySequence::empty()
+        } else {
+            ArraySequence::new(properties_lengths, properties)
+        };
+        Tractogram::new(Streamlines::new(lengths, points), scalars, properties)
+    }
+
+    /// Returns a view on the values of the scalar `name`, as declared in `header`.
+    pub fn scalar(&self, header: &Header, name: &str) -> Result<StridedView<'_>> {
+        let (columns, stride) = find_columns(header.scalars_groups(), name, "scalar")?;
+        self.check_scalars(stride)?;
+        Ok(StridedView { values: &self.scalars, start: columns.start, nb: columns.len(), stride })
+    }
+
+    /// Overwrite the values of the scalar `name`, as declared in `header`. `values` must contain
+    /// the same number of values as the scalar, for each point of each streamline.
+    pub fn set_scalar(
+        &mut self,
+        header: &Header,
+        name: &str,
+        values: &ArraySequence<f32>,
+    ) -> Result<()> {
+        let (columns, stride) = find_columns(header.scalars_groups(), name, "scalar")?;
+        self.check_scalars(stride)?;
+        let nb_points = self.streamlines.iter().map(|s| s.len());
+        check_values(values, nb_points, columns.len(), "scalar", name)?;
+        set_columns(&mut self.scalars, stride, columns, values);
+        Ok(())
+    }
+
+    /// Add the scalar `name` to `self` and to `header`. `values` must contain the same number of
+    /// values, e.g., 3 for a color, for each point of each streamline.
+    pub fn add_scalar(
+        &mut self,
+        header: &mut Header,
+        name: &str,
+        values: ArraySequence<f32>,
+    ) -> Result<()> {
+        let stride = header.scalars_name.len();
+        self.check_scalars(stride)?;
+        let nb_points = self.streamlines.iter().map(|s| s.len()).collect::<Vec<_>>();
+        let nb = nb_values_per_tuple(&values, &nb_points, "scalar", name)?;
+        check_values(&values, nb_points.iter().cloned(), nb, "scalar", name)?;
+        header.add_scalar_n(name, nb)?;
+        self.scalars = append_columns(&self.scalars, stride, &values, nb, &nb_points);
+        Ok(())
+    }
+
+    /// Remove the scalar `name` from `self` and from `header`.
+    pub fn remove_scalar(&mut self, header: &mut Header, name: &str) -> Result<()> {
+        let stride = header.scalars_name.len();
+        self.check_scalars(stride)?;
+        let columns = header.remove_scalar(name)?;
+        self.scalars = remove_columns(&self.scalars, stride, columns);
+        Ok(())
+    }
+
+    /// Returns a view on the values of the property `name`, as declared in `header`.
+    pub fn property(&self, header: &Header, name: &str) -> Result<StridedView<'_>> {
+        let (columns, stride) = find_columns(header.properties_groups(), name, "property")?;
+        self.check_properties(stride)?;
+        Ok(StridedView {
+            values: &self.properties,
+            start: columns.start,
+            nb: columns.len(),
+            stride,
+        })
+    }
+
+    /// Overwrite the values of the property `name`, as declared in `header`. `values` must contain
+    /// the same number of values as the property, for each streamline.
+    pub fn set_property(
+        &mut self,
+        header: &Header,
+        name: &str,
+        values: &ArraySequence<f32>,
+    ) -> Result<()> {
+        let (columns, stride) = find_columns(header.properties_groups(), name, "property")?;
+        self.check_properties(stride)?;
+        let ones = std::iter::repeat_n(1, self.streamlines.len());
+        check_values(values, ones, columns.len(), "property", name)?;
+        set_columns(&mut self.properties, stride, columns, values);
+        Ok(())
+    }
+
+    /// Add the property `name` to `self` and to `header`. `values` must contain the same number of
+    /// values for each streamline.
+    pub fn add_property(
+        &mut self,
+        header: &mut Header,
+        name: &str,
+        values: ArraySequence<f32>,
+    ) -> Result<()> {
+        let stride = header.properties_name.len();
+        self.check_properties(stride)?;
+        let ones = vec![1; self.streamlines.len()];
+        let nb = nb_values_per_tuple(&values, &ones, "property", name)?;
+        check_values(&values, ones.iter().cloned(), nb, "property", name)?;
+        header.add_property_n(name, nb)?;
+        self.properties = append_columns(&self.properties, stride, &values, nb, &ones);
+        Ok(())
+    }
+
+    /// Remove the property `name` from `self` and from `header`.
+    pub fn remove_property(&mut self, header: &mut Header, name: &str) -> Result<()> {
+        let stride = header.properties_name.len();
+        self.check_properties(stride)?;
+        let columns = header.remove_property(name)?;
+        self.properties = remove_columns(&self.properties, stride, columns);
+        Ok(())
+    }
+
+    fn check_scalars(&self, stride: usize) -> Result<()> {
+        if self.scalars.data.len() != stride * self.streamlines.data.len() {
+            bail!("The header declares {} scalars, but the tractogram doesn't have them.", stride);
+        }
+        Ok(())
+    }
+
+    fn check_properties(&self, stride: usize) -> Result<()> {
+        if self.properties.data.len() != stride * self.streamlines.len() {
+            bail!(
+                "The header declares {} properties, but the tractogram doesn't have them.",
+                stride
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns a view on the streamlines in `range`, along with their scalars and properties.
+    pub fn view(&self, range: Range<usize>) -> TractogramView<'_> {
+        let selection = Selection::new_range(range, self.streamlines.len());
+        TractogramView { tractogram: self, selection }
+    }
+
+    /// Returns a view on the streamlines `indices`, in this order, along with their scalars and
+    /// properties. This is the zero-copy version of `select`.
+    pub fn view_indices<'a>(&'a self, indices: &'a [usize]) -> TractogramView<'a> {
+        let selection = Selection::new_indices(indices, self.streamlines.len());
+        TractogramView { tractogram: self, selection }
+    }
+
+    /// Returns an iterator on views of `chunk_size` consecutive streamlines. The last view may be
+    /// shorter.
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = TractogramView<'_>> {
+        if chunk_size == 0 {
+            panic!("The chunk size must be greater than 0");
+        }
+        let len = self.streamlines.len();
+        (0..len)
+            .step_by(chunk_size)
+            .map(move |start| self.view(start..(start + chunk_size).min(len)))
+    }
+
+    /// Concatenate tractograms, described by their headers, in a single tractogram.
+    ///
+    /// The headers must describe the same reference space. Their scalars and properties are
+    /// reconciled by name, as asked in `options`. The returned header is a copy of the first
+    /// header, with the merged scalars and properties.
+    pub fn concat(
+        parts: &[(&Header, &Tractogram)],
+        options: &MergeOptions,
+    ) -> Result<(Header, Tractogram)> {
+        concat(parts, options)
+    }
+
+    /// Transform all points with `affine`, in place.
+    pub fn apply_affine(&mut self, affine: &Affine4) {
+        for p in &mut self.streamlines.data {
+            *p = affine.transform_point(p);
+        }
+    }
+
+    /// Reverse the order of the points of the streamline `idx`, along with its scalars.
+    pub fn flip(&mut self, idx: usize) {
+        let scalars = if self.scalars.is_empty() { &mut [] } else { &mut self.scalars[idx] };
+        flip(&mut self.streamlines[idx], scalars);
+    }
+
+    /// Flip all streamlines that are closer to `reference` when their points are reversed, so that
+    /// they all start on the same side as `reference`.
+    ///
+    /// `reference` can be any streamline, e.g., a representative streamline of the bundle or its
+    /// `streamline::centroid`.
+    pub fn orient_to_reference(&mut self, reference: &[Point]) {
+        for idx in 0..self.streamlines.len() {
+            if is_flipped(&self.streamlines[idx], reference) {
+                self.flip(idx);
+            }
+        }
+    }
+
+    /// Flip all streamlines that end, but don't start, in the region of interest, so that they
+    /// all start in it.
+    ///
+    /// `contains` must return `true` if a point is in the region of interest. Streamlines that
+    /// start in the region, or that don't end in it, are left as they are.
+    pub fn orient_to_roi<F>(&mut self, contains: F)
+    where
+        F: Fn(&Point) -> bool,
+    {
+        for idx in 0..self.streamlines.len() {
+            let streamline = &self.streamlines[idx];
+            if let (Some(first), Some(last)) = (streamline.first(), streamline.last())
+                && !contains(first)
+                && contains(last)
+            {
+                self.flip(idx);
+            }
+        }
+    }
+}
+
+/// Read-only view on some streamlines of a `Tractogram`, with their scalars and properties,
+/// without copying them. Built with `Tractogram::view`, `view_indices` or `chunks`.
+#[derive(Clone, Copy)]
+pub struct TractogramView<'data> {
+    tractogram: &'data Tractogram,
+    selection: Selection<'data>,
+}
+
+impl<'data> TractogramView<'data> {
+    /// Number of streamlines.
+    pub fn len(&self) -> usize {
+        self.selection.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn item(&self, idx: usize) -> RefTractogramItem<'data> {
+        self.tractogram.item(self.selection.get(idx))
+    }
+
+    pub fn streamlines(&self) -> ArraySequenceView<'data, Point> {
+        self.view_of(&self.tractogram.streamlines)
+    }
+
+    /// The scalars of the streamlines. Like in `Tractogram`, it's empty if there's no scalar.
+    pub fn scalars(&self) -> ArraySequenceView<'data, f32> {
+        self.view_of(&self.tractogram.scalars)
+    }
+
+    /// The properties of the streamlines. Like in `Tractogram`, it's empty if there's no property.
+    pub fn properties(&self) -> ArraySequenceView<'data, f32> {
+        self.view_of(&self.tractogram.properties)
+    }
+
+    fn view_of<T>(&self, values: &'data ArraySequence<T>) -> ArraySequenceView<'data, T> {
+        match self.selection {
+            _ if values.is_empty() => values.view(0..0),
+            Selection::Range { start, end } => values.view(start..end),
+            Selection::Indices(indices) => values.view_indices(indices),
+        }
+    }
+
+    /// Returns a view on the streamlines in `range` of this view.
+    pub fn view(&self, range: Range<usize>) -> TractogramView<'data> {
+        TractogramView { tractogram: self.tractogram, selection: self.selection.sub_range(range) }
+    }
+
+    pub fn iter(&self) -> TractogramViewIterator<'data> {
+        self.into_iter()
+    }
+
+    /// Copy the viewed streamlines, scalars and properties in a new `Tractogram`.
+    pub fn to_tractogram(&self) -> Tractogram {
+        Tractogram::new(
+            self.streamlines().to_array_sequence(),
+            self.scalars().to_array_sequence(),
+            self.properties().to_array_sequence(),
+        )
+    }
+}
+
+impl<'data> IntoIterator for TractogramView<'data> {
+    type Item = RefTractogramItem<'data>;
+    type IntoIter = TractogramViewIterator<'data>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TractogramViewIterator { view: self, index: 0..self.len() }
+    }
+}
+
+impl<'data> IntoIterator for &TractogramView<'data> {
+    type Item = RefTractogramItem<'data>;
+    type IntoIter = TractogramViewIterator<'data>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).into_iter()
+    }
+}
+
+pub struct TractogramViewIterator<'data> {
+    view: TractogramView<'data>,
+    index: Range<usize>,
+}
+
+impl<'data> Iterator for TractogramViewIterator<'data> {
+    type Item = RefTractogramItem<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.index.next()?;
+        Some(self.view.item(idx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.index.size_hint()
+    }
+}
+
+impl<'data> ExactSizeIterator for TractogramViewIterator<'data> {}
+
+impl<'data> DoubleEndedIterator for TractogramViewIterator<'data> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let idx = self.index.next_back()?;
+        Some(self.view.item(idx))
+    }
+}
+
+/// View on some interleaved columns of an `ArraySequence`, e.g., a scalar or a property of a
+/// `Tractogram`.
+#[derive(Clone, Copy)]
+pub struct StridedView<'data> {
+    values: &'data ArraySequence<f32>,
+    start: usize,
+    nb: usize,
+    stride: usize,
+}
+
+impl<'data> StridedView<'data> {
+    /// Number of streamlines.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of values per point for a scalar, or per streamline for a property.
+    pub fn nb_components(&self) -> usize {
+        self.nb
+    }
+
+    /// Returns the values of the streamline `idx`, `nb_components` values per point for a scalar,
+    /// or a single array for a property.
+    pub fn get(&self, idx: usize) -> impl Iterator<Item = &'data [f32]> + use<'data> {
+        let (start, nb) = (self.start, self.nb);
+        self.values[idx].chunks(self.stride).map(move |tuple| &tuple[start..start + nb])
+    }
+
+    /// Iterate on the values of all streamlines.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = impl Iterator<Item = &'data [f32]> + use<'data>> + use<'data, '_>
+    {
+        (0..self.len()).map(|idx| self.get(idx))
+    }
+
+    /// Copy the values in a new `ArraySequence`, with one array per streamline.
+    pub fn to_array_sequence(&self) -> ArraySequence<f32> {
+        let lengths = self.values.iter().map(|a| a.len() / self.stride * self.nb).collect();
+        let data = self.iter().flatten().flatten().cloned().collect();
+        ArraySequence::new(lengths, data)
+    }
+}
+
+/// Returns the columns of the scalar or property `name`, along with the total number of columns.
+fn find_columns(
+    groups: Vec<(String, Range<usize>)>,
+    name: &str,
+    kind: &str,
+) -> Result<(Range<usize>, usize)> {
+    let stride = groups.last().map_or(0, |(_, range)| range.end);
+    match groups.into_iter().find(|(n, _)| n == name) {
+        Some((_, columns)) => Ok((columns, stride)),
+        None => bail!("There's no {} named {:?} in the header.", kind, name),
+    }
+}
+
+/// Returns the number of values per tuple, e.g., per point, in `values`.
+fn nb_values_per_tuple(
+    values: &ArraySequence<f32>,
+    nb_tuples: &[usize],
+    kind: &str,
+    name: &str,
+) -> Result<usize> {
+    let nb_total = nb_tuples.iter().sum::<usize>();
+    if nb_total == 0 || values.data.is_empty() || !values.data.len().is_multiple_of(nb_total) {
+        bail!("Can't deduce the number of values per item of {} {:?}", kind, name);
+    }
+    Ok(values.data.len() / nb_total)
+}
+
+/// Check that `values` contains `nb` values for each tuple.
+fn check_values<I: Iterator<Item = usize>>(
+    values: &ArraySequence<f32>,
+    nb_tuples: I,
+    nb: usize,
+    kind: &str,
+    name: &str,
+) -> Result<()> {
+    let mut nb_arrays = 0;
+    for (idx, nb_tuples) in nb_tuples.enumerate() {
+        if idx >= values.len() || values.length_of_array(idx) != nb_tuples * nb {
+            bail!("{} {:?} must have {} values per item for streamline {}", kind, name, nb, idx);
+        }
+        nb_arrays += 1;
+    }
+    if nb_arrays != values.len() {
+        bail!("{} {:?} must have {} arrays. Got {}", kind, name, nb_arrays, values.len());
+    }
+    Ok(())
+}
+
+fn set_columns(
+    values: &mut ArraySequence<f32>,
+    stride: usize,
+    columns: Range<usize>,
+    new: &ArraySequence<f32>,
+) {
+    let nb = columns.len();
+    for (tuple, new) in values.data.chunks_mut(stride).zip(new.data.chunks(nb)) {
+        tuple[columns.clone()].copy_from_slice(new);
+    }
+}
+
+/// Interleave the `nb` values of each tuple of `new` after the `stride` values of each tuple of
+/// `values`.
+fn append_columns(
+    values: &ArraySequence<f32>,
+    stride: usize,
+    new: &ArraySequence<f32>,
+    nb: usize,
+    nb_tuples: &[usize],
+) -> ArraySequence<f32> {
+    let new_stride = stride + nb;
+    let lengths = nb_tuples.iter().map(|n| n * new_stride).collect();
+    let mut data = Vec::with_capacity(values.data.len() + new.data.len());
+    let old_tuples = values.data.chunks(stride.max(1)).map(|tuple| &tuple[..stride]);
+    let old_tuples = old_tuples.chain(std::iter::repeat(&[][..]));
+    for (old, new) in old_tuples.zip(new.data.chunks(nb)) {
+        data.extend_from_slice(old);
+        data.extend_from_slice(new);
+    }
+    ArraySequence::new(lengths, data)
+}
+
+fn remove_columns(
+    values: &ArraySequence<f32>,
+    stride: usize,
+    columns: Range<usize>,
+) -> ArraySequence<f32> {
+    let new_stride = stride - columns.len();
+    if new_stride == 0 {
+        return ArraySequence::empty();
+    }
+    let lengths = values.iter().map(|a| a.len() / stride * new_stride).collect();
+    let mut data = Vec::with_capacity(values.data.len() / stride * new_stride);
+    for tuple in values.data.chunks(stride) {
+        data.extend_from_slice(&tuple[..columns.start]);
+        data.extend_from_slice(&tuple[columns.end..]);
+    }
+    ArraySequence::new(lengths, data)
 }
 
 impl<'data> IntoIterator for &'data Tractogram {
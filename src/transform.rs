@@ -0,0 +1,170 @@
+use std::{
+    fs::{read, read_to_string},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use nalgebra::{Matrix3, Matrix4, Vector3, Vector4};
+#[cfg(feature = "nifti_images")]
+use nifti::NiftiHeader;
+
+use crate::{mat4::read_mat4, Affine4};
+
+/// Load an ITK/ANTs linear transform, either a binary `.mat` file (MATLAB v4) or a text file
+/// (`#Insight Transform File V1.0`).
+///
+/// ITK transforms are defined in LPS and map the points of the fixed image to the points of the
+/// moving image. The returned affine is in RAS+ and maps the same points, thus `invert` must be
+/// `true` to bring streamlines from the moving space to the fixed space.
+pub fn load_itk<P: AsRef<Path>>(path: P, invert: bool) -> Result<Affine4> {
+    let path = path.as_ref();
+    let bytes = read(path).with_context(|| format!("Failed to load {:?}", path))?;
+    let (parameters, center) = if bytes.starts_with(b"#Insight Transform File") {
+        read_itk_text(&String::from_utf8_lossy(&bytes), path)?
+    } else {
+        let variables = read_mat4(&mut bytes.as_slice())
+            .with_context(|| format!("{:?} is not a valid ITK transform file", path))?;
+        let mut parameters = None;
+        let mut center = None;
+        for variable in variables {
+            if variable.name == "fixed" {
+                center = Some(variable.data.to_f64());
+            } else {
+                parameters = Some(variable.data.to_f64());
+            }
+        }
+        match parameters {
+            Some(parameters) => (parameters, center.unwrap_or_else(|| vec![0.0; 3])),
+            None => bail!("No transform parameters in {:?}", path),
+        }
+    };
+    if parameters.len() != 12 || center.len() != 3 {
+        bail!("Only 3D affine ITK transforms are supported ({:?})", path);
+    }
+
+    let matrix = Matrix3::from_row_slice(&parameters[..9]);
+    let translation = Vector3::from_row_slice(&parameters[9..]);
+    let center = Vector3::from_row_slice(&center);
+    let offset = translation + center - matrix * center;
+
+    let mut lps = Matrix4::<f64>::identity();
+    lps.fixed_view_mut::<3, 3>(0, 0).copy_from(&matrix);
+    lps.fixed_view_mut::<3, 1>(0, 3).copy_from(&offset);
+
+    let lps_to_ras = Matrix4::from_diagonal(&Vector4::new(-1.0, -1.0, 1.0, 1.0));
+    to_affine4(lps_to_ras * lps * lps_to_ras, invert)
+}
+
+/// Load a FLIRT matrix, e.g., from `flirt -omat`.
+///
+/// FLIRT matrices are defined between the scaled-voxel spaces of the `source` and `reference`
+/// images, where the first axis is flipped for images in neurological order. The returned affine
+/// maps the points of the source image to the points of the reference image, in RAS+ mm, thus it
+/// can be used as-is to bring streamlines to the reference space.
+#[cfg(feature = "nifti_images")]
+pub fn load_fsl<P: AsRef<Path>>(
+    path: P,
+    source: &NiftiHeader,
+    reference: &NiftiHeader,
+    invert: bool,
+) -> Result<Affine4> {
+    let flirt = read_text_matrix(path.as_ref())?;
+    let source_affine = source.affine::<f64>();
+    let source_to_voxel = match source_affine.try_inverse() {
+        Some(to_voxel) => to_voxel,
+        None => bail!("The affine of the source image is not invertible."),
+    };
+    let reference_scaled = match fsl_scaled_voxel(reference).try_inverse() {
+        Some(inverse) => inverse,
+        None => bail!("The voxel size of the reference image is not valid."),
+    };
+
+    let affine = reference.affine::<f64>()
+        * reference_scaled
+        * flirt
+        * fsl_scaled_voxel(source)
+        * source_to_voxel;
+    to_affine4(affine, invert)
+}
+
+/// Load a 4x4 affine saved as plain text, one row per line, e.g., by `numpy.savetxt`.
+///
+/// Lines starting with `#` are ignored. The affine is returned as it is, or inverted.
+pub fn load_text<P: AsRef<Path>>(path: P, invert: bool) -> Result<Affine4> {
+    to_affine4(read_text_matrix(path.as_ref())?, invert)
+}
+
+/// Affine mapping the voxel indices to FSL's scaled-voxel space.
+#[cfg(feature = "nifti_images")]
+fn fsl_scaled_voxel(header: &NiftiHeader) -> Matrix4<f64> {
+    let pixdim = header.pixdim;
+    let mut scaled = Matrix4::from_diagonal(&Vector4::new(
+        pixdim[1] as f64,
+        pixdim[2] as f64,
+        pixdim[3] as f64,
+        1.0,
+    ));
+    if header.affine::<f64>().fixed_view::<3, 3>(0, 0).determinant() > 0.0 {
+        scaled[(0, 0)] = -scaled[(0, 0)];
+        scaled[(0, 3)] = (header.dim[1] as f64 - 1.0) * pixdim[1] as f64;
+    }
+    scaled
+}
+
+fn read_text_matrix(path: &Path) -> Result<Matrix4<f64>> {
+    let text = read_to_string(path).with_context(|| format!("Failed to load {:?}", path))?;
+    let mut values = vec![];
+    for line in text.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        for value in line.split(|c: char| c.is_whitespace() || c == ',') {
+            if !value.is_empty() {
+                values.push(value.parse::<f64>().with_context(|| {
+                    format!("Can't parse {:?} as a number in {:?}", value, path)
+                })?);
+            }
+        }
+    }
+    match values.len() {
+        16 => Ok(Matrix4::from_row_slice(&values)),
+        12 => {
+            values.extend([0.0, 0.0, 0.0, 1.0]);
+            Ok(Matrix4::from_row_slice(&values))
+        }
+        nb => bail!("Expected a 4x4 matrix in {:?}, found {} numbers", path, nb),
+    }
+}
+
+/// Read the `Parameters` and `FixedParameters` lines of an ITK text transform.
+fn read_itk_text(text: &str, path: &Path) -> Result<(Vec<f64>, Vec<f64>)> {
+    let parse = |line: &str| -> Result<Vec<f64>> {
+        let values = line.split_whitespace().map(|v| v.parse::<f64>());
+        Ok(values.collect::<Result<Vec<_>, _>>()?)
+    };
+    let mut parameters = None;
+    let mut center = None;
+    for line in text.lines() {
+        if let Some(values) = line.strip_prefix("Parameters:") {
+            parameters = Some(parse(values)?);
+        } else if let Some(values) = line.strip_prefix("FixedParameters:") {
+            center = Some(parse(values)?);
+        }
+    }
+    match parameters {
+        Some(parameters) => Ok((parameters, center.unwrap_or_else(|| vec![0.0; 3]))),
+        None => bail!("No transform parameters in {:?}", path),
+    }
+}
+
+fn to_affine4(affine: Matrix4<f64>, invert: bool) -> Result<Affine4> {
+    let affine = if invert {
+        match affine.try_inverse() {
+            Some(inverse) => inverse,
+            None => bail!("The transform is not invertible."),
+        }
+    } else {
+        affine
+    };
+    Ok(affine.cast::<f32>())
+}
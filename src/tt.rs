@@ -0,0 +1,188 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{
+    mat4::{read_mat4, write_mat4, MatData, MatVariable},
+    Affine4, ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+/// The coordinates of the tracks are stored in 1/32 voxel.
+const SCALE: f32 = 32.0;
+
+/// Read a DSI Studio tractography file (.tt.gz).
+///
+/// The tracks are returned in RAS+ mm, using `trans_to_mni`. Old files without `trans_to_mni` are
+/// assumed to be in DSI Studio's LPS voxel grid. The returned header describes this voxel grid.
+pub fn read_tt<P: AsRef<Path>>(path: P) -> Result<(Header, Tractogram)> {
+    let path = path.as_ref();
+    let f = File::open(path).with_context(|| format!("Failed to load {:?}", path))?;
+    let variables = read_mat4(&mut GzDecoder::new(BufReader::new(f)))
+        .with_context(|| format!("{:?} is not a valid DSI Studio tt.gz file", path))?;
+    let get = |name: &str| variables.iter().find(|v| v.name == name);
+
+    let dim = match get("dimension").map(|v| v.data.to_f64()) {
+        Some(dim)
+            if dim.len() == 3 && dim.iter().all(|&d| (1.0..=i16::MAX as f64).contains(&d)) =>
+        {
+            [dim[0] as i16, dim[1] as i16, dim[2] as i16]
+        }
+        _ => bail!("No valid dimension in {:?}", path),
+    };
+    let voxel_size = match get("voxel_size").map(|v| v.data.to_f64()) {
+        Some(vs) if vs.len() == 3 => [vs[0] as f32, vs[1] as f32, vs[2] as f32],
+        _ => bail!("No valid voxel_size in {:?}", path),
+    };
+    let affine = match get("trans_to_mni").map(|v| v.data.to_f64()) {
+        // Written from a row-major matrix
+        Some(trans) if trans.len() == 16 => {
+            Affine4::from_row_slice(&trans.iter().map(|&v| v as f32).collect::<Vec<_>>())
+        }
+        _ => lps_affine(dim, voxel_size),
+    };
+    let bytes = match get("track") {
+        Some(MatVariable { data: MatData::U8(bytes), .. }) => bytes.as_slice(),
+        _ => bail!("No track in {:?}", path),
+    };
+
+    let streamlines = decode_tracks(bytes, &affine)
+        .with_context(|| format!("Failed to decode the tracks of {:?}", path))?;
+    let mut header = Header::from_affine(&affine, dim, voxel_size);
+    header.nb_streamlines = streamlines.len();
+    let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+    Ok((header, tractogram))
+}
+
+/// Write `tractogram`, which must be in RAS+ mm, as a DSI Studio tractography file (.tt.gz).
+///
+/// The coordinates are stored in the voxel grid of the reference image of `header`, with a
+/// precision of 1/32 voxel. Scalars and properties are not saved. Fails if two consecutive points
+/// are more than 4 voxels apart, because the format can't represent them.
+pub fn write_tt<P: AsRef<Path>>(path: P, header: &Header, tractogram: &Tractogram) -> Result<()> {
+    let path = path.as_ref();
+    let c_header = header.raw_header();
+    let affine = c_header.get_voxel_to_rasmm();
+    let to_voxel = match affine.try_inverse() {
+        Some(to_voxel) => to_voxel,
+        None => bail!("The voxel to RAS+ mm affine of the header is not invertible."),
+    };
+
+    let mut track = vec![];
+    for (idx, streamline) in tractogram.streamlines.iter().enumerate() {
+        encode_track(streamline, &to_voxel, &mut track)
+            .with_context(|| format!("Can't encode streamline {}", idx))?;
+    }
+
+    let variables = [
+        MatVariable {
+            name: "dimension".into(),
+            rows: 1,
+            cols: 3,
+            data: MatData::I32(c_header.dim.iter().map(|&d| d as i32).collect()),
+        },
+        MatVariable {
+            name: "voxel_size".into(),
+            rows: 1,
+            cols: 3,
+            data: MatData::F32(c_header.voxel_size.to_vec()),
+        },
+        MatVariable {
+            name: "trans_to_mni".into(),
+            rows: 4,
+            cols: 4,
+            data: MatData::F32(affine.transpose().iter().cloned().collect()),
+        },
+        MatVariable { name: "track".into(), rows: 1, cols: track.len(), data: MatData::U8(track) },
+    ];
+
+    let f = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut encoder = GzEncoder::new(BufWriter::new(f), Compression::default());
+    write_mat4(&mut encoder, &variables)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Each track is stored as the number of coordinates (uint32), the first point (3 int32), then
+/// the difference between each point and the previous one (3 int8).
+fn decode_tracks(bytes: &[u8], affine: &Affine4) -> Result<Streamlines> {
+    let mut streamlines = Streamlines::with_capacity(bytes.len() / 3);
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if pos + 16 > bytes.len() {
+            bail!("Truncated track at byte {}", pos);
+        }
+        let nb_coordinates = LittleEndian::read_u32(&bytes[pos..]) as usize;
+        if nb_coordinates < 3 || !nb_coordinates.is_multiple_of(3) {
+            bail!("Invalid number of coordinates {} at byte {}", nb_coordinates, pos);
+        }
+        let end = match (pos + 13).checked_add(nb_coordinates) {
+            Some(end) if end <= bytes.len() => end,
+            _ => bail!("Truncated track at byte {}", pos),
+        };
+
+        let mut current = [0i32; 3];
+        LittleEndian::read_i32_into(&bytes[pos + 4..pos + 16], &mut current);
+        let to_rasmm = |c: &[i32; 3]| {
+            let voxel = Point::new(c[0] as f32, c[1] as f32, c[2] as f32) / SCALE;
+            affine.transform_point(&voxel)
+        };
+        streamlines.push(to_rasmm(&current));
+        for delta in bytes[pos + 16..end].chunks(3) {
+            for (c, &d) in current.iter_mut().zip(delta) {
+                *c = match c.checked_add(d as i8 as i32) {
+                    Some(c) => c,
+                    None => bail!("Coordinate overflow in the track at byte {}", pos),
+                };
+            }
+            streamlines.push(to_rasmm(&current));
+        }
+        streamlines.end_push();
+        pos = end;
+    }
+    Ok(streamlines)
+}
+
+fn encode_track(streamline: &[Point], to_voxel: &Affine4, track: &mut Vec<u8>) -> Result<()> {
+    if streamline.is_empty() {
+        bail!("Empty streamlines can't be saved");
+    }
+    let coordinates = streamline.iter().map(|p| {
+        let voxel = to_voxel.transform_point(p) * SCALE;
+        [voxel.x.round() as i32, voxel.y.round() as i32, voxel.z.round() as i32]
+    });
+    track.extend_from_slice(&(3 * streamline.len() as u32).to_le_bytes());
+    let mut previous: Option<[i32; 3]> = None;
+    for current in coordinates {
+        match previous {
+            None => current.iter().for_each(|c| track.extend_from_slice(&c.to_le_bytes())),
+            Some(previous) => {
+                for (c, p) in current.iter().zip(&previous) {
+                    match i8::try_from(c - p) {
+                        Ok(delta) => track.push(delta as u8),
+                        Err(_) => bail!("The points are too far apart; resample the streamline"),
+                    }
+                }
+            }
+        }
+        previous = Some(current);
+    }
+    Ok(())
+}
+
+/// Affine of DSI Studio's LPS voxel grid, with the origin at the corner of the volume.
+fn lps_affine(dim: [i16; 3], voxel_size: [f32; 3]) -> Affine4 {
+    #[rustfmt::skip]
+    let affine = Affine4::new(
+        -voxel_size[0], 0.0, 0.0, (dim[0] as f32 - 1.0) * voxel_size[0],
+        0.0, -voxel_size[1], 0.0, (dim[1] as f32 - 1.0) * voxel_size[1],
+        0.0, 0.0, voxel_size[2], 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    );
+    affine
+}
@@ -0,0 +1,149 @@
+use anyhow::{bail, Result};
+use nalgebra::Vector3;
+
+use crate::{Affine4, Header, Point, Tractogram};
+
+/// Tolerance, in mm, on the limits of the volume, to absorb the rounding errors of the
+/// conversions between spaces.
+const TOLERANCE: f32 = 1e-3;
+
+/// What to do with the invalid streamlines of a tractogram.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Remove the streamlines with points outside the volume or with NaN/Inf coordinates.
+    Reject,
+
+    /// Move the points outside the volume to its nearest border. Streamlines with NaN/Inf
+    /// coordinates can't be clipped, thus they are removed.
+    Clip,
+
+    /// Only report the invalid streamlines.
+    Ignore,
+}
+
+/// Invalid streamlines found in a tractogram.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Sorted indices of the streamlines with at least one point outside the volume.
+    pub out_of_bounds: Vec<usize>,
+
+    /// Sorted indices of the streamlines with at least one NaN or infinite coordinate.
+    pub non_finite: Vec<usize>,
+
+    /// Total number of points outside the volume.
+    pub nb_points_out_of_bounds: usize,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no invalid streamline was found.
+    pub fn is_valid(&self) -> bool {
+        self.out_of_bounds.is_empty() && self.non_finite.is_empty()
+    }
+
+    /// Sorted indices of all invalid streamlines.
+    pub fn invalid(&self) -> Vec<usize> {
+        let mut invalid = self.out_of_bounds.clone();
+        invalid.extend(&self.non_finite);
+        invalid.sort_unstable();
+        invalid.dedup();
+        invalid
+    }
+}
+
+/// The volume of the reference image of a trk file, that is, `dim * voxel_size`, from the corner
+/// of the first voxel to the corner of the last one.
+///
+/// Works on points in RAS+ mm, as returned by `Reader`, thus it can also be used to check the
+/// items of a `Reader` without loading the whole file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    to_voxmm: Affine4,
+    to_rasmm: Affine4,
+    extent: Vector3<f32>,
+}
+
+impl BoundingBox {
+    /// Fails if the header has an invalid affine, e.g., a null voxel size.
+    pub fn from_header(header: &Header) -> Result<BoundingBox> {
+        let to_voxmm = match header.affine4_to_rasmm.try_inverse() {
+            Some(to_voxmm) => to_voxmm,
+            None => bail!("The affine of the header is not invertible."),
+        };
+        let c_header = header.raw_header();
+        let extent = Vector3::from_fn(|i, _| c_header.dim[i] as f32 * c_header.voxel_size[i]);
+        Ok(BoundingBox { to_voxmm, to_rasmm: header.affine4_to_rasmm, extent })
+    }
+
+    /// Returns `true` if `p` is inside the volume, or on its border. Returns `false` for points
+    /// with NaN/Inf coordinates.
+    pub fn contains(&self, p: &Point) -> bool {
+        let p = self.to_voxmm.transform_point(p);
+        (0..3).all(|i| p[i] >= -TOLERANCE && p[i] <= self.extent[i] + TOLERANCE)
+    }
+
+    /// Returns the nearest point of the volume.
+    pub fn clip(&self, p: &Point) -> Point {
+        let voxmm = self.to_voxmm.transform_point(p);
+        let clipped = voxmm.coords.zip_map(&self.extent, |c, e| c.clamp(0.0, e));
+        if clipped == voxmm.coords {
+            *p
+        } else {
+            self.to_rasmm.transform_point(&Point::from(clipped))
+        }
+    }
+}
+
+/// Check all points of `tractogram`, which must be in RAS+ mm, against the volume described by
+/// `header`.
+pub fn validate(tractogram: &Tractogram, header: &Header) -> Result<ValidationReport> {
+    let bbox = BoundingBox::from_header(header)?;
+    let mut report = ValidationReport::default();
+    for (idx, streamline) in tractogram.streamlines.iter().enumerate() {
+        if streamline.iter().any(|p| p.iter().any(|c| !c.is_finite())) {
+            report.non_finite.push(idx);
+            continue;
+        }
+        let nb_outside = streamline.iter().filter(|p| !bbox.contains(p)).count();
+        if nb_outside > 0 {
+            report.out_of_bounds.push(idx);
+            report.nb_points_out_of_bounds += nb_outside;
+        }
+    }
+    Ok(report)
+}
+
+/// Check `tractogram` like `validate`, then handle its invalid streamlines according to `mode`.
+///
+/// Returns the report of the tractogram before modification, thus the indices refer to the
+/// original streamlines.
+pub fn validate_and_fix(
+    tractogram: &mut Tractogram,
+    header: &Header,
+    mode: ValidationMode,
+) -> Result<ValidationReport> {
+    let report = validate(tractogram, header)?;
+    if report.is_valid() {
+        return Ok(report);
+    }
+
+    let to_remove = match mode {
+        ValidationMode::Reject => report.invalid(),
+        ValidationMode::Clip => {
+            let bbox = BoundingBox::from_header(header)?;
+            for &idx in &report.out_of_bounds {
+                for p in &mut tractogram.streamlines[idx] {
+                    *p = bbox.clip(p);
+                }
+            }
+            report.non_finite.clone()
+        }
+        ValidationMode::Ignore => vec![],
+    };
+    if !to_remove.is_empty() {
+        let kept = (0..tractogram.streamlines.len())
+            .filter(|idx| to_remove.binary_search(idx).is_err())
+            .collect::<Vec<_>>();
+        *tractogram = tractogram.select(&kept);
+    }
+    Ok(report)
+}
@@ -24,10 +24,6 @@ impl Iterator for VoxelSpaceReader {
     type Item = Points;
 
     fn next(&mut self) -> Option<Points> {
-        if let Some(points) = self.reader.next() {
-            return Some(points);
-        } else {
-            return None;
-        }
+        self.reader.next()
     }
 }
@@ -0,0 +1,554 @@
+use std::{
+    fs::{read, File},
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+    str::from_utf8,
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+
+use crate::{ArraySequence, Header, Point, Streamlines, Tractogram};
+
+/// Encoding of the data of a legacy VTK file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VtkEncoding {
+    Ascii,
+
+    /// Big endian binary data, as required by the legacy VTK format.
+    Binary,
+}
+
+/// A named VTK data array, with `nb_components` values per point or per cell.
+pub(crate) struct DataArray {
+    pub name: String,
+    pub nb_components: usize,
+    pub values: Vec<f32>,
+}
+
+/// Read a legacy VTK file (.vtk) containing a `POLYDATA` dataset with `LINES` cells.
+///
+/// The points are expected to be in RAS+ mm. All `POINT_DATA` arrays are read as per-point
+/// scalars and all `CELL_DATA` arrays as properties. The components of an array stay grouped under
+/// its name. The returned header has no reference image, but it contains the names of the scalars
+/// and properties.
+pub fn read_vtk<P: AsRef<Path>>(path: P) -> Result<(Header, Tractogram)> {
+    let path = path.as_ref();
+    let bytes = read(path).with_context(|| format!("Failed to load {:?}", path))?;
+    read_legacy(&bytes).with_context(|| format!("Failed to read {:?} as a VTK file", path))
+}
+
+/// Write `tractogram` as a legacy VTK file (.vtk), with the streamlines as `LINES` cells.
+///
+/// The points are written as they are, thus they should be in RAS+ mm. The scalars are written as
+/// `POINT_DATA` and the properties as `CELL_DATA`, using the names in `header`.
+pub fn write_vtk<P: AsRef<Path>>(
+    path: P,
+    header: &Header,
+    tractogram: &Tractogram,
+    encoding: VtkEncoding,
+) -> Result<()> {
+    let path = path.as_ref();
+    let f = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut writer = BufWriter::new(f);
+    let streamlines = &tractogram.streamlines;
+    let nb_points = streamlines.data.len();
+
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "Streamlines written by trk-io")?;
+    match encoding {
+        VtkEncoding::Ascii => writeln!(writer, "ASCII")?,
+        VtkEncoding::Binary => writeln!(writer, "BINARY")?,
+    }
+    writeln!(writer, "DATASET POLYDATA")?;
+
+    writeln!(writer, "POINTS {} float", nb_points)?;
+    let coordinates = streamlines.data.iter().flat_map(|p| [p.x, p.y, p.z]);
+    write_values(&mut writer, coordinates, 3, encoding)?;
+
+    writeln!(writer, "LINES {} {}", streamlines.len(), streamlines.len() + nb_points)?;
+    match encoding {
+        VtkEncoding::Ascii => {
+            for (streamline, offset) in streamlines.iter().zip(&streamlines.offsets) {
+                write!(writer, "{}", streamline.len())?;
+                for i in *offset..offset + streamline.len() {
+                    write!(writer, " {}", i)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        VtkEncoding::Binary => {
+            for (streamline, offset) in streamlines.iter().zip(&streamlines.offsets) {
+                writer.write_i32::<BigEndian>(streamline.len() as i32)?;
+                for i in *offset..offset + streamline.len() {
+                    writer.write_i32::<BigEndian>(i as i32)?;
+                }
+            }
+            writeln!(writer)?;
+        }
+    }
+
+    let scalars = data_arrays(header.scalars_groups(), &tractogram.scalars, nb_points)?;
+    if !scalars.is_empty() {
+        writeln!(writer, "POINT_DATA {}", nb_points)?;
+        write_arrays(&mut writer, &scalars, encoding)?;
+    }
+    let properties =
+        data_arrays(header.properties_groups(), &tractogram.properties, streamlines.len())?;
+    if !properties.is_empty() {
+        writeln!(writer, "CELL_DATA {}", streamlines.len())?;
+        write_arrays(&mut writer, &properties, encoding)?;
+    }
+    Ok(())
+}
+
+/// Split the interleaved `values` into one array per group of columns.
+pub(crate) fn data_arrays(
+    groups: Vec<(String, Range<usize>)>,
+    values: &ArraySequence<f32>,
+    nb_tuples: usize,
+) -> Result<Vec<DataArray>> {
+    let nb_columns = groups.last().map_or(0, |(_, range)| range.end);
+    if nb_columns == 0 {
+        return Ok(vec![]);
+    }
+    if values.data.len() != nb_columns * nb_tuples {
+        bail!(
+            "The header declares {} values per item, but there's {} values for {} items.",
+            nb_columns,
+            values.data.len(),
+            nb_tuples
+        );
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(name, range)| DataArray {
+            name,
+            nb_components: range.len(),
+            values: values
+                .data
+                .chunks(nb_columns)
+                .flat_map(|tuple| tuple[range.clone()].iter().cloned())
+                .collect(),
+        })
+        .collect())
+}
+
+/// Build a tractogram from VTK polydata, where `connectivity` contains the indices of the points
+/// of each line, one line after the other.
+///
+/// The arrays with many components are kept grouped, with `add_scalar_n` and `add_property_n`.
+pub(crate) fn build_tractogram(
+    points: &[Point],
+    connectivity: &[usize],
+    lengths: Vec<usize>,
+    point_data: &[DataArray],
+    cell_data: &[DataArray],
+) -> Result<(Header, Tractogram)> {
+    if let Some(idx) = connectivity.iter().find(|&&idx| idx >= points.len()) {
+        bail!("The lines refer to point {}, but there's only {} points.", idx, points.len());
+    }
+    let nb_lines = lengths.len();
+    for (arrays, nb) in [(point_data, points.len()), (cell_data, nb_lines)] {
+        for array in arrays {
+            if array.values.len() != array.nb_components * nb {
+                bail!("Array {:?} has {} values; expected {}", array.name, array.values.len(), nb);
+            }
+        }
+    }
+
+    let mut header = Header::default();
+    header.nb_streamlines = nb_lines;
+    for DataArray { name, nb_components, .. } in point_data {
+        header
+            .add_scalar_n(name, *nb_components)
+            .with_context(|| format!("Can't add scalar {:?}", name))?;
+    }
+    for DataArray { name, nb_components, .. } in cell_data {
+        header
+            .add_property_n(name, *nb_components)
+            .with_context(|| format!("Can't add property {:?}", name))?;
+    }
+
+    let data = connectivity.iter().map(|&idx| points[idx]).collect();
+    let scalars = if point_data.is_empty() {
+        ArraySequence::empty()
+    } else {
+        let nb_scalars = header.scalars_name.len();
+        let scalar_lengths = lengths.iter().map(|&l| l * nb_scalars).collect();
+        ArraySequence::new(scalar_lengths, interleave(point_data, connectivity.iter().cloned()))
+    };
+    let properties = if cell_data.is_empty() {
+        ArraySequence::empty()
+    } else {
+        let nb_properties = header.properties_name.len();
+        ArraySequence::new(vec![nb_properties; nb_lines], interleave(cell_data, 0..nb_lines))
+    };
+    let streamlines = Streamlines::new(lengths, data);
+    Ok((header, Tractogram::new(streamlines, scalars, properties)))
+}
+
+/// Values of all `arrays`, for each index, one after the other.
+fn interleave<I: Iterator<Item = usize>>(arrays: &[DataArray], indices: I) -> Vec<f32> {
+    let mut values = vec![];
+    for idx in indices {
+        for array in arrays {
+            let n = array.nb_components;
+            values.extend_from_slice(&array.values[idx * n..(idx + 1) * n]);
+        }
+    }
+    values
+}
+
+fn write_arrays<W: Write>(
+    writer: &mut W,
+    arrays: &[DataArray],
+    encoding: VtkEncoding,
+) -> Result<()> {
+    for array in arrays {
+        let name = array.name.replace('%', "%25").replace(' ', "%20");
+        // SCALARS are limited to 4 components
+        if array.nb_components <= 4 {
+            writeln!(writer, "SCALARS {} float {}", name, array.nb_components)?;
+            writeln!(writer, "LOOKUP_TABLE default")?;
+        } else {
+            let nb_tuples = array.values.len() / array.nb_components;
+            writeln!(writer, "FIELD FieldData 1")?;
+            writeln!(writer, "{} {} {} float", name, array.nb_components, nb_tuples)?;
+        }
+        write_values(writer, array.values.iter().cloned(), array.nb_components, encoding)?;
+    }
+    Ok(())
+}
+
+/// Write `values`, `per_line` values per line if the encoding is ASCII.
+fn write_values<W: Write, I: Iterator<Item = f32>>(
+    writer: &mut W,
+    values: I,
+    per_line: usize,
+    encoding: VtkEncoding,
+) -> Result<()> {
+    match encoding {
+        VtkEncoding::Ascii => {
+            for (i, value) in values.enumerate() {
+                let separator = if (i + 1) % per_line == 0 { '\n' } else { ' ' };
+                write!(writer, "{}{}", value, separator)?;
+            }
+        }
+        VtkEncoding::Binary => {
+            for value in values {
+                writer.write_f32::<BigEndian>(value)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_legacy(bytes: &[u8]) -> Result<(Header, Tractogram)> {
+    let mut parser = Parser { bytes, pos: 0, binary: false };
+    if !parser.raw_line().starts_with("# vtk DataFile") {
+        bail!("Missing '# vtk DataFile' signature");
+    }
+    let _title = parser.raw_line();
+    parser.binary = match parser.line()?.to_uppercase().as_str() {
+        "ASCII" => false,
+        "BINARY" => true,
+        encoding => bail!("Unknown encoding {:?}", encoding),
+    };
+    let dataset = parser.line()?;
+    if dataset.split_whitespace().nth(1).map(|d| d.to_uppercase()) != Some("POLYDATA".into()) {
+        bail!("Only POLYDATA datasets are supported. Got {:?}", dataset);
+    }
+
+    let mut points = vec![];
+    let mut connectivity = vec![];
+    let mut lengths = vec![];
+    let mut point_data = vec![];
+    let mut cell_data = vec![];
+    // Attributes are ignored until POINT_DATA or CELL_DATA is found
+    let mut attributes: Option<(bool, usize)> = None;
+    while let Some(line) = parser.next_line() {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let keyword = words[0].to_uppercase();
+        let count = |i: usize| -> Result<usize> {
+            match words.get(i).map(|w| w.parse::<usize>()) {
+                Some(Ok(count)) => Ok(count),
+                _ => bail!("Can't parse {:?}", line),
+            }
+        };
+        let dtype = |i: usize| words.get(i).cloned().unwrap_or("float");
+        let name = || words.get(1).cloned().with_context(|| format!("Can't parse {:?}", line));
+
+        let mut new_arrays = vec![];
+        match keyword.as_str() {
+            "POINTS" => {
+                let values = parser.numbers(nb_values(3, count(1)?)?, dtype(2))?;
+                points = values
+                    .chunks(3)
+                    .map(|c| Point::new(c[0] as f32, c[1] as f32, c[2] as f32))
+                    .collect();
+            }
+            "LINES" | "VERTICES" | "POLYGONS" | "TRIANGLE_STRIPS" => {
+                let (cells_connectivity, cells_lengths) = parser.cells(count(1)?, count(2)?)?;
+                if keyword == "LINES" {
+                    connectivity = cells_connectivity;
+                    lengths = cells_lengths;
+                }
+            }
+            "POINT_DATA" => attributes = Some((true, count(1)?)),
+            "CELL_DATA" => attributes = Some((false, count(1)?)),
+            "SCALARS" => {
+                let nb_components = if words.len() > 3 { count(3)? } else { 1 };
+                if parser.peek_line().to_uppercase().starts_with("LOOKUP_TABLE") {
+                    parser.next_line();
+                }
+                new_arrays.push((name()?, nb_components, dtype(2)));
+            }
+            "VECTORS" | "NORMALS" => new_arrays.push((name()?, 3, dtype(2))),
+            "TENSORS" => new_arrays.push((name()?, 9, dtype(2))),
+            "TEXTURE_COORDINATES" => new_arrays.push((name()?, count(2)?, dtype(3))),
+            "COLOR_SCALARS" => {
+                let dtype = if parser.binary { "unsigned_char" } else { "float" };
+                new_arrays.push((name()?, count(2)?, dtype));
+            }
+            "LOOKUP_TABLE" => {
+                let dtype = if parser.binary { "unsigned_char" } else { "float" };
+                parser.numbers(nb_values(4, count(2)?)?, dtype)?;
+            }
+            "FIELD" => {
+                for _ in 0..count(2)? {
+                    let line = parser.line()?;
+                    let words = line.split_whitespace().collect::<Vec<_>>();
+                    if words.len() != 4 {
+                        bail!("Can't parse field array {:?}", line);
+                    }
+                    let nb_components = words[1].parse::<usize>()?;
+                    let nb_tuples = words[2].parse::<usize>()?;
+                    let values = parser.numbers(nb_values(nb_components, nb_tuples)?, words[3])?;
+                    if let Some((is_point_data, _)) = attributes {
+                        let array = DataArray {
+                            name: decode_name(words[0]),
+                            nb_components,
+                            values: values.into_iter().map(|v| v as f32).collect(),
+                        };
+                        if is_point_data {
+                            point_data.push(array);
+                        } else {
+                            cell_data.push(array);
+                        }
+                    }
+                }
+            }
+            "METADATA" => parser.skip_block(),
+            _ => bail!("Unsupported VTK section {:?}", line),
+        }
+
+        for (name, nb_components, dtype) in new_arrays {
+            let Some((is_point_data, nb_tuples)) = attributes else {
+                bail!("{:?} is not in POINT_DATA or CELL_DATA", line);
+            };
+            let mut values = parser.numbers(nb_values(nb_components, nb_tuples)?, dtype)?;
+            if keyword == "COLOR_SCALARS" && parser.binary {
+                values.iter_mut().for_each(|v| *v /= 255.0);
+            }
+            let values = values.into_iter().map(|v| v as f32).collect();
+            let array = DataArray { name: decode_name(name), nb_components, values };
+            if is_point_data {
+                point_data.push(array);
+            } else {
+                cell_data.push(array);
+            }
+        }
+    }
+
+    build_tractogram(&points, &connectivity, lengths, &point_data, &cell_data)
+}
+
+/// Number of values of `nb_tuples` tuples of `nb_components`, failing on overflow.
+fn nb_values(nb_components: usize, nb_tuples: usize) -> Result<usize> {
+    match nb_components.checked_mul(nb_tuples) {
+        Some(n) => Ok(n),
+        None => bail!("Too many values: {} x {}", nb_components, nb_tuples),
+    }
+}
+
+/// Decode the `%XX` escapes that VTK uses for the spaces and special characters in the names.
+fn decode_name(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| from_utf8(hex).ok());
+        match (bytes[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Reads the text lines and the ASCII or binary data of a legacy VTK file.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    binary: bool,
+}
+
+impl<'a> Parser<'a> {
+    /// Next line, as it is.
+    fn raw_line(&mut self) -> String {
+        let rest = &self.bytes[self.pos..];
+        let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        self.pos += (end + 1).min(rest.len());
+        String::from_utf8_lossy(&rest[..end]).trim_end_matches('\r').to_string()
+    }
+
+    /// Next non-empty line, trimmed, or `None` at the end of the file.
+    fn next_line(&mut self) -> Option<String> {
+        while self.pos < self.bytes.len() {
+            let line = self.raw_line();
+            if !line.trim().is_empty() {
+                return Some(line.trim().to_string());
+            }
+        }
+        None
+    }
+
+    fn line(&mut self) -> Result<String> {
+        match self.next_line() {
+            Some(line) => Ok(line),
+            None => bail!("Unexpected end of file"),
+        }
+    }
+
+    fn peek_line(&mut self) -> String {
+        let pos = self.pos;
+        let line = self.next_line().unwrap_or_default();
+        self.pos = pos;
+        line
+    }
+
+    /// Skip lines until the next empty line.
+    fn skip_block(&mut self) {
+        while self.pos < self.bytes.len() && !self.raw_line().trim().is_empty() {}
+    }
+
+    /// Read `n` numbers of type `dtype`.
+    fn numbers(&mut self, n: usize, dtype: &str) -> Result<Vec<f64>> {
+        if self.binary {
+            self.binary_numbers(n, dtype)
+        } else {
+            // Each ASCII value takes at least one byte, so `n` can't be trusted before this check
+            let remaining = self.bytes.len() - self.pos;
+            if n > remaining {
+                bail!("Expected {} values, but there's only {} bytes left", n, remaining);
+            }
+            let mut values = Vec::with_capacity(n);
+            while values.len() < n {
+                let rest = &self.bytes[self.pos..];
+                let start = match rest.iter().position(|b| !b.is_ascii_whitespace()) {
+                    Some(start) => start,
+                    None => bail!("Expected {} values, found {}", n, values.len()),
+                };
+                let len = rest[start..]
+                    .iter()
+                    .position(|b| b.is_ascii_whitespace())
+                    .unwrap_or(rest.len() - start);
+                let token = from_utf8(&rest[start..start + len])?;
+                values
+                    .push(token.parse::<f64>().with_context(|| format!("Bad value {:?}", token))?);
+                self.pos += start + len;
+            }
+            Ok(values)
+        }
+    }
+
+    fn binary_numbers(&mut self, n: usize, dtype: &str) -> Result<Vec<f64>> {
+        let size = match dtype.to_lowercase().as_str() {
+            "char" | "unsigned_char" => 1,
+            "short" | "unsigned_short" => 2,
+            "int" | "unsigned_int" | "float" => 4,
+            "long" | "unsigned_long" | "double" | "vtktypeint64" | "vtktypeuint64" => 8,
+            _ => bail!("Unsupported data type {:?}", dtype),
+        };
+        let end = match n.checked_mul(size).and_then(|nb_bytes| nb_bytes.checked_add(self.pos)) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => bail!("Unexpected end of file"),
+        };
+        let data = &self.bytes[self.pos..end];
+        self.pos = end;
+        let values = data.chunks(size).map(|b| match dtype.to_lowercase().as_str() {
+            "char" => b[0] as i8 as f64,
+            "unsigned_char" => b[0] as f64,
+            "short" => BigEndian::read_i16(b) as f64,
+            "unsigned_short" => BigEndian::read_u16(b) as f64,
+            "int" => BigEndian::read_i32(b) as f64,
+            "unsigned_int" => BigEndian::read_u32(b) as f64,
+            "float" => BigEndian::read_f32(b) as f64,
+            "double" => BigEndian::read_f64(b),
+            "unsigned_long" | "vtktypeuint64" => BigEndian::read_u64(b) as f64,
+            _ => BigEndian::read_i64(b) as f64,
+        });
+        Ok(values.collect())
+    }
+
+    /// Read a cell section, either in the classic format, `count id0 id1 ...` for each cell, or in
+    /// the `OFFSETS` and `CONNECTIVITY` format of VTK 5.
+    ///
+    /// Returns the connectivity and the number of points of each cell.
+    fn cells(&mut self, first: usize, second: usize) -> Result<(Vec<usize>, Vec<usize>)> {
+        let header = self.peek_line();
+        if header.to_uppercase().starts_with("OFFSETS") {
+            self.next_line();
+            let dtype = header.split_whitespace().nth(1).unwrap_or("vtktypeint64").to_string();
+            let offsets = self.numbers(first, &dtype)?;
+            let header = self.line()?;
+            let dtype = header.split_whitespace().nth(1).unwrap_or("vtktypeint64").to_string();
+            let connectivity = self.numbers(second, &dtype)?;
+            let connectivity = connectivity.into_iter().map(|i| i as usize).collect();
+
+            // The offsets must go from 0 to the size of the connectivity, without decreasing
+            if offsets.first().is_some_and(|&o| o != 0.0)
+                || offsets.last().is_some_and(|&o| o != second as f64)
+                || offsets.windows(2).any(|w| w[1] < w[0])
+            {
+                bail!("Invalid offsets in cells section");
+            }
+            let lengths = offsets.windows(2).map(|w| (w[1] - w[0]) as usize).collect();
+            Ok((connectivity, lengths))
+        } else {
+            // Each cell has its number of points, then the indices of its points
+            let Some(nb_indices) = second.checked_sub(first) else {
+                bail!("{} values can't hold {} cells", second, first);
+            };
+            let values = self.numbers(second, "int")?;
+            let mut connectivity = Vec::with_capacity(nb_indices);
+            let mut lengths = Vec::with_capacity(first);
+            let mut i = 0;
+            for _ in 0..first {
+                let length = match values.get(i) {
+                    Some(&length) => length as usize,
+                    None => bail!("Not enough values in cells section"),
+                };
+                match values.get(i + 1..).and_then(|rest| rest.get(..length)) {
+                    Some(ids) => connectivity.extend(ids.iter().map(|&id| id as usize)),
+                    None => bail!("Not enough values in cells section"),
+                }
+                lengths.push(length);
+                i += 1 + length;
+            }
+            if i != second {
+                bail!("Expected {} values in cells section, found {}", second, i);
+            }
+            Ok((connectivity, lengths))
+        }
+    }
+}
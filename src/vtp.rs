@@ -0,0 +1,424 @@
+use std::{
+    fs::{read, File},
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use roxmltree::{Document, Node};
+
+use crate::{
+    vtk::{build_tractogram, data_arrays, DataArray},
+    Header, Point, Tractogram,
+};
+
+/// Size of the blocks compressed by `vtkZLibDataCompressor`.
+const BLOCK_SIZE: usize = 32768;
+
+/// Encoding of the appended data of a VTK XML file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VtpEncoding {
+    /// Binary data, as it is. This is the most compact format.
+    Raw,
+
+    /// Base64 encoded binary data, which keeps the file valid XML.
+    Base64,
+}
+
+/// Read a VTK XML PolyData file (.vtp) containing `Lines` cells.
+///
+/// Supports the `ascii`, `binary` and `appended` (raw or base64) formats, in both byte orders,
+/// with or without zlib compression. Like `read_vtk`, the `PointData` arrays are read as
+/// per-point scalars and the `CellData` arrays as properties.
+pub fn read_vtp<P: AsRef<Path>>(path: P) -> Result<(Header, Tractogram)> {
+    let path = path.as_ref();
+    let bytes = read(path).with_context(|| format!("Failed to load {:?}", path))?;
+    read_xml(&bytes).with_context(|| format!("Failed to read {:?} as a VTP file", path))
+}
+
+/// Write `tractogram` as a VTK XML PolyData file (.vtp), with all data arrays appended at the end
+/// of the file and, optionally, compressed with zlib.
+///
+/// The points are written as they are, thus they should be in RAS+ mm. The scalars are written as
+/// `PointData` and the properties as `CellData`, using the names in `header`.
+pub fn write_vtp<P: AsRef<Path>>(
+    path: P,
+    header: &Header,
+    tractogram: &Tractogram,
+    encoding: VtpEncoding,
+    compressed: bool,
+) -> Result<()> {
+    let path = path.as_ref();
+    let streamlines = &tractogram.streamlines;
+    let nb_points = streamlines.data.len();
+    let nb_lines = streamlines.len();
+    let scalars = data_arrays(header.scalars_groups(), &tractogram.scalars, nb_points)?;
+    let properties = data_arrays(header.properties_groups(), &tractogram.properties, nb_lines)?;
+
+    let mut xml = String::new();
+    let mut appended = vec![];
+    let mut add_array = |xml: &mut String, type_: &str, name: &str, nb: usize, data: Vec<u8>| {
+        let name = if name.is_empty() { String::new() } else { format!(" Name=\"{}\"", name) };
+        xml.push_str(&format!(
+            "        <DataArray type=\"{}\"{} NumberOfComponents=\"{}\" format=\"appended\" \
+             offset=\"{}\"/>\n",
+            type_,
+            name,
+            nb,
+            appended.len()
+        ));
+        appended.extend(encode_block(&data, encoding, compressed));
+    };
+
+    let compressor = if compressed { " compressor=\"vtkZLibDataCompressor\"" } else { "" };
+    xml.push_str("<?xml version=\"1.0\"?>\n");
+    xml.push_str(&format!(
+        "<VTKFile type=\"PolyData\" version=\"1.0\" byte_order=\"LittleEndian\" \
+         header_type=\"UInt64\"{}>\n",
+        compressor
+    ));
+    xml.push_str("  <PolyData>\n");
+    xml.push_str(&format!(
+        "    <Piece NumberOfPoints=\"{}\" NumberOfVerts=\"0\" NumberOfLines=\"{}\" \
+         NumberOfStrips=\"0\" NumberOfPolys=\"0\">\n",
+        nb_points, nb_lines
+    ));
+    for (tag, arrays) in [("PointData", &scalars), ("CellData", &properties)] {
+        xml.push_str(&format!("      <{}>\n", tag));
+        for array in arrays {
+            let data = array.values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            let name = escape(&array.name);
+            add_array(&mut xml, "Float32", &name, array.nb_components, data);
+        }
+        xml.push_str(&format!("      </{}>\n", tag));
+    }
+
+    xml.push_str("      <Points>\n");
+    let data = streamlines.data.iter().flat_map(|p| [p.x, p.y, p.z]).flat_map(f32::to_le_bytes);
+    add_array(&mut xml, "Float32", "Points", 3, data.collect());
+    xml.push_str("      </Points>\n");
+
+    // The points are stored in order, thus the offsets of `ArraySequence` are exactly the offsets
+    // expected by VTK, without the first 0.
+    xml.push_str("      <Lines>\n");
+    let connectivity = (0..nb_points as i64).flat_map(i64::to_le_bytes).collect();
+    add_array(&mut xml, "Int64", "connectivity", 1, connectivity);
+    let offsets = streamlines.offsets[1..].iter().flat_map(|&o| (o as i64).to_le_bytes());
+    add_array(&mut xml, "Int64", "offsets", 1, offsets.collect());
+    xml.push_str("      </Lines>\n");
+
+    xml.push_str("    </Piece>\n");
+    xml.push_str("  </PolyData>\n");
+    match encoding {
+        VtpEncoding::Raw => xml.push_str("  <AppendedData encoding=\"raw\">\n   _"),
+        VtpEncoding::Base64 => xml.push_str("  <AppendedData encoding=\"base64\">\n   _"),
+    }
+
+    let f = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut writer = BufWriter::new(f);
+    writer.write_all(xml.as_bytes())?;
+    writer.write_all(&appended)?;
+    writer.write_all(b"\n  </AppendedData>\n</VTKFile>\n")?;
+    Ok(())
+}
+
+/// Encode a block of binary data, with its header, as VTK would.
+fn encode_block(data: &[u8], encoding: VtpEncoding, compressed: bool) -> Vec<u8> {
+    if !compressed {
+        let mut block = (data.len() as u64).to_le_bytes().to_vec();
+        block.extend_from_slice(data);
+        return match encoding {
+            VtpEncoding::Raw => block,
+            VtpEncoding::Base64 => STANDARD.encode(block).into_bytes(),
+        };
+    }
+
+    let chunks = data
+        .chunks(BLOCK_SIZE)
+        .map(|chunk| {
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            encoder.write_all(chunk).unwrap(); // Can't fail on a Vec
+            encoder.finish().unwrap()
+        })
+        .collect::<Vec<_>>();
+    let mut header = vec![chunks.len() as u64, BLOCK_SIZE as u64, (data.len() % BLOCK_SIZE) as u64];
+    header.extend(chunks.iter().map(|chunk| chunk.len() as u64));
+    let header = header.iter().flat_map(|h| h.to_le_bytes()).collect::<Vec<_>>();
+    let chunks = chunks.concat();
+
+    // The header and the data are encoded separately when compressed
+    match encoding {
+        VtpEncoding::Raw => [header, chunks].concat(),
+        VtpEncoding::Base64 => (STANDARD.encode(header) + &STANDARD.encode(chunks)).into_bytes(),
+    }
+}
+
+fn escape(name: &str) -> String {
+    name.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn read_xml(bytes: &[u8]) -> Result<(Header, Tractogram)> {
+    // The raw appended data is not valid XML, so it's removed before parsing the rest of the file.
+    let mut appended: &[u8] = &[];
+    let mut xml = bytes;
+    let closing_tag;
+    if let Some(start) = find(bytes, b"<AppendedData") {
+        let underscore = match find(&bytes[start..], b"_") {
+            Some(underscore) => start + underscore,
+            None => bail!("Missing '_' at the start of the appended data"),
+        };
+        appended = &bytes[underscore + 1..];
+        closing_tag = [&bytes[..underscore], b"</AppendedData></VTKFile>"].concat();
+        xml = &closing_tag;
+    }
+    let xml = std::str::from_utf8(xml)?;
+    let document = Document::parse(xml)?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "VTKFile" || root.attribute("type") != Some("PolyData") {
+        bail!("Not a VTK PolyData file");
+    }
+    let decoder = Decoder {
+        big_endian: root.attribute("byte_order") == Some("BigEndian"),
+        header_size: if root.attribute("header_type") == Some("UInt64") { 8 } else { 4 },
+        compressed: match root.attribute("compressor") {
+            None | Some("") => false,
+            Some("vtkZLibDataCompressor") => true,
+            Some(compressor) => bail!("Unsupported compressor {:?}", compressor),
+        },
+        appended,
+        appended_base64: child(root, "AppendedData")
+            .is_some_and(|node| node.attribute("encoding") == Some("base64")),
+    };
+
+    let pieces: Vec<_> = child(root, "PolyData")
+        .map(|polydata| polydata.children().filter(|n| n.has_tag_name("Piece")).collect())
+        .unwrap_or_default();
+    if pieces.len() != 1 {
+        bail!("Only VTP files with a single Piece are supported. Found {}", pieces.len());
+    }
+    let piece = pieces[0];
+
+    let points = match child(piece, "Points").and_then(|points| child(points, "DataArray")) {
+        Some(array) => decoder.values(array)?,
+        None => vec![],
+    };
+    let points = points
+        .chunks_exact(3)
+        .map(|c| Point::new(c[0] as f32, c[1] as f32, c[2] as f32))
+        .collect::<Vec<_>>();
+
+    let mut connectivity = vec![];
+    let mut lengths = vec![];
+    if let Some(lines) = child(piece, "Lines") {
+        for array in lines.children().filter(|n| n.has_tag_name("DataArray")) {
+            let values = decoder.values(array)?.into_iter().map(|v| v as usize);
+            match array.attribute("Name") {
+                Some("connectivity") => connectivity = values.collect(),
+                Some("offsets") => {
+                    let mut previous = 0;
+                    for offset in values {
+                        if offset < previous {
+                            bail!("The offsets of the lines are not sorted.");
+                        }
+                        lengths.push(offset - previous);
+                        previous = offset;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if lengths.iter().sum::<usize>() != connectivity.len() {
+        bail!("The offsets of the lines don't match their connectivity.");
+    }
+
+    let mut attributes = [vec![], vec![]];
+    for (tag, arrays) in ["PointData", "CellData"].iter().zip(&mut attributes) {
+        let Some(node) = child(piece, tag) else {
+            continue;
+        };
+        for array in node.children().filter(|n| n.has_tag_name("DataArray")) {
+            let nb_components = match array.attribute("NumberOfComponents") {
+                Some(nb) => nb.parse::<usize>()?,
+                None => 1,
+            };
+            arrays.push(DataArray {
+                name: array.attribute("Name").unwrap_or("").to_string(),
+                nb_components,
+                values: decoder.values(array)?.into_iter().map(|v| v as f32).collect(),
+            });
+        }
+    }
+
+    let [point_data, cell_data] = attributes;
+    build_tractogram(&points, &connectivity, lengths, &point_data, &cell_data)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Maximal compression ratio of zlib.
+const MAX_ZLIB_RATIO: usize = 1032;
+
+/// Decodes the data of the `DataArray` elements.
+struct Decoder<'a> {
+    big_endian: bool,
+    header_size: usize,
+    compressed: bool,
+    appended: &'a [u8],
+    appended_base64: bool,
+}
+
+impl<'a> Decoder<'a> {
+    fn values(&self, array: Node) -> Result<Vec<f64>> {
+        let type_ = array.attribute("type").unwrap_or("Float32");
+        let bytes = match array.attribute("format").unwrap_or("ascii") {
+            "ascii" => {
+                let text = array.text().unwrap_or("");
+                let values = text.split_whitespace().map(|v| v.parse::<f64>());
+                return Ok(values.collect::<Result<Vec<_>, _>>()?);
+            }
+            "binary" => {
+                let text = array.text().unwrap_or("");
+                let text = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect::<Vec<_>>();
+                self.block(&text, true)?
+            }
+            "appended" => {
+                let offset = match array.attribute("offset").map(|o| o.parse::<usize>()) {
+                    Some(Ok(offset)) if offset <= self.appended.len() => offset,
+                    _ => bail!("Invalid offset for the array {:?}", array.attribute("Name")),
+                };
+                self.block(&self.appended[offset..], self.appended_base64)?
+            }
+            format => bail!("Unknown data array format {:?}", format),
+        };
+        self.numbers(&bytes, type_)
+    }
+
+    /// Decode the block of binary data at the start of `data`.
+    fn block(&self, data: &[u8], base64: bool) -> Result<Vec<u8>> {
+        let size = self.header_size;
+        if !self.compressed {
+            let header = self.decode(data, 0, size, base64)?;
+            let nb_bytes = self.read_size(&header)?;
+            let Some(block_len) = size.checked_add(nb_bytes) else {
+                bail!("Invalid block size {}", nb_bytes);
+            };
+            let block = self.decode(data, 0, block_len, base64)?;
+            return Ok(block[size..].to_vec());
+        }
+
+        let header = self.decode(data, 0, 3 * size, base64)?;
+        let nb_blocks = self.read_size(&header)?;
+        let block_size = self.read_size(&header[size..])?;
+        let last_size = self.read_size(&header[2 * size..])?;
+        let Some(header_len) = nb_blocks.checked_add(3).and_then(|n| n.checked_mul(size)) else {
+            bail!("Invalid number of compressed blocks {}", nb_blocks);
+        };
+        let header = self.decode(data, 0, header_len, base64)?;
+        let compressed_sizes = header[3 * size..]
+            .chunks(size)
+            .map(|c| self.read_size(c))
+            .collect::<Result<Vec<_>>>()?;
+
+        // The compressed data is encoded separately from its header
+        let header_encoded_len = if base64 { header_len.div_ceil(3) * 4 } else { header_len };
+        let Some(total) = compressed_sizes.iter().try_fold(0usize, |sum, &s| sum.checked_add(s))
+        else {
+            bail!("Invalid sizes of compressed blocks");
+        };
+        let compressed = self.decode(data, header_encoded_len, total, base64)?;
+
+        // The sizes in the header can't be trusted, so the decompressed data is never allowed to
+        // grow larger than what they announce, nor than what the compressed data can hold.
+        let nb_bytes = nb_blocks.saturating_mul(block_size);
+        let mut block = Vec::with_capacity(nb_bytes.min(total.saturating_mul(MAX_ZLIB_RATIO)));
+        let mut start = 0;
+        for (i, &compressed_size) in compressed_sizes.iter().enumerate() {
+            let expected =
+                if i + 1 == nb_blocks && last_size != 0 { last_size } else { block_size };
+            let before = block.len();
+            ZlibDecoder::new(&compressed[start..start + compressed_size])
+                .take((expected as u64).saturating_add(1))
+                .read_to_end(&mut block)?;
+            if block.len() - before != expected {
+                bail!("Compressed block {} has an unexpected size", i);
+            }
+            start += compressed_size;
+        }
+        Ok(block)
+    }
+
+    /// Returns the first `nb_bytes` bytes of `data[start..]`, decoding them if needed.
+    fn decode(&self, data: &[u8], start: usize, nb_bytes: usize, base64: bool) -> Result<Vec<u8>> {
+        let encoded_len = if base64 { nb_bytes.div_ceil(3).checked_mul(4) } else { Some(nb_bytes) };
+        let end = match encoded_len.and_then(|len| start.checked_add(len)) {
+            Some(end) if end <= data.len() => end,
+            _ => bail!("Unexpected end of data"),
+        };
+        if base64 {
+            let mut decoded = STANDARD.decode(&data[start..end])?;
+            decoded.truncate(nb_bytes);
+            Ok(decoded)
+        } else {
+            Ok(data[start..end].to_vec())
+        }
+    }
+
+    /// Read a size of the header of a block.
+    fn read_size(&self, bytes: &[u8]) -> Result<usize> {
+        match usize::try_from(self.read_uint(bytes)) {
+            Ok(size) => Ok(size),
+            Err(_) => bail!("Invalid size in block header"),
+        }
+    }
+
+    fn read_uint(&self, bytes: &[u8]) -> u64 {
+        match (self.header_size, self.big_endian) {
+            (8, false) => LittleEndian::read_u64(bytes),
+            (8, true) => BigEndian::read_u64(bytes),
+            (_, false) => LittleEndian::read_u32(bytes) as u64,
+            (_, true) => BigEndian::read_u32(bytes) as u64,
+        }
+    }
+
+    fn numbers(&self, bytes: &[u8], type_: &str) -> Result<Vec<f64>> {
+        if self.big_endian {
+            numbers::<BigEndian>(bytes, type_)
+        } else {
+            numbers::<LittleEndian>(bytes, type_)
+        }
+    }
+}
+
+fn numbers<E: ByteOrder>(bytes: &[u8], type_: &str) -> Result<Vec<f64>> {
+    let size = match type_ {
+        "Int8" | "UInt8" => 1,
+        "Int16" | "UInt16" => 2,
+        "Int32" | "UInt32" | "Float32" => 4,
+        "Int64" | "UInt64" | "Float64" => 8,
+        _ => bail!("Unsupported data type {:?}", type_),
+    };
+    let values = bytes.chunks_exact(size).map(|b| match type_ {
+        "Int8" => b[0] as i8 as f64,
+        "UInt8" => b[0] as f64,
+        "Int16" => E::read_i16(b) as f64,
+        "UInt16" => E::read_u16(b) as f64,
+        "Int32" => E::read_i32(b) as f64,
+        "UInt32" => E::read_u32(b) as f64,
+        "Float32" => E::read_f32(b) as f64,
+        "Int64" => E::read_i64(b) as f64,
+        "UInt64" => E::read_u64(b) as f64,
+        _ => E::read_f64(b),
+    });
+    Ok(values.collect())
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use nalgebra::Vector3;
+use ndarray::{Array4, ArrayD, Axis, Order};
+use nifti::{IntoNdArray, NiftiHeader, NiftiObject, ReaderOptions};
+
+use crate::{interpolation::trilinear, Affine4, Header, Point, Streamlines, Tractogram};
+
+/// Convention used to store the displacements of a deformation field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WarpConvention {
+    /// ITK and ANTs displacement fields, e.g., `*Warp.nii.gz` and `*InverseWarp.nii.gz`. The
+    /// displacements are in mm, in LPS physical space.
+    Itk,
+
+    /// FSL relative warp fields, e.g., from `fnirt --fout`, `invwarp` or `convertwarp --relout`.
+    /// The displacements are in mm along the voxel axes, in FSL scaled-voxel space, where the first
+    /// axis is flipped for images in neurological order.
+    Fsl,
+}
+
+/// Nonlinear transformation stored as a displacement field.
+///
+/// Registration tools define their warps from the target space to the source space, because it's
+/// what is needed to resample an image. To bring streamlines from the source space to the target
+/// space, the *inverse* warp must be used, e.g., `*InverseWarp.nii.gz` for ANTs or the output of
+/// `invwarp` for FSL.
+pub struct DeformationField {
+    header: NiftiHeader,
+    affine: Affine4,
+    to_voxel: Affine4,
+
+    /// Displacements in RAS+ mm, indexed by (x, y, z, component).
+    displacements: Array4<f32>,
+}
+
+impl DeformationField {
+    /// Load a deformation field from a NIfTI file.
+    ///
+    /// The image must have 3 components per voxel, either as a 4D image (FSL) or as a 5D image
+    /// with a singleton 4th dimension (ITK).
+    pub fn from_nifti<P: AsRef<Path>>(path: P, convention: WarpConvention) -> Result<Self> {
+        let object = ReaderOptions::new()
+            .read_file(path.as_ref())
+            .with_context(|| format!("Failed to load {:?}", path.as_ref()))?;
+        let header = object.header().clone();
+        let data = object.into_volume().into_ndarray::<f32>()?;
+        DeformationField::new(&header, data, convention)
+    }
+
+    /// Build a deformation field from an image `data` and its `header`.
+    ///
+    /// `data` must be ordered like the images returned by `nifti`, that is, (x, y, z, ...).
+    pub fn new(
+        header: &NiftiHeader,
+        data: ArrayD<f32>,
+        convention: WarpConvention,
+    ) -> Result<Self> {
+        let shape = data.shape().to_vec();
+        let nb_components = shape.iter().skip(3).product::<usize>();
+        if shape.len() < 4 || nb_components != 3 {
+            bail!("A deformation field must have 3 components per voxel. Got shape {:?}", shape);
+        }
+
+        let (x, y, z) = (shape[0], shape[1], shape[2]);
+        let mut displacements = data.to_shape(((x, y, z, 3), Order::ColumnMajor))?.into_owned();
+
+        let affine = header.affine::<f32>();
+        let to_voxel = match affine.try_inverse() {
+            Some(to_voxel) => to_voxel,
+            None => bail!("The affine of the deformation field is not invertible."),
+        };
+
+        let linear = affine.fixed_view::<3, 3>(0, 0).into_owned();
+        let voxel_size = Vector3::from_fn(|i, _| linear.column(i).norm());
+        let radiological = linear.determinant() < 0.0;
+        for mut d in displacements.lanes_mut(Axis(3)) {
+            let v = Vector3::new(d[0], d[1], d[2]);
+            let v = match convention {
+                WarpConvention::Itk => Vector3::new(-v.x, -v.y, v.z),
+                WarpConvention::Fsl => {
+                    let x = if radiological { v.x } else { -v.x };
+                    linear * Vector3::new(x, v.y, v.z).component_div(&voxel_size)
+                }
+            };
+            d[0] = v.x;
+            d[1] = v.y;
+            d[2] = v.z;
+        }
+
+        Ok(DeformationField { header: header.clone(), affine, to_voxel, displacements })
+    }
+
+    /// Affine mapping the voxel indices of the field to RAS+ mm.
+    pub fn affine(&self) -> &Affine4 {
+        &self.affine
+    }
+
+    /// Trk header of the space in which the field is defined, which is usually the target space.
+    pub fn header(&self) -> Header {
+        Header::from_nifti(&self.header)
+    }
+
+    /// Displacement in RAS+ mm at `p`, using trilinear interpolation.
+    pub fn displacement(&self, p: &Point) -> Vector3<f32> {
+        let voxel = self.to_voxel.transform_point(p);
+        Vector3::from_fn(|i, _| trilinear(&self.displacements.index_axis(Axis(3), i), &voxel))
+    }
+
+    /// Returns `p` moved by the field.
+    pub fn apply(&self, p: &Point) -> Point {
+        p + self.displacement(p)
+    }
+}
+
+/// Apply the deformation `field` to all points of `tractogram`, which must be in RAS+ mm.
+///
+/// `pre` is applied to the points before the field and `post` after it, e.g., the affine parts of
+/// an ANTs or FSL transform chain. Returns the moved tractogram along with the header of the
+/// field's image, with the scalars and properties of `header`.
+pub fn warp_tractogram(
+    tractogram: &Tractogram,
+    header: &Header,
+    field: &DeformationField,
+    pre: Option<&Affine4>,
+    post: Option<&Affine4>,
+) -> (Header, Tractogram) {
+    let mut new_header = field.header();
+    new_header.copy_scalars_and_properties(header);
+
+    let data = tractogram
+        .streamlines
+        .data
+        .iter()
+        .map(|p| {
+            let p = pre.map_or(*p, |pre| pre.transform_point(p));
+            let p = field.apply(&p);
+            post.map_or(p, |post| post.transform_point(&p))
+        })
+        .collect();
+    let streamlines = Streamlines { offsets: tractogram.streamlines.offsets.clone(), data };
+    let tractogram =
+        Tractogram::new(streamlines, tractogram.scalars.clone(), tractogram.properties.clone());
+    (new_header, tractogram)
+}
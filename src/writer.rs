@@ -6,7 +6,7 @@ use nalgebra::Vector4;
 
 use crate::{
     affine::get_affine_and_translation,
-    tractogram::{Point, RefTractogramItem, Tractogram, TractogramItem},
+    tractogram::{Point, RefTractogramItem, Tractogram, TractogramItem, TractogramView},
     Affine, Affine4, CHeader, Header, Spacing, Translation, TrkEndianness,
 };
 
@@ -61,6 +61,14 @@ impl Writable for Tractogram {
     }
 }
 
+impl Writable for TractogramView<'_> {
+    fn write(self, w: &mut Writer) {
+        for item in self {
+            item.write(w);
+        }
+    }
+}
+
 impl Writable for TractogramItem {
     fn write(self, writer: &mut Writer) {
         let (streamline, scalars, properties) = self;
@@ -75,7 +83,7 @@ impl<'data> Writable for RefTractogramItem<'data> {
     }
 }
 
-impl<'data> Writable for &'data [Point] {
+impl Writable for &[Point] {
     fn write(self, writer: &mut Writer) {
         write_streamline!(writer, self, self.len());
     }
@@ -163,7 +171,7 @@ impl Writer {
     ///
     /// The TrackVis header (on disk) will **not** be modified.
     pub fn apply_affine(&mut self, affine: &Affine4) {
-        self.affine4 = self.affine4 * affine;
+        self.affine4 *= affine;
         let (affine, translation) = get_affine_and_translation(&self.affine4);
         self.affine = affine;
         self.translation = translation;
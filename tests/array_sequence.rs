@@ -46,6 +46,7 @@ fn test_new_too_much() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_empty() {
     let mut arr = ArraySequence::empty();
     assert_eq!(arr.is_empty(), true);
@@ -180,3 +181,123 @@ fn test_filter() {
     // Ensure that arr is still usable
     assert_eq!(arr.len(), 4);
 }
+
+#[test]
+fn test_remove_insert_replace() {
+    let mut arr = ArraySequence::new(vec![2, 3, 1], vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(arr.remove(1), [3, 4, 5]);
+    assert_eq!(arr.offsets, [0, 2, 3]);
+    assert_eq!(&arr[1], &[6]);
+
+    arr.insert(1, vec![7, 8]);
+    assert_eq!(arr.offsets, [0, 2, 4, 5]);
+    assert_eq!(arr.data, [1, 2, 7, 8, 6]);
+    arr.insert(3, vec![]);
+    arr.insert(0, vec![9]);
+    assert_eq!(arr.offsets, [0, 1, 3, 5, 6, 6]);
+    assert_eq!(arr.len(), 5);
+
+    assert_eq!(arr.replace(2, vec![10, 11, 12]), [7, 8]);
+    assert_eq!(arr.offsets, [0, 1, 3, 6, 7, 7]);
+    assert_eq!(arr.replace(1, vec![]), [1, 2]);
+    assert_eq!(arr.offsets, [0, 1, 1, 4, 5, 5]);
+    assert_eq!(arr.data, [9, 10, 11, 12, 6]);
+}
+
+#[test]
+fn test_swap_remove() {
+    let mut arr = ArraySequence::new(vec![2, 3, 1], vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(arr.swap_remove(0), [1, 2]);
+    assert_eq!(arr.offsets, [0, 1, 4]);
+    assert_eq!(arr.data, [6, 3, 4, 5]);
+    assert_eq!(arr.swap_remove(1), [3, 4, 5]);
+    assert_eq!(arr.swap_remove(0), [6]);
+    assert_eq!(arr.len(), 0);
+    assert!(arr.is_empty());
+}
+
+#[test]
+fn test_slice_split_append_truncate() {
+    let streamlines = get_toy_streamlines();
+    let slice = streamlines.slice(1..3);
+    assert_eq!(slice.offsets, [0, 3, 6]);
+    assert_eq!(&slice[0], &streamlines[1]);
+    assert!(streamlines.view(1..3).to_array_sequence() == slice);
+    assert_eq!(streamlines.slice(2..2).len(), 0);
+
+    let mut first = streamlines.clone();
+    let mut second = first.split_off(1);
+    assert_eq!(first.offsets, [0, 2]);
+    assert!(second == slice);
+
+    first.append(&mut second);
+    assert!(first == streamlines);
+    assert_eq!(second.len(), 0);
+    assert!(second.is_empty());
+
+    first.truncate(5);
+    assert_eq!(first.len(), 3);
+    first.truncate(1);
+    assert_eq!(first.offsets, [0, 2]);
+    assert_eq!(first.data.len(), 2);
+}
+
+#[test]
+fn test_permute() {
+    let mut arr = ArraySequence::new(vec![2, 3, 1], vec![1, 2, 3, 4, 5, 6]);
+    arr.permute(&[2, 0, 1]);
+    assert_eq!(arr.offsets, [0, 1, 3, 6]);
+    assert_eq!(arr.data, [6, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+#[should_panic]
+fn test_permute_duplicates() {
+    let mut arr = ArraySequence::new(vec![2, 3, 1], vec![1, 2, 3, 4, 5, 6]);
+    arr.permute(&[0, 0, 1]);
+}
+
+#[test]
+fn test_owning_iterator() {
+    let arr: ArraySequence<i32> = vec![vec![1, 2], vec![], vec![3]].into_iter().collect();
+    assert_eq!(arr.offsets, [0, 2, 2, 3]);
+    let arrays = arr.into_arrays().collect::<Vec<_>>();
+    assert_eq!(arrays, [vec![1, 2], vec![], vec![3]]);
+
+    let streamlines = get_toy_streamlines();
+    let copy = streamlines.clone().into_arrays().collect::<ArraySequence<Point>>();
+    assert!(copy == streamlines);
+}
+
+#[test]
+fn test_views() {
+    let streamlines = get_toy_streamlines();
+    let view = streamlines.view(1..3);
+    assert_eq!(view.len(), 2);
+    assert!(!view.is_empty());
+    assert_eq!(&view[0], &streamlines[1]);
+    assert_eq!(view.length_of_array(1), 3);
+    assert_eq!(view.iter().next_back().unwrap(), &streamlines[2]);
+    assert!(view.to_array_sequence() == streamlines.slice(1..3));
+    assert_eq!(&view.view(1..2)[0], &streamlines[2]);
+    assert!(streamlines.view(3..3).is_empty());
+
+    // Same semantics as `ArraySequence::is_empty`, the view contains no elements
+    let arr = ArraySequence::new(vec![0, 2], vec![1, 2]);
+    assert!(arr.view(0..1).is_empty());
+    assert_eq!(arr.view(0..1).len(), 1);
+    assert!(!arr.view(0..2).is_empty());
+
+    let indices = [2, 0, 2];
+    let view = streamlines.view_indices(&indices);
+    let arrays = view.iter().collect::<Vec<_>>();
+    assert_eq!(arrays, [&streamlines[2], &streamlines[0], &streamlines[2]]);
+    assert_eq!(view.to_array_sequence().offsets, [0, 3, 5, 8]);
+    assert_eq!(&view.view(1..3)[0], &streamlines[0]);
+}
+
+#[test]
+#[should_panic]
+fn test_view_out_of_bounds() {
+    get_toy_streamlines().view_indices(&[0, 3]);
+}
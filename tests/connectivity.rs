@@ -0,0 +1,119 @@
+#[cfg(feature = "nifti_images")]
+mod nifti_tests {
+    use anyhow::Result;
+    use ndarray::Array3;
+    use trk_io::{
+        connectivity::{connectivity_matrix, ConnectivityOptions},
+        Affine4, ArraySequence, Header, Point, Streamlines, Tractogram,
+    };
+
+    /// Labels 1, 2 and 3 at x = 0, 4 and 8, with background in between.
+    fn get_toy_parcellation() -> Array3<i32> {
+        let mut parcellation = Array3::zeros((9, 1, 1));
+        parcellation[(0, 0, 0)] = 1;
+        parcellation[(4, 0, 0)] = 2;
+        parcellation[(8, 0, 0)] = 3;
+        parcellation
+    }
+
+    fn get_toy_tractogram() -> (Header, Tractogram) {
+        let mut header = Header::default();
+        header.add_property("weight").unwrap();
+
+        let streamlines = Streamlines::new(
+            vec![2, 3, 2, 2],
+            vec![
+                Point::new(0.0, 0.0, 0.0), // 1 - 2
+                Point::new(4.0, 0.0, 0.0),
+                Point::new(8.0, 0.0, 0.0), // 3 - 1
+                Point::new(4.0, 0.0, 0.0),
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0), // 1 - 2, only when searching
+                Point::new(3.0, 0.0, 0.0),
+                Point::new(2.0, 0.0, 0.0), // Never assigned
+                Point::new(6.0, 0.0, 0.0),
+            ],
+        );
+        let properties = ArraySequence::new(vec![1, 1, 1, 1], vec![1.0, 2.0, 3.0, 4.0]);
+        (header, Tractogram::new(streamlines, ArraySequence::empty(), properties))
+    }
+
+    #[test]
+    fn test_connectivity_matrix() -> Result<()> {
+        let (header, tractogram) = get_toy_tractogram();
+        let parcellation = get_toy_parcellation();
+        let options = ConnectivityOptions::default();
+        let connectome = connectivity_matrix(
+            &tractogram,
+            &header,
+            &parcellation,
+            &Affine4::identity(),
+            &options,
+        )?;
+
+        assert_eq!(connectome.labels, vec![1, 2, 3]);
+        assert_eq!(connectome.nb_unassigned, 2);
+        assert_eq!(connectome.count[(0, 1)], 1.0);
+        assert_eq!(connectome.count[(1, 0)], 1.0);
+        assert_eq!(connectome.count[(0, 2)], 1.0);
+        assert_eq!(connectome.count[(2, 0)], 1.0);
+        assert_eq!(connectome.count[(1, 2)], 0.0);
+        assert_eq!(connectome.mean_length[(0, 1)], 4.0);
+        assert_eq!(connectome.mean_length[(2, 0)], 8.0);
+        assert!(connectome.mean_scalar.is_none());
+        assert!(connectome.edges.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_connectivity_matrix_search() -> Result<()> {
+        let (header, tractogram) = get_toy_tractogram();
+        let parcellation = get_toy_parcellation();
+        let options =
+            ConnectivityOptions { search_radius: 1.0, scalar: Some("weight"), return_edges: true };
+        let connectome = connectivity_matrix(
+            &tractogram,
+            &header,
+            &parcellation,
+            &Affine4::identity(),
+            &options,
+        )?;
+
+        assert_eq!(connectome.nb_unassigned, 1);
+        assert_eq!(connectome.count[(0, 1)], 2.0);
+        assert_eq!(connectome.mean_length[(0, 1)], 3.0);
+        let mean_scalar = connectome.mean_scalar.unwrap();
+        assert_eq!(mean_scalar[(0, 1)], 2.0);
+        assert_eq!(mean_scalar[(2, 0)], 2.0);
+
+        let edges = connectome.edges.unwrap();
+        assert_eq!(edges[&(0, 1)], vec![0, 2]);
+        assert_eq!(edges[&(0, 2)], vec![1]);
+        assert_eq!(edges.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_connectivity_matrix_unknown_scalar() {
+        let (header, tractogram) = get_toy_tractogram();
+        let options = ConnectivityOptions { scalar: Some("fa"), ..ConnectivityOptions::default() };
+        let affine = Affine4::identity();
+        let parcellation = get_toy_parcellation();
+        assert!(
+            connectivity_matrix(&tractogram, &header, &parcellation, &affine, &options).is_err()
+        );
+    }
+
+    #[test]
+    fn test_connectivity_matrix_missing_values() {
+        let (header, mut tractogram) = get_toy_tractogram();
+        tractogram.properties = ArraySequence::empty();
+        let options =
+            ConnectivityOptions { scalar: Some("weight"), ..ConnectivityOptions::default() };
+        let affine = Affine4::identity();
+        let parcellation = get_toy_parcellation();
+        assert!(
+            connectivity_matrix(&tractogram, &header, &parcellation, &affine, &options).is_err()
+        );
+    }
+}
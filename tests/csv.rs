@@ -0,0 +1,110 @@
+mod test;
+
+use std::fs::{read_to_string, write};
+
+use anyhow::Result;
+
+use test::load_trk;
+use trk_io::{
+    csv::{read_csv, CsvLayout, CsvWriter},
+    Reader,
+};
+
+fn get_random_csv_path() -> String {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.keep().join("out.csv");
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_points_round_trip() -> Result<()> {
+    let (header, tractogram) = load_trk("data/complex.trk");
+    let write_to = get_random_csv_path();
+    {
+        let mut writer = CsvWriter::new(&write_to, &header, CsvLayout::Points)?;
+        writer.write_tractogram(&tractogram)?;
+    }
+
+    let (csv_header, csv_tractogram) = read_csv(&write_to)?;
+    assert_eq!(csv_header.nb_streamlines, tractogram.streamlines.len());
+    assert_eq!(csv_header.scalars_name, header.scalars_name);
+    assert!(csv_header.properties_name.is_empty());
+    assert!(csv_tractogram.streamlines == tractogram.streamlines);
+    assert_eq!(csv_tractogram.scalars.data, tractogram.scalars.data);
+    Ok(())
+}
+
+#[test]
+fn test_streamlines_table() -> Result<()> {
+    let (header, tractogram) = load_trk("data/complex.trk");
+    let write_to = get_random_csv_path();
+    {
+        let mut writer = CsvWriter::new(&write_to, &header, CsvLayout::Streamlines)?;
+        writer.write_tractogram(&tractogram)?;
+    }
+
+    let text = read_to_string(&write_to)?;
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1 + tractogram.streamlines.len());
+    let columns = lines[0].split(',').collect::<Vec<_>>();
+    assert_eq!(columns[..3], ["streamline_id", "length", "n_points"]);
+    assert_eq!(
+        columns[3..],
+        ["mean_colors_0", "mean_colors_1", "mean_colors_2", "mean_curvature", "mean_torsion"]
+    );
+
+    let fields = lines[2].split(',').collect::<Vec<_>>();
+    assert_eq!(fields.len(), columns.len());
+    assert_eq!(fields[0], "1");
+    assert_eq!(fields[2], tractogram.streamlines[1].len().to_string());
+    let properties = fields[3..].iter().map(|f| f.parse().unwrap()).collect::<Vec<f32>>();
+    assert_eq!(properties, tractogram.properties[1]);
+    Ok(())
+}
+
+#[test]
+fn test_streaming_from_reader() -> Result<()> {
+    let (_, tractogram) = load_trk("data/complex.trk");
+    let reader = Reader::new("data/complex.trk")?;
+    let write_to = get_random_csv_path();
+    {
+        let mut writer = CsvWriter::new(&write_to, &reader.header, CsvLayout::Points)?;
+        for (streamline, scalars, properties) in reader {
+            writer.write(&streamline, &scalars.data, &properties)?;
+        }
+    }
+
+    let (_, csv_tractogram) = read_csv(&write_to)?;
+    assert!(csv_tractogram.streamlines == tractogram.streamlines);
+    Ok(())
+}
+
+#[test]
+fn test_read_unordered_rows() -> Result<()> {
+    let path = get_random_csv_path();
+    write(
+        &path,
+        "streamline_id,point_id,x,y,z,\"fa, mean\"\n\
+         7,1,1,0,0,0.2\n\
+         3,0,5,5,5,0.5\n\
+         7,0,0,0,0,0.1\n",
+    )?;
+
+    let (header, tractogram) = read_csv(&path)?;
+    assert_eq!(header.nb_streamlines, 2);
+    assert_eq!(header.scalars_name, vec!["fa, mean".to_string()]);
+    assert_eq!(tractogram.streamlines.offsets, vec![0, 2, 3]);
+    assert_eq!(tractogram.streamlines[0][1].x, 1.0);
+    assert_eq!(tractogram.scalars.data, vec![0.1, 0.2, 0.5]);
+    Ok(())
+}
+
+#[test]
+fn test_read_invalid_columns() {
+    let path = get_random_csv_path();
+    write(&path, "id,x,y,z\n0,1,2,3\n").unwrap();
+    assert!(read_csv(&path).is_err());
+
+    write(&path, "streamline_id,point_id,x,y,z\n0,0,1,2\n").unwrap();
+    assert!(read_csv(&path).is_err());
+}
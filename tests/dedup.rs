@@ -0,0 +1,77 @@
+use trk_io::{
+    dedup::{deduplicate, unique_indices, DedupOptions},
+    ArraySequence, Point, Streamlines, Tractogram,
+};
+
+/// A straight line from x=0 to x=4, made of `nb` points.
+fn line(y: f32, z: f32, nb: usize) -> Vec<Point> {
+    (0..nb).map(|i| Point::new(4.0 * i as f32 / (nb - 1) as f32, y, z)).collect()
+}
+
+fn get_streamlines() -> Streamlines {
+    let mut streamlines = Streamlines::empty();
+    for points in [
+        line(0.0, 0.0, 5),
+        line(0.0, 0.0, 5),                             // Exact duplicate of 0
+        line(0.1, 0.0, 5),                             // Near-duplicate of 0
+        line(0.0, 0.0, 5).into_iter().rev().collect(), // Flipped near-duplicate of 0
+        line(5.0, 0.0, 5),                             // Far away
+        line(0.0, 0.05, 9),                            // Near-duplicate of 0, more points
+        line(5.0, 0.0, 5),                             // Exact duplicate of 4
+    ] {
+        streamlines.extend(points);
+    }
+    streamlines
+}
+
+#[test]
+fn test_exact_duplicates() {
+    let streamlines = get_streamlines();
+    assert_eq!(unique_indices(&streamlines, &DedupOptions::default()).unwrap(), [0, 2, 3, 4, 5]);
+
+    // -0.0 and 0.0 are not the same bytes
+    let mut streamlines = Streamlines::empty();
+    streamlines.extend([Point::new(0.0, 0.0, 0.0)]);
+    streamlines.extend([Point::new(-0.0, 0.0, 0.0)]);
+    assert_eq!(unique_indices(&streamlines, &DedupOptions::default()).unwrap(), [0, 1]);
+}
+
+#[test]
+fn test_near_duplicates() {
+    let streamlines = get_streamlines();
+    let options = DedupOptions { mdf_threshold: Some(0.5), ..Default::default() };
+    assert_eq!(unique_indices(&streamlines, &options).unwrap(), [0, 4]);
+
+    let options = DedupOptions { mdf_threshold: Some(0.08), ..Default::default() };
+    assert_eq!(unique_indices(&streamlines, &options).unwrap(), [0, 2, 4]);
+}
+
+#[test]
+fn test_deduplicate_tractogram() {
+    let streamlines = get_streamlines();
+    let nb_points = streamlines.iter().map(|s| s.len()).collect::<Vec<_>>();
+    let scalars = ArraySequence::new(nb_points.clone(), vec![1.0; nb_points.iter().sum()]);
+    let properties = ArraySequence::new(vec![1; 7], (0..7).map(|i| i as f32).collect());
+    let tractogram = Tractogram::new(streamlines, scalars, properties);
+
+    let options = DedupOptions { mdf_threshold: Some(0.5), ..Default::default() };
+    let (unique, indices) = deduplicate(&tractogram, &options).unwrap();
+    assert_eq!(indices, [0, 4]);
+    assert_eq!(unique.streamlines.len(), 2);
+    assert_eq!(unique.streamlines[1], tractogram.streamlines[4]);
+    assert_eq!(unique.scalars.data.len(), 10);
+    assert_eq!(unique.properties.data, [0.0, 4.0]);
+}
+
+#[test]
+fn test_invalid_options() {
+    let streamlines = get_streamlines();
+    for nb_points in [0, 1] {
+        let options = DedupOptions { mdf_threshold: Some(0.5), nb_points };
+        assert!(unique_indices(&streamlines, &options).is_err());
+    }
+    for threshold in [0.0, -1.0, f32::NAN] {
+        let options = DedupOptions { mdf_threshold: Some(threshold), ..Default::default() };
+        assert!(unique_indices(&streamlines, &options).is_err());
+    }
+}
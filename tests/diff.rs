@@ -0,0 +1,127 @@
+mod test;
+
+use test::{get_random_trk_path, load_trk};
+use trk_io::{
+    diff::{diff_headers, diff_tractograms, diff_trk, DiffOptions, Mismatch},
+    Point, Writer,
+};
+
+#[test]
+fn test_identical_files() {
+    let report = diff_trk("data/complex.trk", "data/complex.trk", &DiffOptions::default()).unwrap();
+    assert!(report.is_identical());
+    assert_eq!(report.first(), None);
+
+    let report =
+        diff_trk("data/complex.trk", "data/complex_big_endian.trk", &DiffOptions::default())
+            .unwrap();
+    assert!(report.is_identical());
+}
+
+#[test]
+fn test_points_and_values() {
+    let (header, tractogram) = load_trk("data/complex.trk");
+    let mut modified = tractogram.clone();
+    let idx = modified.streamlines.offsets[2] + 1;
+    modified.streamlines.data[idx] += Point::new(0.01, 0.0, 0.0).coords;
+    modified.scalars.data[idx * 4 + 3] += 0.5;
+    modified.properties.data[4] = f32::NAN;
+
+    let path = get_random_trk_path();
+    let mut writer = Writer::new(&path, Some(&header)).unwrap();
+    writer.write(modified);
+    drop(writer);
+
+    let report = diff_trk("data/complex.trk", &path, &DiffOptions::default()).unwrap();
+    assert_eq!(report.mismatches.len(), 3);
+    assert_eq!(
+        report.first(),
+        Some(&Mismatch::Property {
+            left: 0,
+            right: 0,
+            name: String::from("mean_torsion"),
+            max_difference: f32::INFINITY
+        })
+    );
+    match &report.mismatches[1] {
+        Mismatch::Points { left: 2, right: 2, max_distance } => {
+            assert!((max_distance - 0.01).abs() < 1e-5)
+        }
+        mismatch => panic!("Unexpected mismatch {:?}", mismatch),
+    }
+    match &report.mismatches[2] {
+        Mismatch::Scalar { left: 2, right: 2, name, max_difference } => {
+            assert_eq!(name, "fa");
+            assert!((max_difference - 0.5).abs() < 1e-5);
+        }
+        mismatch => panic!("Unexpected mismatch {:?}", mismatch),
+    }
+
+    let options = DiffOptions { distance_tolerance: 0.1, value_tolerance: 1.0, ordered: true };
+    let report = diff_trk("data/complex.trk", &path, &options).unwrap();
+    assert_eq!(report.mismatches.len(), 1);
+}
+
+#[test]
+fn test_headers() {
+    let (header, _) = load_trk("data/complex.trk");
+    let left = header.raw_header();
+    let mut right = left.clone();
+    right.dim = [1, 2, 3];
+    right.voxel_size[0] += 1e-6;
+    right.origin[1] += 1.0;
+    let mismatches = diff_headers(&left, &right, 1e-4);
+    let fields = mismatches
+        .iter()
+        .map(|mismatch| match mismatch {
+            Mismatch::Header { field, .. } => *field,
+            _ => panic!("Unexpected mismatch {:?}", mismatch),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(fields, ["dim", "origin"]);
+}
+
+#[test]
+fn test_unordered() {
+    let (header, tractogram) = load_trk("data/complex.trk");
+    let reversed = tractogram.select(&[2, 1, 0]);
+    let ordered = DiffOptions::default();
+    let report = diff_tractograms(&header, &tractogram, &header, &reversed, &ordered);
+    assert_eq!(
+        report.first(),
+        Some(&Mismatch::NbPoints { left: 0, right: 0, nb_left: 1, nb_right: 5 })
+    );
+
+    let unordered = DiffOptions { ordered: false, ..DiffOptions::default() };
+    let report = diff_tractograms(&header, &tractogram, &header, &reversed, &unordered);
+    assert!(report.is_identical());
+
+    let partial = tractogram.select(&[2, 0]);
+    let report = diff_tractograms(&header, &tractogram, &header, &partial, &unordered);
+    assert_eq!(
+        report.mismatches,
+        [Mismatch::NbStreamlines { left: 3, right: 2 }, Mismatch::MissingInRight { left: 1 }]
+    );
+    let report = diff_tractograms(&header, &partial, &header, &tractogram, &unordered);
+    assert_eq!(
+        report.mismatches,
+        [Mismatch::NbStreamlines { left: 2, right: 3 }, Mismatch::MissingInLeft { right: 1 }]
+    );
+}
+
+#[test]
+fn test_different_names() {
+    let (header, tractogram) = load_trk("data/complex.trk");
+    let (mut other_header, mut other) = (header.clone(), tractogram.clone());
+    other.remove_scalar(&mut other_header, "colors").unwrap();
+    let report =
+        diff_tractograms(&header, &tractogram, &other_header, &other, &DiffOptions::default());
+    assert_eq!(
+        report.mismatches,
+        [Mismatch::Names {
+            kind: "scalars",
+            left: vec![String::from("colors[3]"), String::from("fa[1]")],
+            right: vec![String::from("fa[1]")],
+        }]
+    );
+}
@@ -1,6 +1,9 @@
+mod test;
+
 use anyhow::Result;
 
-use trk_io::Header;
+use test::{get_random_trk_path, load_trk};
+use trk_io::{ArraySequence, Header, Point, Streamlines, Tractogram, Writer};
 
 #[test]
 fn test_copy_scalars_and_properties() -> Result<()> {
@@ -26,6 +29,66 @@ fn test_copy_scalars_and_properties() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_copy_grouped_scalars_and_properties() -> Result<()> {
+    let mut header1 = Header::default();
+    header1.add_scalar("fa")?;
+    header1.add_scalar_n("color", 3)?;
+    header1.add_property_n("centroid", 3)?;
+
+    let mut header2 = Header::default();
+    header2.copy_scalars_and_properties(&header1);
+    assert_eq!(header2.scalars_name, ["fa", "color", "color", "color"]);
+    assert_eq!(header2.scalars_groups(), [("fa".to_string(), 0..1), ("color".to_string(), 1..4)]);
+    assert_eq!(header2.properties_groups(), [("centroid".to_string(), 0..3)]);
+    assert_eq!(header2.raw_header().n_scalars, 4);
+    assert_eq!(header2.raw_header().n_properties, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_write_grouped_scalars() -> Result<()> {
+    let mut header = Header::default();
+    header.add_scalar_n("color", 3)?;
+    header.add_scalar("fa")?;
+    header.add_property_n("p", 12)?;
+
+    let streamlines = Streamlines::new(vec![2], vec![Point::new(0.0, 0.0, 0.0); 2]);
+    let scalars = ArraySequence::new(vec![8], (0..8).map(|v| v as f32).collect());
+    let properties = ArraySequence::new(vec![12], vec![1.0; 12]);
+    let tractogram = Tractogram::new(streamlines, scalars, properties);
+
+    let write_to = get_random_trk_path();
+    {
+        let mut writer = Writer::new(&write_to, Some(&header))?;
+        writer.write(tractogram.clone());
+    }
+
+    let (read_header, read_tractogram) = load_trk(&write_to);
+    assert_eq!(read_header.scalars_name, ["color", "color", "color", "fa"]);
+    assert_eq!(read_header.scalars_groups(), header.scalars_groups());
+    assert_eq!(read_header.properties_name, vec!["p".to_string(); 12]);
+    assert_eq!(read_header.properties_groups(), [("p".to_string(), 0..12)]);
+    assert!(read_tractogram.scalars == tractogram.scalars);
+    assert!(read_tractogram.properties == tractogram.properties);
+
+    Ok(())
+}
+
+#[test]
+fn test_grouped_scalars_use_one_slot() -> Result<()> {
+    let mut header = Header::default();
+    for i in 0..10 {
+        header.add_scalar_n(&format!("s{}", i), 3)?;
+    }
+    assert_eq!(header.scalars_name.len(), 30);
+    assert!(header.add_scalar("s10").is_err());
+    assert!(Header::default().add_scalar_n("a_name_of_19_chars_", 3).is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_add_scalar() -> Result<()> {
     let torsion = "torsion".to_string();
@@ -93,3 +156,86 @@ fn test_unicode_property() {
     let mut header = Header::default();
     header.add_property("平仮名, ひらがな").unwrap();
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_round_trip() {
+    use trk_io::CHeader;
+
+    let c_header = Header::from_trk("data/complex.trk").unwrap().raw_header();
+    let json = c_header.to_json().unwrap();
+    assert!(json.contains("\"id_string\": \"TRACK\""));
+    assert!(json.contains("\"voxel_order\": \"RAS\""));
+    assert!(json.contains("\"colors\\u00003\""));
+
+    let back = CHeader::from_json(&json).unwrap();
+    let mut bytes = vec![];
+    c_header.write(&mut bytes).unwrap();
+    let mut back_bytes = vec![];
+    back.write(&mut back_bytes).unwrap();
+    assert_eq!(bytes, back_bytes);
+
+    // Edit the header by hand
+    let edited = CHeader::from_json(&json.replace("\"RAS\"", "\"LPS\"")).unwrap();
+    assert_eq!(&edited.voxel_order, b"LPS\0");
+    assert!(CHeader::from_json(&json.replace("\"RAS\"", "\"RASRAS\"")).is_err());
+    assert!(CHeader::from_json(&json.replace("\"TRACK\"", "\"TRAC\"")).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_reserved_bytes() {
+    use trk_io::CHeader;
+
+    let mut c_header = Header::from_trk("data/complex.trk").unwrap().raw_header();
+    c_header.reserved[..4].copy_from_slice(&[0xFF, 0x80, 0, 0xE9]);
+    let json = c_header.to_json().unwrap();
+    assert!(json.contains("\"reserved\": \"/4AA6Q==\""));
+
+    let back = CHeader::from_json(&json).unwrap();
+    assert_eq!(back.reserved, c_header.reserved);
+    assert!(CHeader::from_json(&json.replace("/4AA6Q==", "not base64")).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_invalid_fields() {
+    use trk_io::CHeader;
+
+    let json = Header::from_trk("data/complex.trk").unwrap().raw_header().to_json().unwrap();
+    let header_json =
+        serde_json::to_string(&Header::from_trk("data/complex.trk").unwrap()).unwrap();
+    for (from, to) in [
+        ("\"RAS\"", "\"RASL\""),
+        ("\"RAS\"", "\"RRS\""),
+        ("\"RAS\"", "\"ras\""),
+        ("\"dim\":[", "\"dim\":[0,"),
+        ("\"voxel_size\":[", "\"voxel_size\":[-1.0,"),
+        ("\"n_scalars\":4", "\"n_scalars\":-4"),
+        ("\"n_properties\":5", "\"n_properties\":2"),
+    ] {
+        let pretty_from = from.replace(':', ": ");
+        let pretty_to = to.replace(':', ": ");
+        let edited = json.replace(&pretty_from, &pretty_to);
+        assert!(edited != json, "{:?} not found", pretty_from);
+        assert!(CHeader::from_json(&edited).is_err(), "{:?} should be rejected", to);
+
+        let edited = header_json.replace(from, to);
+        assert!(edited != header_json, "{:?} not found", from);
+        assert!(serde_json::from_str::<Header>(&edited).is_err(), "{:?} should be rejected", to);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_header_serde() {
+    let mut header = Header::from_trk("data/complex.trk").unwrap();
+    header.nb_streamlines = 42;
+    let json = serde_json::to_string(&header).unwrap();
+    assert!(json.contains("\"n_count\":42"));
+
+    let back: Header = serde_json::from_str(&json).unwrap();
+    assert!(back == header);
+    assert_eq!(back.scalars_groups(), header.scalars_groups());
+    assert_eq!(back.affine4_to_rasmm, header.affine4_to_rasmm);
+}
@@ -0,0 +1,102 @@
+mod test;
+
+use std::fs;
+
+use test::{get_random_trk_path, load_trk};
+use trk_io::{
+    header_edit::{edit_trk, edit_trk_in_place, EditMode},
+    Affine4, CHeader, Header, Reader, Streamlines, Translation,
+};
+
+fn shift_origin(header: &mut CHeader) {
+    let mut affine = header.get_voxel_to_rasmm();
+    affine[(0, 3)] += 10.0;
+    header.set_voxel_to_rasmm(&affine);
+}
+
+fn raw_streamlines(path: &str) -> Streamlines {
+    let mut reader = Reader::new(path).unwrap().raw();
+    reader.tractogram().streamlines
+}
+
+#[test]
+fn test_reinterpret() {
+    let output = get_random_trk_path();
+    let header =
+        edit_trk("data/complex.trk", &output, EditMode::Reinterpret, shift_origin).unwrap();
+    assert_eq!(header.nb_streamlines, 3);
+    assert_eq!(header.translation, Translation::new(9.5, -0.5, -0.5));
+    assert!(raw_streamlines("data/complex.trk") == raw_streamlines(&output));
+
+    let (_, original) = load_trk("data/complex.trk");
+    let (new_header, moved) = load_trk(&output);
+    assert_eq!(new_header.scalars_name, header.scalars_name);
+    for (p, q) in original.streamlines.data.iter().zip(&moved.streamlines.data) {
+        assert_eq!(q.x, p.x + 10.0);
+        assert_eq!((q.y, q.z), (p.y, p.z));
+    }
+    assert!(original.scalars == moved.scalars);
+    assert!(original.properties == moved.properties);
+}
+
+#[test]
+fn test_preserve_world() {
+    let output = get_random_trk_path();
+    edit_trk("data/complex.trk", &output, EditMode::PreserveWorld, shift_origin).unwrap();
+    let (_, original) = load_trk("data/complex.trk");
+    let (_, same) = load_trk(&output);
+    for (p, q) in original.streamlines.data.iter().zip(&same.streamlines.data) {
+        assert!((p - q).norm() < 1e-5);
+    }
+
+    let (original, moved) = (raw_streamlines("data/complex.trk"), raw_streamlines(&output));
+    for (p, q) in original.data.iter().zip(&moved.data) {
+        assert!((q.x - (p.x - 10.0)).abs() < 1e-5);
+    }
+
+    let singular = |header: &mut CHeader| header.set_voxel_to_rasmm(&Affine4::zeros());
+    assert!(edit_trk("data/complex.trk", &output, EditMode::PreserveWorld, singular).is_err());
+}
+
+#[test]
+fn test_in_place() {
+    let path = get_random_trk_path();
+    fs::copy("data/complex.trk", &path).unwrap();
+    let size = fs::metadata(&path).unwrap().len();
+
+    let c_header = edit_trk_in_place(&path, |header| {
+        header.voxel_order = *b"LAS\0";
+        header.dim = [2, 3, 4];
+    })
+    .unwrap();
+    assert_eq!(&c_header.voxel_order, b"LAS\0");
+    assert_eq!(fs::metadata(&path).unwrap().len(), size);
+
+    let header = Header::from_trk(&path).unwrap();
+    assert_eq!(header.raw_header().dim, [2, 3, 4]);
+    assert_eq!(&header.raw_header().voxel_order, b"LAS\0");
+    assert!(raw_streamlines("data/complex.trk") == raw_streamlines(&path));
+    let (_, original) = load_trk("data/complex.trk");
+    let (_, edited) = load_trk(&path);
+    assert!(original.scalars == edited.scalars);
+}
+
+#[test]
+fn test_invalid_edits() {
+    let path = get_random_trk_path();
+    fs::copy("data/complex.trk", &path).unwrap();
+    assert!(edit_trk_in_place(&path, |header| header.n_scalars = 0).is_err());
+    assert!(edit_trk_in_place(&path, |header| header.hdr_size = 0).is_err());
+    assert!(edit_trk_in_place(&path, |header| header.version = 3).is_err());
+    assert!(edit_trk_in_place(&path, |header| header.id_string = *b"TRACC\0").is_err());
+    assert!(edit_trk_in_place(&path, |header| header.voxel_order = *b"RASL").is_err());
+    assert!(edit_trk_in_place(&path, |header| header.dim = [0, 1, 1]).is_err());
+    assert!(edit_trk_in_place(&path, |header| header.voxel_size = [1.0, 0.0, 1.0]).is_err());
+    let same = |header: &mut CHeader| header.dim = [1, 2, 3];
+    assert!(edit_trk(&path, &path, EditMode::Reinterpret, same).is_err());
+    assert!(fs::read(&path).unwrap() == fs::read("data/complex.trk").unwrap());
+
+    let path = get_random_trk_path();
+    fs::copy("data/complex_big_endian.trk", &path).unwrap();
+    assert!(edit_trk_in_place(&path, |header| header.dim = [1, 1, 1]).is_err());
+}
@@ -0,0 +1,113 @@
+mod test;
+
+use anyhow::Result;
+
+use test::{get_random_trk_path, load_trk};
+use trk_io::{
+    merge::{check_compatible, merge_trk, MergeOptions, MergePolicy},
+    ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+/// A tractogram of `nb` streamlines of 2 points, with a scalar and a property for each `names`.
+fn get_tractogram(nb: usize, names: &[&str]) -> (Header, Tractogram) {
+    let mut header = Header::default();
+    let points = (0..2 * nb).map(|i| Point::new(i as f32, 0.0, 0.0)).collect();
+    let mut tractogram = Tractogram::new(
+        Streamlines::new(vec![2; nb], points),
+        ArraySequence::empty(),
+        ArraySequence::empty(),
+    );
+    for (i, name) in names.iter().enumerate() {
+        let scalars = ArraySequence::new(vec![2; nb], vec![i as f32; 2 * nb]);
+        tractogram.add_scalar(&mut header, name, scalars).unwrap();
+        let properties = ArraySequence::new(vec![1; nb], vec![10.0 + i as f32; nb]);
+        tractogram.add_property(&mut header, name, properties).unwrap();
+    }
+    header.nb_streamlines = nb;
+    (header, tractogram)
+}
+
+#[test]
+fn test_concat() -> Result<()> {
+    let (h1, t1) = get_tractogram(2, &["a", "b"]);
+    let (h2, t2) = get_tractogram(1, &["b", "a"]);
+    let options = MergeOptions { source_property: Some("source"), ..Default::default() };
+    let (header, tractogram) = Tractogram::concat(&[(&h1, &t1), (&h2, &t2)], &options)?;
+
+    assert_eq!(header.nb_streamlines, 3);
+    assert_eq!(header.scalars_name, ["a", "b"]);
+    assert_eq!(header.properties_name, ["a", "b", "source"]);
+    assert_eq!(tractogram.streamlines.len(), 3);
+    assert_eq!(tractogram.streamlines[2], t2.streamlines[0]);
+    // The scalars of the second tractogram are reordered
+    assert_eq!(tractogram.scalars[2], [1.0, 0.0, 1.0, 0.0]);
+    assert_eq!(tractogram.scalars[0], [0.0, 1.0, 0.0, 1.0]);
+    assert_eq!(tractogram.properties.data, [10.0, 11.0, 0.0, 10.0, 11.0, 0.0, 11.0, 10.0, 1.0]);
+    Ok(())
+}
+
+#[test]
+fn test_concat_policies() -> Result<()> {
+    let (h1, t1) = get_tractogram(1, &["a", "b"]);
+    let (h2, t2) = get_tractogram(1, &["a"]);
+    let parts = [(&h1, &t1), (&h2, &t2)];
+    assert!(Tractogram::concat(&parts, &MergeOptions::default()).is_err());
+
+    let options = MergeOptions { policy: MergePolicy::Intersect, ..Default::default() };
+    let (header, tractogram) = Tractogram::concat(&parts, &options)?;
+    assert_eq!(header.scalars_name, ["a"]);
+    assert_eq!(tractogram.scalars.data, [0.0; 4]);
+    assert_eq!(tractogram.properties.data, [10.0, 10.0]);
+
+    let options = MergeOptions { policy: MergePolicy::Fill, ..Default::default() };
+    let (header, tractogram) = Tractogram::concat(&parts, &options)?;
+    assert_eq!(header.properties_name, ["a", "b"]);
+    assert_eq!(tractogram.properties[0], [10.0, 11.0]);
+    assert_eq!(tractogram.properties[1][0], 10.0);
+    assert!(tractogram.properties[1][1].is_nan());
+    assert!(tractogram.scalars[1][1].is_nan() && tractogram.scalars[1][3].is_nan());
+
+    // Without scalars nor properties
+    let (h3, t3) = get_tractogram(2, &[]);
+    let (header, tractogram) = Tractogram::concat(&[(&h3, &t3), (&h3, &t3)], &options)?;
+    assert!(header.scalars_name.is_empty());
+    assert_eq!(tractogram.streamlines.len(), 4);
+    assert!(tractogram.scalars.is_empty() && tractogram.properties.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_incompatible_headers() {
+    let h1 = Header::from_trk("data/standard.trk").unwrap();
+    let h2 = Header::from_trk("data/standard.LPS.trk").unwrap();
+    let h3 = Header::from_trk("data/simple.trk").unwrap();
+    assert!(check_compatible(&h1, &h1, 1e-4).is_ok());
+    assert!(check_compatible(&h1, &h2, 1e-4).is_err());
+    assert!(check_compatible(&h1, &h3, 1e-4).is_err());
+
+    let (_, tractogram) = load_trk("data/standard.trk");
+    let parts = [(&h1, &tractogram), (&h3, &tractogram)];
+    assert!(Tractogram::concat(&parts, &MergeOptions::default()).is_err());
+}
+
+#[test]
+fn test_merge_trk() -> Result<()> {
+    let write_to = get_random_trk_path();
+    let inputs = ["data/complex.trk", "data/complex.trk", "data/complex.trk"];
+    let options = MergeOptions { source_property: Some("source"), ..Default::default() };
+    let header = merge_trk(&inputs, &write_to, &options)?;
+    assert_eq!(header.nb_streamlines, 9);
+
+    let (original_header, original) = load_trk("data/complex.trk");
+    let (merged_header, merged) = load_trk(&write_to);
+    assert_eq!(merged_header.nb_streamlines, 9);
+    assert_eq!(merged_header.raw_header().n_count, 9);
+    assert_eq!(merged_header.scalars_groups(), original_header.scalars_groups());
+    assert_eq!(merged_header.properties_name.last().unwrap(), "source");
+    assert_eq!(merged.streamlines.len(), 9);
+    assert_eq!(merged.streamlines[7], original.streamlines[1]);
+    assert_eq!(merged.scalars[7], original.scalars[1]);
+    let source = merged.property(&merged_header, "source")?.to_array_sequence();
+    assert_eq!(source.data, [0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+    Ok(())
+}
@@ -0,0 +1,180 @@
+mod test;
+
+use std::{collections::HashMap, fs::read};
+
+use anyhow::Result;
+
+use test::load_trk;
+use trk_io::{
+    mesh::{write_mesh, Geometry, MeshFormat, MeshOptions},
+    ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+fn get_random_mesh_path(extension: &str) -> String {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.keep().join(format!("out.{}", extension));
+    path.to_str().unwrap().to_string()
+}
+
+/// A straight and a bent streamline, colored in red and blue.
+fn get_colored_tractogram() -> Result<(Header, Tractogram)> {
+    let mut header = Header::default();
+    header.add_scalar("color_x")?;
+    header.add_scalar("color_y")?;
+    header.add_scalar("color_z")?;
+    let streamlines = Streamlines::new(
+        vec![3, 4],
+        vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(2.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(1.0, 2.0, 0.0),
+            Point::new(1.0, 2.0, 1.0),
+        ],
+    );
+    let mut colors = [255.0, 0.0, 0.0].repeat(3);
+    colors.extend([0.0, 0.0, 255.0].repeat(4));
+    let scalars = ArraySequence::new(vec![9, 12], colors);
+    Ok((header, Tractogram::new(streamlines, scalars, ArraySequence::empty())))
+}
+
+#[test]
+fn test_obj_polylines() -> Result<()> {
+    let (header, tractogram) = get_colored_tractogram()?;
+    let path = get_random_mesh_path("obj");
+    let options =
+        MeshOptions { colors: Some(["color_x", "color_y", "color_z"]), ..Default::default() };
+    write_mesh(&path, &header, &tractogram, MeshFormat::Obj, &options)?;
+
+    let text = String::from_utf8(read(&path)?)?;
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.iter().filter(|l| l.starts_with("v ")).count(), 7);
+    assert!(lines.contains(&"v 0 0 0 1 0 0"));
+    assert!(lines.contains(&"v 1 2 1 0 0 1"));
+    assert!(lines.contains(&"l 1 2 3"));
+    assert!(lines.contains(&"l 4 5 6 7"));
+    Ok(())
+}
+
+#[test]
+fn test_polylines_skip_degenerate_streamlines() -> Result<()> {
+    let streamlines = Streamlines::new(
+        vec![1, 0, 2],
+        vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0)],
+    );
+    let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+    let path = get_random_mesh_path("obj");
+    write_mesh(&path, &Header::default(), &tractogram, MeshFormat::Obj, &MeshOptions::default())?;
+    let text = String::from_utf8(read(&path)?)?;
+    let lines = text.lines().filter(|l| l.starts_with('l')).collect::<Vec<_>>();
+    assert_eq!(lines, ["l 2 3"]);
+
+    let path = get_random_mesh_path("ply");
+    write_mesh(&path, &Header::default(), &tractogram, MeshFormat::Ply, &MeshOptions::default())?;
+    let bytes = read(&path)?;
+    assert!(String::from_utf8_lossy(&bytes).contains("element edge 1\n"));
+    // The only edge joins the 2 points of the last streamline
+    assert!(bytes.ends_with(&[1, 0, 0, 0, 2, 0, 0, 0]));
+    Ok(())
+}
+
+#[test]
+fn test_obj_tubes_are_closed() -> Result<()> {
+    let (header, tractogram) = get_colored_tractogram()?;
+    let path = get_random_mesh_path("obj");
+    let options =
+        MeshOptions { geometry: Geometry::Tube { radius: 0.2, nb_sides: 6 }, colors: None };
+    write_mesh(&path, &header, &tractogram, MeshFormat::Obj, &options)?;
+
+    let text = String::from_utf8(read(&path)?)?;
+    // 6 vertices per point + 2 caps per streamline
+    assert_eq!(text.lines().filter(|l| l.starts_with("v ")).count(), 6 * 7 + 4);
+    let faces = text
+        .lines()
+        .filter_map(|l| l.strip_prefix("f "))
+        .map(|l| l.split(' ').map(|v| v.parse::<usize>().unwrap()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    // 2 triangles per side per segment + 1 triangle per side per cap
+    assert_eq!(faces.len(), 2 * 6 * (2 + 3) + 2 * 2 * 6);
+
+    // In a closed and consistently oriented mesh, each directed edge is used exactly once and its
+    // reverse is also used exactly once.
+    let mut edges = HashMap::new();
+    for face in &faces {
+        for i in 0..3 {
+            *edges.entry((face[i], face[(i + 1) % 3])).or_insert(0) += 1;
+        }
+    }
+    for (&(a, b), &count) in &edges {
+        assert_eq!(count, 1);
+        assert_eq!(edges.get(&(b, a)), Some(&1));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_ply() -> Result<()> {
+    let (header, tractogram) = get_colored_tractogram()?;
+    let path = get_random_mesh_path("ply");
+    let options =
+        MeshOptions { colors: Some(["color_x", "color_y", "color_z"]), ..Default::default() };
+    write_mesh(&path, &header, &tractogram, MeshFormat::Ply, &options)?;
+
+    let bytes = read(&path)?;
+    let end = b"end_header\n";
+    let header_end = bytes.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+    let ply_header = String::from_utf8(bytes[..header_end].to_vec())?;
+    assert!(ply_header.contains("element vertex 7\n"));
+    assert!(ply_header.contains("property uchar red\n"));
+    assert!(ply_header.contains("element edge 5\n"));
+    // 7 vertices of 3 floats and 3 colors, 5 edges of 2 ints
+    assert_eq!(bytes.len() - header_end, 7 * 15 + 5 * 8);
+
+    let path = get_random_mesh_path("ply");
+    let options =
+        MeshOptions { geometry: Geometry::Tube { radius: 0.5, nb_sides: 4 }, colors: None };
+    write_mesh(&path, &header, &tractogram, MeshFormat::Ply, &options)?;
+    let text = String::from_utf8_lossy(&read(&path)?).to_string();
+    assert!(text.contains("element face 56\n"));
+    assert!(!text.contains("property uchar red"));
+    Ok(())
+}
+
+#[test]
+fn test_stl() -> Result<()> {
+    let (header, tractogram) = load_trk("data/standard.trk");
+    let path = get_random_mesh_path("stl");
+    let options =
+        MeshOptions { geometry: Geometry::Tube { radius: 0.5, nb_sides: 8 }, colors: None };
+    write_mesh(&path, &header, &tractogram, MeshFormat::Stl, &options)?;
+
+    let bytes = read(&path)?;
+    let nb_triangles = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let expected = tractogram
+        .streamlines
+        .iter()
+        .filter(|s| s.len() >= 2)
+        .map(|s| 2 * 8 * (s.len() - 1) + 2 * 8)
+        .sum::<usize>();
+    assert_eq!(nb_triangles, expected);
+    assert_eq!(bytes.len(), 84 + 50 * nb_triangles);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_options() -> Result<()> {
+    let (header, tractogram) = get_colored_tractogram()?;
+    let path = get_random_mesh_path("stl");
+    let polylines = MeshOptions::default();
+    assert!(write_mesh(&path, &header, &tractogram, MeshFormat::Stl, &polylines).is_err());
+
+    let bad_tube =
+        MeshOptions { geometry: Geometry::Tube { radius: 1.0, nb_sides: 2 }, colors: None };
+    assert!(write_mesh(&path, &header, &tractogram, MeshFormat::Obj, &bad_tube).is_err());
+
+    let bad_colors = MeshOptions { colors: Some(["r", "g", "b"]), ..Default::default() };
+    assert!(write_mesh(&path, &header, &tractogram, MeshFormat::Ply, &bad_colors).is_err());
+    Ok(())
+}
@@ -0,0 +1,135 @@
+mod test;
+
+use anyhow::Result;
+
+use test::get_random_trk_path;
+use trk_io::{
+    profile::{gaussian_weights, tract_profile, ScalarSource},
+    ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+/// 3 parallel streamlines going from x=0 to x=4, the last one being stored in reverse order.
+fn get_toy_bundle() -> (Header, Tractogram) {
+    let mut header = Header::default();
+    header.add_scalar("fa").unwrap();
+    header.add_scalar("md").unwrap();
+
+    let mut streamlines = Streamlines::empty();
+    let mut scalars = ArraySequence::empty();
+    for (y, reversed) in [(-1.0, false), (0.0, false), (1.0, true)] {
+        let mut xs = [0.0, 1.0, 2.0, 3.0, 4.0];
+        if reversed {
+            xs.reverse();
+        }
+        streamlines.extend(xs.iter().map(|&x| Point::new(x, y, 0.0)));
+        scalars.extend(xs.iter().flat_map(|&x| [x / 10.0, 1.0]));
+    }
+    (header, Tractogram::new(streamlines, scalars, ArraySequence::empty()))
+}
+
+#[test]
+fn test_profile_by_name() -> Result<()> {
+    let (header, tractogram) = get_toy_bundle();
+    let profile = tract_profile(&tractogram, &header, ScalarSource::Name("fa"), 3)?;
+    assert_eq!(profile.len(), 3);
+    for (mean, expected) in profile.mean.iter().zip([0.0, 0.2, 0.4]) {
+        assert!((mean - expected).abs() < 1e-6);
+    }
+    assert!(profile.std.iter().all(|&std| std < 1e-6));
+
+    let profile = tract_profile(&tractogram, &header, ScalarSource::Name("md"), 10)?;
+    assert!(profile.mean.iter().all(|&mean| (mean - 1.0).abs() < 1e-6));
+    Ok(())
+}
+
+#[test]
+fn test_profile_unknown_name() {
+    let (header, tractogram) = get_toy_bundle();
+    assert!(tract_profile(&tractogram, &header, ScalarSource::Name("rd"), 3).is_err());
+}
+
+#[test]
+fn test_profile_too_few_nodes() {
+    let (header, tractogram) = get_toy_bundle();
+    assert!(tract_profile(&tractogram, &header, ScalarSource::Name("fa"), 1).is_err());
+    assert!(tract_profile(&tractogram, &header, ScalarSource::Name("fa"), 0).is_err());
+}
+
+#[test]
+fn test_profile_empty_streamlines() -> Result<()> {
+    let (header, tractogram) = get_toy_bundle();
+    let with_empty = Tractogram::new(
+        Streamlines::new(vec![5, 0, 5, 5], tractogram.streamlines.data.clone()),
+        ArraySequence::new(vec![10, 0, 10, 10], tractogram.scalars.data.clone()),
+        ArraySequence::empty(),
+    );
+    let expected = tract_profile(&tractogram, &header, ScalarSource::Name("fa"), 3)?;
+    let profile = tract_profile(&with_empty, &header, ScalarSource::Name("fa"), 3)?;
+    assert_eq!(profile, expected);
+
+    let only_empty = Tractogram::new(
+        Streamlines::new(vec![0, 0], vec![]),
+        ArraySequence::empty(),
+        ArraySequence::empty(),
+    );
+    assert!(tract_profile(&only_empty, &header, ScalarSource::Name("fa"), 3).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_gaussian_weights() {
+    let streamlines = Streamlines::new(
+        vec![2, 2, 2, 2],
+        vec![
+            Point::new(0.0, -1.0, 0.0),
+            Point::new(1.0, -1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, -1.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(1.0, 0.0, 1.0),
+        ],
+    );
+    let weights = gaussian_weights(&streamlines);
+    assert_eq!(weights.len(), 4);
+    for node in 0..2 {
+        let sum = weights.iter().map(|w| w[node]).sum::<f32>();
+        assert!((sum - 1.0).abs() < 1e-6);
+        assert!(weights.iter().all(|w| (w[node] - 0.25).abs() < 1e-6));
+    }
+}
+
+#[test]
+fn test_profile_write_csv() -> Result<()> {
+    let (header, tractogram) = get_toy_bundle();
+    let profile = tract_profile(&tractogram, &header, ScalarSource::Name("fa"), 2)?;
+
+    let write_to = get_random_trk_path().replace(".trk", ".csv");
+    profile.write_csv(&write_to)?;
+    let csv = std::fs::read_to_string(&write_to)?;
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "node,mean,std");
+    assert!(lines[1].starts_with("0,"));
+    Ok(())
+}
+
+#[cfg(feature = "nifti_images")]
+#[test]
+fn test_profile_by_volume() -> Result<()> {
+    use ndarray::Array3;
+    use trk_io::Affine4;
+
+    let (header, tractogram) = get_toy_bundle();
+    let data = Array3::from_shape_fn((5, 3, 3), |(x, _, _)| x as f32 * 2.0);
+    let mut affine = Affine4::identity();
+    affine[(1, 3)] = -1.0;
+
+    let source = ScalarSource::Volume { data: &data, affine: &affine };
+    let profile = tract_profile(&tractogram, &header, source, 5)?;
+    for (mean, expected) in profile.mean.iter().zip([0.0, 2.0, 4.0, 6.0, 8.0]) {
+        assert!((mean - expected).abs() < 1e-5);
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+mod test;
+
+use anyhow::Result;
+
+use test::{get_random_trk_path, load_trk};
+use trk_io::{
+    smoothing::{Smoother, Smoothing},
+    ArraySequence, Point, Reader, Streamlines, Tractogram,
+};
+
+fn get_zigzag() -> Vec<Point> {
+    (0..20).map(|i| Point::new(i as f32, if i % 2 == 0 { 0.5 } else { -0.5 }, 0.0)).collect()
+}
+
+fn max_abs_y(streamline: &[Point]) -> f32 {
+    streamline[2..streamline.len() - 2].iter().map(|p| p.y.abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn test_straight_line_is_unchanged() {
+    let line = (0..10).map(|i| Point::new(i as f32, 2.0, 1.0)).collect::<Vec<_>>();
+    for method in [
+        Smoothing::MovingAverage { radius: 2 },
+        Smoothing::Gaussian { sigma: 1.5 },
+        Smoothing::BSpline { smoothing: 10.0, nb_points: None },
+    ] {
+        let (smoothed, _) = Smoother::new(method).unwrap().smooth(&line, &[]);
+        assert_eq!(smoothed.len(), line.len());
+        for (p1, p2) in smoothed.iter().zip(&line) {
+            assert!((p1.y - p2.y).abs() < 1e-4 && (p1.z - p2.z).abs() < 1e-4);
+        }
+    }
+}
+
+#[test]
+fn test_smoothing_reduces_noise() {
+    let zigzag = get_zigzag();
+    for method in [
+        Smoothing::MovingAverage { radius: 1 },
+        Smoothing::Gaussian { sigma: 2.0 },
+        Smoothing::BSpline { smoothing: 1.0, nb_points: None },
+    ] {
+        let (smoothed, _) = Smoother::new(method).unwrap().smooth(&zigzag, &[]);
+        assert!(max_abs_y(&smoothed) < 0.25, "{:?}", method);
+    }
+}
+
+#[test]
+fn test_keep_endpoints() {
+    let zigzag = get_zigzag();
+    let smoother = Smoother::new(Smoothing::MovingAverage { radius: 3 }).unwrap();
+    let (smoothed, _) = smoother.smooth(&zigzag, &[]);
+    assert_ne!(smoothed[0], zigzag[0]);
+
+    let (smoothed, _) = smoother.keep_endpoints(true).smooth(&zigzag, &[]);
+    assert_eq!(smoothed[0], zigzag[0]);
+    assert_eq!(smoothed[19], zigzag[19]);
+}
+
+#[test]
+fn test_bspline_resample_scalars() {
+    let line = (0..5).map(|i| Point::new(i as f32, 0.0, 0.0)).collect::<Vec<_>>();
+    let scalars = (0..5).flat_map(|i| [i as f32, 1.0]).collect::<Vec<_>>();
+    let smoother =
+        Smoother::new(Smoothing::BSpline { smoothing: 0.0, nb_points: Some(9) }).unwrap();
+    let (smoothed, scalars) = smoother.smooth(&line, &scalars);
+    assert_eq!(smoothed.len(), 9);
+    assert_eq!(scalars.len(), 18);
+    assert_eq!(scalars[2..4], [0.5, 1.0]);
+}
+
+#[test]
+fn test_smooth_tractogram() {
+    let (_, tractogram) = load_trk("data/complex.trk");
+    let smoother = Smoother::new(Smoothing::Gaussian { sigma: 1.0 }).unwrap();
+    let smoothed = smoother.smooth_tractogram(&tractogram);
+    assert_eq!(smoothed.streamlines.offsets, tractogram.streamlines.offsets);
+    assert!(smoothed.scalars == tractogram.scalars);
+    assert!(smoothed.properties == tractogram.properties);
+
+    let empty =
+        Tractogram::new(Streamlines::empty(), ArraySequence::empty(), ArraySequence::empty());
+    assert_eq!(smoother.smooth_tractogram(&empty).streamlines.len(), 0);
+}
+
+#[test]
+fn test_smooth_tractogram_keeps_empty_streamlines() {
+    let points = get_zigzag();
+    let streamlines = Streamlines::new(vec![10, 0, 10], points);
+    let scalars = ArraySequence::new(vec![10, 0, 10], (0..20).map(|i| i as f32).collect());
+    let properties = ArraySequence::new(vec![1, 1, 1], vec![1.0, 2.0, 3.0]);
+    let tractogram = Tractogram::new(streamlines, scalars, properties);
+
+    let smoother =
+        Smoother::new(Smoothing::BSpline { smoothing: 1.0, nb_points: Some(5) }).unwrap();
+    let smoothed = smoother.smooth_tractogram(&tractogram);
+    assert_eq!(smoothed.streamlines.offsets, [0, 5, 5, 10]);
+    assert_eq!(smoothed.scalars.offsets, [0, 5, 5, 10]);
+    assert!(smoothed.properties == tractogram.properties);
+}
+
+#[test]
+fn test_bspline_too_few_points() {
+    for nb_points in [0, 1] {
+        let method = Smoothing::BSpline { smoothing: 1.0, nb_points: Some(nb_points) };
+        assert!(Smoother::new(method).is_err());
+    }
+}
+
+#[test]
+fn test_smooth_streaming() -> Result<()> {
+    let write_to = get_random_trk_path();
+    let reader = Reader::new("data/complex.trk")?;
+    {
+        let mut writer = reader.build_writer(&write_to)?;
+        let smoother = Smoother::new(Smoothing::MovingAverage { radius: 1 })?;
+        for item in reader {
+            writer.write(smoother.smooth_item(item));
+        }
+    }
+
+    let (original_header, original) = load_trk("data/complex.trk");
+    let (header, tractogram) = load_trk(&write_to);
+    assert!(header == original_header);
+    assert_eq!(tractogram.streamlines.offsets, original.streamlines.offsets);
+    assert!(tractogram.scalars == original.scalars);
+    assert!(tractogram.properties == original.properties);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+mod test;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use test::{get_random_trk_path, load_trk};
+use trk_io::{
+    split::{reassemble_trk, split_by_property, split_into_chunks, Balance},
+    ArraySequence, Header, Point, Streamlines, Tractogram, Writer,
+};
+
+/// Write a trk file of 10 streamlines of 1 to 10 points, with a `cluster` property: 0, 1, 2, 0, ...
+fn write_clusters() -> (String, Header, Tractogram) {
+    let lengths = (1..=10).collect::<Vec<_>>();
+    let nb_points = lengths.iter().sum::<usize>();
+    let points = (0..nb_points).map(|i| Point::new(i as f32, 1.0, 2.0)).collect();
+    let mut header = Header::from_trk("data/standard.trk").unwrap();
+    header.clear_scalars_and_properties();
+    let mut tractogram = Tractogram::new(
+        Streamlines::new(lengths, points),
+        ArraySequence::empty(),
+        ArraySequence::empty(),
+    );
+    let clusters = (0..10).map(|i| (i % 3) as f32).collect();
+    tractogram
+        .add_property(&mut header, "cluster", ArraySequence::new(vec![1; 10], clusters))
+        .unwrap();
+
+    let path = get_random_trk_path();
+    let mut writer = Writer::new(&path, Some(&header)).unwrap();
+    writer.write(tractogram.clone());
+    (path, header, tractogram)
+}
+
+#[test]
+fn test_split_into_chunks() -> Result<()> {
+    let (path, _, _) = write_clusters();
+    let (_, original) = load_trk(&path);
+    let dir = tempfile::TempDir::new()?.keep();
+
+    let chunk_path = |i: usize| dir.join(format!("chunk_{}.trk", i));
+    let paths = split_into_chunks(&path, 3, Balance::Streamlines, chunk_path)?;
+    let lengths = paths.iter().map(|p| load_trk(p.to_str().unwrap()).1.streamlines.len());
+    assert_eq!(lengths.collect::<Vec<_>>(), [3, 4, 3]);
+
+    let chunk_path = |i: usize| dir.join(format!("points_{}.trk", i));
+    let paths = split_into_chunks(&path, 3, Balance::Points, chunk_path)?;
+    let chunks = paths.iter().map(|p| load_trk(p.to_str().unwrap()).1).collect::<Vec<_>>();
+    // 55 points: 1+...+6 = 21, 7+8 = 15, 9+10 = 19
+    let nb_points = chunks.iter().map(|c| c.streamlines.data.len()).collect::<Vec<_>>();
+    assert_eq!(nb_points, [21, 15, 19]);
+
+    let write_to = get_random_trk_path();
+    let header = reassemble_trk(&paths, &write_to, None)?;
+    assert_eq!(header.nb_streamlines, 10);
+    let (_, reassembled) = load_trk(&write_to);
+    assert!(reassembled == original);
+    Ok(())
+}
+
+#[test]
+fn test_split_by_property() -> Result<()> {
+    let (path, header, _) = write_clusters();
+    let (_, original) = load_trk(&path);
+    let dir = tempfile::TempDir::new()?.keep();
+
+    let cluster_path = |value: f32| dir.join(format!("cluster_{}.trk", value));
+    let files = split_by_property(&path, "cluster", Some("index"), cluster_path)?;
+    assert_eq!(files.iter().map(|(value, _)| *value).collect::<Vec<_>>(), [0.0, 1.0, 2.0]);
+    let (cluster_header, cluster) = load_trk(files[1].1.to_str().unwrap());
+    assert_eq!(cluster_header.properties_name, ["cluster", "index"]);
+    assert_eq!(cluster.properties.data, [1.0, 1.0, 1.0, 4.0, 1.0, 7.0]);
+    assert_eq!(cluster.streamlines[1], original.streamlines[4]);
+
+    let paths = files.into_iter().map(|(_, path)| path).collect::<Vec<PathBuf>>();
+    let write_to = get_random_trk_path();
+    let reassembled_header = reassemble_trk(&paths, &write_to, Some("index"))?;
+    assert_eq!(reassembled_header.properties_name, header.properties_name);
+    let (_, reassembled) = load_trk(&write_to);
+    assert!(reassembled == original);
+
+    assert!(split_by_property(&path, "weight", None, |_| dir.join("bad.trk")).is_err());
+    Ok(())
+}
@@ -0,0 +1,119 @@
+mod test;
+
+use anyhow::Result;
+
+use test::{get_random_trk_path, load_trk};
+use trk_io::{ArraySequence, Origin, Reader, Space, Spacing, StatefulTractogram};
+
+fn assert_points_eq(a: &StatefulTractogram, b: &StatefulTractogram) {
+    let a = &a.tractogram().streamlines.data;
+    let b = &b.tractogram().streamlines.data;
+    assert_eq!(a.len(), b.len());
+    for (p1, p2) in a.iter().zip(b) {
+        assert!((p1 - p2).norm() < 1e-4, "{:?} != {:?}", p1, p2);
+    }
+}
+
+#[test]
+fn test_load() -> Result<()> {
+    let sft = StatefulTractogram::load("data/complex.trk")?;
+    let (header, tractogram) = load_trk("data/complex.trk");
+    assert_eq!(sft.space(), Space::RasMm);
+    assert_eq!(sft.origin(), Origin::Center);
+    assert!(*sft.header() == header);
+    assert!(*sft.tractogram() == tractogram);
+    Ok(())
+}
+
+#[test]
+fn test_vox_corner_is_trackvis_voxel_space() -> Result<()> {
+    // The voxel order of standard.trk matches its vox_to_ras, thus the voxel space of Reader is
+    // the same as Space::Vox with Origin::Corner.
+    let mut sft = StatefulTractogram::load("data/standard.trk")?;
+    sft.to_vox();
+    sft.to_corner();
+
+    let reader = Reader::new("data/standard.trk")?;
+    let spacing = Spacing::from_iterator(reader.header.raw_header().voxel_size.iter().cloned());
+    let mut reader = reader.to_voxel_space(spacing);
+    let streamlines = reader.streamlines();
+    for (p1, p2) in sft.tractogram().streamlines.data.iter().zip(&streamlines.data) {
+        assert!((p1 - p2).norm() < 1e-4, "{:?} != {:?}", p1, p2);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_conversions_round_trip() -> Result<()> {
+    let original = StatefulTractogram::load("data/standard.LPS.trk")?;
+    let mut sft = original.clone();
+    for (space, origin) in [
+        (Space::Vox, Origin::Center),
+        (Space::Vox, Origin::Corner),
+        (Space::VoxMm, Origin::Corner),
+        (Space::VoxMm, Origin::Center),
+        (Space::RasMm, Origin::Corner),
+    ] {
+        sft.to_space(space);
+        sft.to_origin(origin);
+        assert_eq!((sft.space(), sft.origin()), (space, origin));
+    }
+    sft.to_rasmm();
+    sft.to_center();
+    assert_points_eq(&sft, &original);
+    Ok(())
+}
+
+#[test]
+fn test_corner_is_half_voxel_away() -> Result<()> {
+    let mut center = StatefulTractogram::load("data/simple.trk")?;
+    center.to_vox();
+    let mut corner = center.clone();
+    corner.to_corner();
+    for (p1, p2) in
+        center.tractogram().streamlines.data.iter().zip(&corner.tractogram().streamlines.data)
+    {
+        assert!((p2 - p1).iter().all(|&d| (d - 0.5).abs() < 1e-4));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_save_from_any_space() -> Result<()> {
+    let original = StatefulTractogram::load("data/complex.trk")?;
+    for space in [Space::RasMm, Space::VoxMm, Space::Vox] {
+        for origin in [Origin::Center, Origin::Corner] {
+            let mut sft = original.clone();
+            sft.to_space(space);
+            sft.to_origin(origin);
+
+            let write_to = get_random_trk_path();
+            sft.save(&write_to)?;
+            let saved = StatefulTractogram::load(&write_to)?;
+            assert_points_eq(&saved, &original);
+            assert!(saved.tractogram().scalars == original.tractogram().scalars);
+            assert!(saved.tractogram().properties == original.tractogram().properties);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_scalars_and_properties() -> Result<()> {
+    let mut sft = StatefulTractogram::load("data/complex.trk")?;
+    let colors = sft.scalar("colors")?.to_array_sequence();
+    assert_eq!(colors.data.len(), 3 * sft.tractogram().streamlines.data.len());
+
+    let lengths = sft.tractogram().streamlines.iter().map(|s| s.len() as f32).collect();
+    sft.add_property("nb_points", ArraySequence::new(vec![1; 3], lengths))?;
+    sft.remove_scalar("colors")?;
+    assert_eq!(sft.header().scalars_name, ["fa"]);
+
+    let write_to = get_random_trk_path();
+    sft.save(&write_to)?;
+    let saved = StatefulTractogram::load(&write_to)?;
+    assert_eq!(saved.header().properties_name.last().unwrap(), "nb_points");
+    assert_eq!(saved.property("nb_points")?.get(2).next().unwrap(), [5.0]);
+    assert!(saved.scalar("fa")?.to_array_sequence() == sft.scalar("fa")?.to_array_sequence());
+    Ok(())
+}
@@ -0,0 +1,105 @@
+mod test;
+
+use test::get_random_trk_path;
+use trk_io::{
+    stats::{Bounds, TractogramStats},
+    ArraySequence, Header, Point, Reader, Streamlines, Tractogram, Writer,
+};
+
+#[test]
+fn test_complex_stats() {
+    let stats = TractogramStats::from_reader(Reader::new("data/complex.trk").unwrap(), 4);
+    assert_eq!(stats.nb_streamlines, 3);
+    assert_eq!(stats.header_nb_streamlines, 3);
+    assert_eq!(stats.nb_points, 8);
+    assert_eq!(stats.nb_degenerate, 1);
+
+    let step = 27.0f32.sqrt();
+    let length = stats.length.unwrap();
+    assert_eq!(length.min, 0.0);
+    assert_eq!(length.median, step);
+    assert!((length.max - 4.0 * step).abs() < 1e-5);
+    assert!((length.mean - 5.0 * step / 3.0).abs() < 1e-5);
+    assert_eq!(length.bin_edges.len(), 5);
+    assert_eq!(length.counts, [1, 1, 0, 1]);
+
+    let step_size = stats.step_size.unwrap();
+    assert_eq!(step_size.count, 5);
+    assert!((step_size.min - step).abs() < 1e-5);
+    assert!((step_size.max - step).abs() < 1e-5);
+
+    let bounds = Bounds { min: [0.0, 1.0, 2.0], max: [12.0, 13.0, 14.0] };
+    assert_eq!(stats.bounds_rasmm, Some(bounds));
+    assert_eq!(stats.bounds_vox, Some(bounds));
+
+    let names = stats.scalars.keys().collect::<Vec<_>>();
+    assert_eq!(names, ["colors_0", "colors_1", "colors_2", "fa"]);
+    let fa = stats.scalars["fa"];
+    assert_eq!(fa.count, 8);
+    assert_eq!((fa.min, fa.max), (0.2, 0.8));
+    assert!((fa.mean - 0.5125).abs() < 1e-6);
+
+    let names = stats.properties.keys().collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["mean_colors_0", "mean_colors_1", "mean_colors_2", "mean_curvature", "mean_torsion"]
+    );
+    let curvature = stats.properties["mean_curvature"];
+    assert_eq!((curvature.count, curvature.min, curvature.max), (3, 1.11, 3.11));
+}
+
+#[test]
+fn test_reader_and_tractogram_agree() {
+    let mut reader = Reader::new("data/complex.trk").unwrap();
+    let tractogram = reader.tractogram();
+    let from_tractogram = TractogramStats::from_tractogram(&reader.header, &tractogram, 10);
+    let from_reader = TractogramStats::from_reader(Reader::new("data/complex.trk").unwrap(), 10);
+    assert_eq!(from_tractogram, from_reader);
+}
+
+#[test]
+fn test_empty_stats() {
+    let stats = TractogramStats::from_reader(Reader::new("data/empty.trk").unwrap(), 10);
+    assert_eq!(stats.nb_streamlines, 0);
+    assert_eq!(stats.nb_points, 0);
+    assert_eq!(stats.length, None);
+    assert_eq!(stats.step_size, None);
+    assert_eq!(stats.bounds_rasmm, None);
+    assert_eq!(stats.bounds_vox, None);
+    assert!(stats.scalars.is_empty());
+}
+
+#[test]
+fn test_missing_n_count() {
+    let points =
+        vec![Point::new(0.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0)];
+    let streamlines = Streamlines::new(vec![2, 0, 1], points);
+    let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+    let path = get_random_trk_path();
+    let mut writer = Writer::new(&path, None).unwrap();
+    writer.write(tractogram);
+    drop(writer);
+
+    // Pretend that the writer didn't know the number of streamlines
+    let mut header = Header::from_trk(&path).unwrap();
+    header.nb_streamlines = 0;
+    let stats =
+        TractogramStats::from_tractogram(&header, &Reader::new(&path).unwrap().tractogram(), 2);
+    assert_eq!(stats.nb_streamlines, 3);
+    assert_eq!(stats.header_nb_streamlines, 0);
+    assert_eq!(stats.nb_degenerate, 2);
+    let length = stats.length.unwrap();
+    assert_eq!((length.min, length.median, length.max), (0.0, 0.0, 2.0));
+    assert_eq!(length.bin_edges, [0.0, 1.0, 2.0]);
+    assert_eq!(length.counts, [2, 1]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize() {
+    let stats = TractogramStats::from_reader(Reader::new("data/complex.trk").unwrap(), 4);
+    let json = serde_json::to_string(&stats).unwrap();
+    assert!(json.contains("\"nb_degenerate\":1"));
+    let back: TractogramStats = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, stats);
+}
@@ -0,0 +1,109 @@
+use trk_io::{
+    streamline::{
+        arc_lengths, centroid, direct_flip_distances, flip, is_flipped, length, mdf, resample,
+        resample_with_scalars,
+    },
+    Point, Streamlines,
+};
+
+fn get_toy_streamline() -> Vec<Point> {
+    vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0), Point::new(1.0, 3.0, 0.0)]
+}
+
+#[test]
+fn test_length() {
+    let streamline = get_toy_streamline();
+    assert_eq!(length(&streamline), 4.0);
+    assert_eq!(arc_lengths(&streamline), vec![0.0, 1.0, 4.0]);
+    assert_eq!(length(&streamline[..1]), 0.0);
+    assert_eq!(arc_lengths(&[]), Vec::<f32>::new());
+}
+
+#[test]
+fn test_resample() {
+    let streamline = get_toy_streamline();
+    let resampled = resample(&streamline, 5);
+    assert_eq!(
+        resampled,
+        vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(1.0, 2.0, 0.0),
+            Point::new(1.0, 3.0, 0.0),
+        ]
+    );
+
+    let resampled = resample(&streamline[..1], 3);
+    assert_eq!(resampled, vec![streamline[0]; 3]);
+}
+
+#[test]
+fn test_resample_with_scalars() {
+    let streamline = get_toy_streamline();
+    let scalars = [0.0, 10.0, 1.0, 20.0, 4.0, 50.0];
+    let (points, scalars) = resample_with_scalars(&streamline, &scalars, 3);
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[1], Point::new(1.0, 1.0, 0.0));
+    assert_eq!(scalars, vec![0.0, 10.0, 2.0, 30.0, 4.0, 50.0]);
+}
+
+#[test]
+#[should_panic]
+fn test_resample_one_point() {
+    resample(&get_toy_streamline(), 1);
+}
+
+#[test]
+fn test_mdf() {
+    let a = vec![Point::new(0.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0)];
+    let b = vec![Point::new(2.0, 1.0, 0.0), Point::new(0.0, 1.0, 0.0)];
+    let (direct, flipped) = direct_flip_distances(&a, &b);
+    assert!((direct - 5.0f32.sqrt()).abs() < 1e-6);
+    assert_eq!(flipped, 1.0);
+    assert_eq!(mdf(&a, &b), 1.0);
+}
+
+#[test]
+fn test_flip() {
+    let mut streamline = get_toy_streamline();
+    let mut scalars = [0.0, 10.0, 1.0, 20.0, 4.0, 50.0];
+    flip(&mut streamline, &mut scalars);
+    assert_eq!(
+        streamline,
+        vec![Point::new(1.0, 3.0, 0.0), Point::new(1.0, 0.0, 0.0), Point::new(0.0, 0.0, 0.0)]
+    );
+    assert_eq!(scalars, [4.0, 50.0, 1.0, 20.0, 0.0, 10.0]);
+
+    flip(&mut streamline, &mut []);
+    assert_eq!(streamline, get_toy_streamline());
+}
+
+#[test]
+fn test_is_flipped() {
+    let streamline = get_toy_streamline();
+    let reversed = streamline.iter().rev().cloned().collect::<Vec<_>>();
+    let reference = [Point::new(0.0, 0.5, 0.0), Point::new(1.0, 3.5, 0.0)];
+    assert!(!is_flipped(&streamline, &reference));
+    assert!(is_flipped(&reversed, &reference));
+    assert!(!is_flipped(&[], &reference));
+}
+
+#[test]
+fn test_centroid() {
+    let streamlines = Streamlines::new(
+        vec![2, 3, 0],
+        vec![
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(4.0, 1.0, 0.0),
+            Point::new(4.0, -1.0, 0.0),
+            Point::new(2.0, -1.0, 0.0),
+            Point::new(0.0, -1.0, 0.0),
+        ],
+    );
+    assert_eq!(
+        centroid(&streamlines, 3),
+        vec![Point::new(0.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0), Point::new(4.0, 0.0, 0.0)]
+    );
+    assert!(centroid(&Streamlines::empty(), 3).is_empty());
+}
@@ -0,0 +1,81 @@
+mod test;
+
+#[cfg(feature = "subsample")]
+mod subsample_tests {
+    use anyhow::Result;
+
+    use crate::test::get_random_trk_path;
+    use trk_io::{
+        subsample::{Sampling, Subsample},
+        ArraySequence, Header, Point, Reader, Streamlines, Tractogram, Writer,
+    };
+
+    /// Write a trk file of 100 streamlines of 2 points, whose first x coordinate is their index, with
+    /// a `weight` property of 0 for the even streamlines and 1 for the odd ones.
+    fn write_numbered() -> String {
+        let points = (0..200).map(|i| Point::new((i / 2) as f32, 0.0, 0.0)).collect();
+        let streamlines = Streamlines::new(vec![2; 100], points);
+        let weights = (0..100).map(|i| (i % 2) as f32).collect();
+        let mut tractogram =
+            Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+        let mut header = Header::default();
+        tractogram
+            .add_property(&mut header, "weight", ArraySequence::new(vec![1; 100], weights))
+            .unwrap();
+
+        let path = get_random_trk_path();
+        let mut writer = Writer::new(&path, Some(&header)).unwrap();
+        writer.write(tractogram);
+        path
+    }
+
+    fn sample(path: &str, sampling: Sampling, seed: u64) -> Result<Vec<usize>> {
+        let subsample = Subsample::new(Reader::new(path)?, sampling, seed)?;
+        Ok(subsample.map(|(streamline, _, _)| streamline[0].x.round() as usize).collect())
+    }
+
+    #[test]
+    fn test_number() -> Result<()> {
+        let path = write_numbered();
+        let indices = sample(&path, Sampling::Number(10), 42)?;
+        assert_eq!(indices.len(), 10);
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(indices, sample(&path, Sampling::Number(10), 42)?);
+        assert_ne!(indices, sample(&path, Sampling::Number(10), 43)?);
+
+        assert_eq!(sample(&path, Sampling::Number(1000), 42)?, (0..100).collect::<Vec<_>>());
+        assert!(sample(&path, Sampling::Number(0), 42)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_percent() -> Result<()> {
+        let path = write_numbered();
+        let indices = sample(&path, Sampling::Percent(30.0), 7)?;
+        assert!((15..=45).contains(&indices.len()));
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(indices, sample(&path, Sampling::Percent(30.0), 7)?);
+
+        assert_eq!(sample(&path, Sampling::Percent(100.0), 7)?.len(), 100);
+        assert!(sample(&path, Sampling::Percent(0.0), 7)?.is_empty());
+        assert!(sample(&path, Sampling::Percent(101.0), 7).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_weighted() -> Result<()> {
+        let path = write_numbered();
+        let sampling = Sampling::Weighted { property: "weight", scale: 1.0 };
+        let indices = sample(&path, sampling, 1)?;
+        assert_eq!(indices, (0..100).filter(|i| i % 2 == 1).collect::<Vec<_>>());
+
+        let sampling = Sampling::Weighted { property: "weight", scale: 0.5 };
+        let indices = sample(&path, sampling, 1)?;
+        assert!(indices.iter().all(|i| i % 2 == 1));
+        assert!((10..=40).contains(&indices.len()));
+
+        let sampling = Sampling::Weighted { property: "length", scale: 1.0 };
+        assert!(sample(&path, sampling, 1).is_err());
+        Ok(())
+    }
+}
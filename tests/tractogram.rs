@@ -0,0 +1,238 @@
+use anyhow::Result;
+
+use trk_io::{
+    Affine4, ArraySequence, Header, Point, Reader, Streamlines, Tractogram, Translation, Writer,
+};
+
+/// Two streamlines going from x=0 to x=2, the second one being stored in reverse order.
+fn get_toy_tractogram() -> Tractogram {
+    let streamlines = Streamlines::new(
+        vec![3, 3],
+        vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(2.0, 0.0, 0.0),
+            Point::new(2.0, 1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ],
+    );
+    let scalars = ArraySequence::new(
+        vec![6, 6],
+        vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 20.0, 20.5, 10.0, 10.5, 0.0, 0.5],
+    );
+    let properties = ArraySequence::new(vec![1, 1], vec![1.0, 2.0]);
+    Tractogram::new(streamlines, scalars, properties)
+}
+
+#[test]
+fn test_flip() {
+    let mut tractogram = get_toy_tractogram();
+    tractogram.flip(1);
+    assert_eq!(
+        tractogram.streamlines[1],
+        [Point::new(0.0, 1.0, 0.0), Point::new(1.0, 1.0, 0.0), Point::new(2.0, 1.0, 0.0)]
+    );
+    assert_eq!(tractogram.scalars[1], [0.0, 0.5, 10.0, 10.5, 20.0, 20.5]);
+    assert_eq!(tractogram.properties[1], [2.0]);
+
+    // Streamlines without scalars
+    let mut tractogram = get_toy_tractogram();
+    tractogram.scalars = ArraySequence::empty();
+    tractogram.flip(0);
+    assert_eq!(tractogram.streamlines[0][0], Point::new(2.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_orient_to_reference() {
+    let mut tractogram = get_toy_tractogram();
+    let reference = [Point::new(-1.0, 0.5, 0.0), Point::new(3.0, 0.5, 0.0)];
+    tractogram.orient_to_reference(&reference);
+    assert_eq!(tractogram.streamlines[0][0], Point::new(0.0, 0.0, 0.0));
+    assert_eq!(tractogram.streamlines[1][0], Point::new(0.0, 1.0, 0.0));
+    assert_eq!(tractogram.scalars[1][..2], [0.0, 0.5]);
+
+    let reference = [Point::new(3.0, 0.5, 0.0), Point::new(-1.0, 0.5, 0.0)];
+    tractogram.orient_to_reference(&reference);
+    assert_eq!(tractogram.streamlines[0][0], Point::new(2.0, 0.0, 0.0));
+    assert_eq!(tractogram.streamlines[1][0], Point::new(2.0, 1.0, 0.0));
+}
+
+#[test]
+fn test_orient_to_roi() {
+    let mut tractogram = get_toy_tractogram();
+    tractogram.orient_to_roi(|p| p.x > 1.5);
+    assert_eq!(tractogram.streamlines[0][0], Point::new(2.0, 0.0, 0.0));
+    assert_eq!(tractogram.streamlines[1][0], Point::new(2.0, 1.0, 0.0));
+    assert_eq!(tractogram.scalars[0][..2], [2.0, 2.5]);
+
+    // No streamline ends in this ROI
+    let original = get_toy_tractogram();
+    let mut tractogram = original.clone();
+    tractogram.orient_to_roi(|p| p.x > 5.0);
+    assert!(tractogram == original);
+}
+
+#[test]
+fn test_apply_affine() {
+    let mut tractogram = get_toy_tractogram();
+    tractogram.apply_affine(&Affine4::new_translation(&Translation::new(1.0, 2.0, 3.0)));
+    assert_eq!(tractogram.streamlines.offsets, vec![0, 3, 6]);
+    assert_eq!(tractogram.streamlines[0][0], Point::new(1.0, 2.0, 3.0));
+    assert_eq!(tractogram.streamlines[1][2], Point::new(1.0, 3.0, 3.0));
+    assert!(tractogram.scalars == get_toy_tractogram().scalars);
+}
+
+#[test]
+fn test_select() {
+    let tractogram = get_toy_tractogram();
+    let selected = tractogram.select(&[1, 0, 1]);
+    assert_eq!(selected.streamlines.len(), 3);
+    assert_eq!(selected.streamlines[0], tractogram.streamlines[1]);
+    assert_eq!(selected.streamlines[1], tractogram.streamlines[0]);
+    assert_eq!(selected.scalars[2], tractogram.scalars[1]);
+    assert_eq!(selected.properties.data, [2.0, 1.0, 2.0]);
+
+    // Streamlines without scalars nor properties
+    let mut tractogram = get_toy_tractogram();
+    tractogram.scalars = ArraySequence::empty();
+    tractogram.properties = ArraySequence::empty();
+    let selected = tractogram.select(&[1]);
+    assert_eq!(selected.streamlines[0], tractogram.streamlines[1]);
+    assert!(selected.scalars.is_empty());
+    assert!(selected.properties.is_empty());
+}
+
+#[test]
+fn test_select_empty_streamline() {
+    let tractogram = Tractogram::new(
+        Streamlines::new(vec![0, 2], vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0)]),
+        ArraySequence::empty(),
+        ArraySequence::new(vec![1, 1], vec![1.0, 2.0]),
+    );
+    let selected = tractogram.select(&[1, 0]);
+    assert_eq!(selected.streamlines.len(), 2);
+    assert_eq!(selected.properties.len(), 2);
+    assert!(selected.streamlines[1].is_empty());
+    assert_eq!(selected.properties[0], [2.0]);
+    assert_eq!(selected.properties[1], [1.0]);
+}
+
+#[test]
+fn test_scalar_accessors() -> Result<()> {
+    let mut header = Header::default();
+    header.add_scalar("fa")?;
+    header.add_scalar("md")?;
+    header.add_property("id")?;
+    let mut tractogram = get_toy_tractogram();
+
+    let md = tractogram.scalar(&header, "md")?;
+    assert_eq!(md.len(), 2);
+    assert_eq!(md.nb_components(), 1);
+    assert_eq!(md.get(1).collect::<Vec<_>>(), [[20.5], [10.5], [0.5]]);
+    let expected = ArraySequence::new(vec![3, 3], vec![0.5, 1.5, 2.5, 20.5, 10.5, 0.5]);
+    assert!(md.to_array_sequence() == expected);
+    assert!(tractogram.scalar(&header, "rd").is_err());
+
+    let fa = ArraySequence::new(vec![3, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    tractogram.set_scalar(&header, "fa", &fa)?;
+    assert_eq!(tractogram.scalars[0], [1.0, 0.5, 2.0, 1.5, 3.0, 2.5]);
+    let too_short = ArraySequence::new(vec![3, 2], vec![1.0; 5]);
+    assert!(tractogram.set_scalar(&header, "fa", &too_short).is_err());
+
+    let colors = ArraySequence::new(vec![9, 9], (0..18).map(|v| v as f32).collect());
+    tractogram.add_scalar(&mut header, "colors", colors.clone())?;
+    assert_eq!(header.scalars_name, ["fa", "md", "colors", "colors", "colors"]);
+    assert_eq!(
+        tractogram.scalars[1],
+        [4.0, 20.5, 9.0, 10.0, 11.0, 5.0, 10.5, 12.0, 13.0, 14.0, 6.0, 0.5, 15.0, 16.0, 17.0]
+    );
+    let view = tractogram.scalar(&header, "colors")?;
+    assert_eq!(view.nb_components(), 3);
+    assert!(view.to_array_sequence() == colors);
+
+    tractogram.remove_scalar(&mut header, "md")?;
+    assert_eq!(header.scalars_name, ["fa", "colors", "colors", "colors"]);
+    assert_eq!(tractogram.scalars[0], [1.0, 0.0, 1.0, 2.0, 2.0, 3.0, 4.0, 5.0, 3.0, 6.0, 7.0, 8.0]);
+    assert!(tractogram.scalar(&header, "colors")?.to_array_sequence() == colors);
+
+    tractogram.remove_scalar(&mut header, "fa")?;
+    tractogram.remove_scalar(&mut header, "colors")?;
+    assert!(header.scalars_name.is_empty());
+    assert!(tractogram.scalars.is_empty());
+    assert!(tractogram.remove_scalar(&mut header, "colors").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_property_accessors() -> Result<()> {
+    let mut header = Header::default();
+    header.add_property("id")?;
+    let mut tractogram = get_toy_tractogram();
+
+    let id = tractogram.property(&header, "id")?;
+    assert_eq!(id.get(1).collect::<Vec<_>>(), [[2.0]]);
+
+    let centroids = ArraySequence::new(vec![3, 3], vec![1.0, 0.0, 0.0, 1.0, 1.0, 0.0]);
+    tractogram.add_property(&mut header, "centroid", centroids.clone())?;
+    assert_eq!(header.properties_groups(), [("id".into(), 0..1), ("centroid".into(), 1..4)]);
+    assert_eq!(tractogram.properties[1], [2.0, 1.0, 1.0, 0.0]);
+    assert!(tractogram.property(&header, "centroid")?.to_array_sequence() == centroids);
+
+    tractogram.set_property(&header, "id", &ArraySequence::new(vec![1, 1], vec![5.0, 6.0]))?;
+    tractogram.remove_property(&mut header, "centroid")?;
+    assert_eq!(header.properties_name, ["id"]);
+    assert_eq!(tractogram.properties.data, [5.0, 6.0]);
+
+    // The tractogram must match the header
+    let mut header = Header::default();
+    header.add_property("id")?;
+    header.add_property("weight")?;
+    assert!(tractogram.property(&header, "id").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_views() {
+    let tractogram = Reader::new("data/complex.trk").unwrap().tractogram();
+    let view = tractogram.view(1..3);
+    assert_eq!(view.len(), 2);
+    assert_eq!(view.item(0), tractogram.item(1));
+    assert_eq!(view.streamlines().length_of_array(1), 5);
+    assert_eq!(view.scalars().get(1), &tractogram.scalars[2]);
+    assert_eq!(view.properties().get(0), &tractogram.properties[1]);
+    assert!(view.to_tractogram() == tractogram.select(&[1, 2]));
+    assert_eq!(view.view(1..2).item(0), tractogram.item(2));
+
+    let indices = [2, 0];
+    let view = tractogram.view_indices(&indices);
+    assert_eq!(view.iter().collect::<Vec<_>>(), [tractogram.item(2), tractogram.item(0)]);
+    assert!(view.to_tractogram() == tractogram.select(&indices));
+
+    let sizes = tractogram.chunks(2).map(|chunk| chunk.len()).collect::<Vec<_>>();
+    assert_eq!(sizes, [2, 1]);
+}
+
+#[test]
+fn test_views_without_scalars() {
+    let streamlines = get_toy_tractogram().streamlines;
+    let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+    let view = tractogram.view_indices(&[1]);
+    assert!(view.scalars().is_empty());
+    assert!(view.properties().is_empty());
+    assert_eq!(view.item(0), tractogram.item(1));
+    assert!(view.to_tractogram() == tractogram.select(&[1]));
+}
+
+#[test]
+fn test_write_view() {
+    let mut reader = Reader::new("data/complex.trk").unwrap();
+    let tractogram = reader.tractogram();
+    let path = tempfile::TempDir::new().unwrap().keep().join("view.trk");
+    {
+        let mut writer = Writer::new(&path, Some(&reader.header)).unwrap();
+        writer.write(tractogram.view(1..3));
+    }
+    let written = Reader::new(&path).unwrap().tractogram();
+    assert!(written == tractogram.select(&[1, 2]));
+}
@@ -0,0 +1,115 @@
+mod test;
+
+use std::fs::write;
+
+use anyhow::Result;
+
+use test::get_random_trk_path;
+use trk_io::{
+    transform::{load_itk, load_text},
+    Point,
+};
+
+fn assert_close(a: Point, b: Point) {
+    assert!((a - b).norm() < 1e-5, "{} != {}", a, b);
+}
+
+#[test]
+fn test_load_itk_mat() -> Result<()> {
+    // x' = -y - 11, y' = x + 8, z' = z + 3 in RAS+
+    let affine = load_itk("data/itk_affine.mat", false)?;
+    assert_close(affine.transform_point(&Point::new(1.0, 2.0, 3.0)), Point::new(-13.0, 9.0, 6.0));
+
+    let inverse = load_itk("data/itk_affine.mat", true)?;
+    assert_close(inverse.transform_point(&Point::new(-13.0, 9.0, 6.0)), Point::new(1.0, 2.0, 3.0));
+    Ok(())
+}
+
+#[test]
+fn test_load_itk_text() -> Result<()> {
+    let path = get_random_trk_path().replace(".trk", ".txt");
+    write(
+        &path,
+        "#Insight Transform File V1.0\n\
+         #Transform 0\n\
+         Transform: AffineTransform_double_3_3\n\
+         Parameters: 0 -1 0 1 0 0 0 0 1 1 2 3\n\
+         FixedParameters: 10 0 0\n",
+    )?;
+    let affine = load_itk(&path, false)?;
+    assert_eq!(affine, load_itk("data/itk_affine.mat", false)?);
+    Ok(())
+}
+
+#[test]
+fn test_load_itk_wrong_file() {
+    assert!(load_itk("data/simple.trk", false).is_err());
+    assert!(load_itk("data/does_not_exist.mat", false).is_err());
+}
+
+#[test]
+fn test_load_text() -> Result<()> {
+    let path = get_random_trk_path().replace(".trk", ".txt");
+    write(&path, "# Some comment\n2 0 0 1\n0 2 0 2\n0 0 2 3\n0 0 0 1\n")?;
+    let affine = load_text(&path, false)?;
+    assert_eq!(affine.transform_point(&Point::new(1.0, 1.0, 1.0)), Point::new(3.0, 4.0, 5.0));
+
+    let inverse = load_text(&path, true)?;
+    assert_eq!(inverse.transform_point(&Point::new(3.0, 4.0, 5.0)), Point::new(1.0, 1.0, 1.0));
+
+    write(&path, "1 0 0\n0 1 0\n")?;
+    assert!(load_text(&path, false).is_err());
+    write(&path, "1 0 0 0\n0 1 0 0\n0 0 0 0\n0 0 0 1\n")?;
+    assert!(load_text(&path, true).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "nifti_images")]
+mod nifti_tests {
+    use std::fs::write;
+
+    use anyhow::Result;
+    use nifti::NiftiHeader;
+    use trk_io::{transform::load_fsl, Affine4, Point};
+
+    use crate::{assert_close, test::get_random_trk_path};
+
+    fn get_nifti_header(affine: &Affine4) -> NiftiHeader {
+        let mut header = NiftiHeader {
+            dim: [3, 10, 10, 10, 1, 1, 1, 1],
+            pixdim: [1.0, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0, 1.0],
+            ..NiftiHeader::default()
+        };
+        header.set_affine(affine);
+        header
+    }
+
+    #[test]
+    fn test_load_fsl() -> Result<()> {
+        let path = get_random_trk_path().replace(".trk", ".mat");
+        let header = get_nifti_header(&Affine4::new_scaling(2.0));
+
+        write(&path, "1 0 0 0\n0 1 0 0\n0 0 1 0\n0 0 0 1\n")?;
+        let affine = load_fsl(&path, &header, &header, false)?;
+        assert!((affine - Affine4::identity()).abs().max() < 1e-5);
+
+        // The first axis of the FSL space is flipped for images in neurological order, so it
+        // always points to the left
+        write(&path, "1 0 0 1\n0 1 0 1\n0 0 1 0\n0 0 0 1\n")?;
+        let affine = load_fsl(&path, &header, &header, false)?;
+        assert_close(affine.transform_point(&Point::new(4.0, 4.0, 4.0)), Point::new(3.0, 5.0, 4.0));
+
+        let mut radiological = Affine4::new_scaling(2.0);
+        radiological[(0, 0)] = -2.0;
+        let header = get_nifti_header(&radiological);
+        let affine = load_fsl(&path, &header, &header, false)?;
+        assert_close(affine.transform_point(&Point::new(4.0, 4.0, 4.0)), Point::new(3.0, 5.0, 4.0));
+
+        let inverse = load_fsl(&path, &header, &header, true)?;
+        assert_close(
+            inverse.transform_point(&Point::new(3.0, 5.0, 4.0)),
+            Point::new(4.0, 4.0, 4.0),
+        );
+        Ok(())
+    }
+}
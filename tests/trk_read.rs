@@ -186,6 +186,7 @@ fn test_load_complex_big_endian() -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::excessive_precision)]
 fn check_complex_scalars_and_properties(
     header: Header,
     scalars: ArraySequence<f32>,
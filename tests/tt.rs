@@ -0,0 +1,130 @@
+mod test;
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+};
+
+use anyhow::Result;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use test::load_trk;
+use trk_io::{
+    tt::{read_tt, write_tt},
+    Affine4, ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+fn get_random_tt_path() -> String {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.keep().join("out.tt.gz");
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_round_trip() -> Result<()> {
+    let (header, tractogram) = load_trk("data/standard.trk");
+    let write_to = get_random_tt_path();
+    write_tt(&write_to, &header, &tractogram)?;
+
+    let (tt_header, tt_tractogram) = read_tt(&write_to)?;
+    assert_eq!(tt_header.nb_streamlines, tractogram.streamlines.len());
+    assert_eq!(tt_header.raw_header().dim, header.raw_header().dim);
+    assert_eq!(tt_header.raw_header().voxel_size, header.raw_header().voxel_size);
+    assert_eq!(tt_header.raw_header().vox_to_ras, header.raw_header().vox_to_ras);
+    assert_eq!(tt_tractogram.streamlines.offsets, tractogram.streamlines.offsets);
+
+    // The coordinates are rounded to 1/32 voxel
+    let max_error = header.raw_header().voxel_size.iter().cloned().fold(0.0, f32::max) / 64.0;
+    for (p1, p2) in tt_tractogram.streamlines.data.iter().zip(&tractogram.streamlines.data) {
+        assert!((p1 - p2).abs().max() <= max_error + 1e-5, "{:?} != {:?}", p1, p2);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encoding() -> Result<()> {
+    let header = Header::from_affine(&Affine4::identity(), [10, 10, 10], [1.0, 1.0, 1.0]);
+    let streamlines =
+        Streamlines::new(vec![2], vec![Point::new(1.0, 2.0, 3.0), Point::new(1.5, 1.0, 3.0)]);
+    let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+    let write_to = get_random_tt_path();
+    write_tt(&write_to, &header, &tractogram)?;
+
+    // `track` is the last variable of the file
+    let mut bytes = vec![];
+    GzDecoder::new(File::open(&write_to)?).read_to_end(&mut bytes)?;
+    let mut expected = vec![6, 0, 0, 0, 32, 0, 0, 0, 64, 0, 0, 0, 96, 0, 0, 0];
+    expected.extend([16, (-32i8) as u8, 0]);
+    assert!(bytes.ends_with(&expected));
+
+    let (_, tt_tractogram) = read_tt(&write_to)?;
+    assert!(tt_tractogram == tractogram);
+    Ok(())
+}
+
+#[test]
+fn test_points_too_far() {
+    let header = Header::from_affine(&Affine4::identity(), [10, 10, 10], [1.0, 1.0, 1.0]);
+    let streamlines =
+        Streamlines::new(vec![2], vec![Point::new(0.0, 0.0, 0.0), Point::new(5.0, 0.0, 0.0)]);
+    let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), ArraySequence::empty());
+    assert!(write_tt(get_random_tt_path(), &header, &tractogram).is_err());
+}
+
+#[test]
+fn test_read_invalid() {
+    assert!(read_tt("data/simple.trk").is_err());
+    assert!(read_tt("data/not_there.tt.gz").is_err());
+}
+
+/// Write a tt.gz file with the `dimension` and `track` variables, as little endian int32 and uint8.
+fn write_raw_tt(path: &str, dim: [i32; 3], track: &[u8]) -> Result<()> {
+    let mut bytes = vec![];
+    let mut add_variable = |type_: i32, name: &[u8], cols: usize, data: &[u8]| {
+        for i in [type_, 1, cols as i32, 0, name.len() as i32 + 1] {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+        bytes.extend_from_slice(name);
+        bytes.push(0);
+        bytes.extend_from_slice(data);
+    };
+    add_variable(
+        20,
+        b"dimension",
+        3,
+        &dim.iter().flat_map(|d| d.to_le_bytes()).collect::<Vec<_>>(),
+    );
+    add_variable(
+        10,
+        b"voxel_size",
+        3,
+        &[1.0f32; 3].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>(),
+    );
+    add_variable(50, b"track", track.len(), track);
+
+    let mut encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    encoder.write_all(&bytes)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+#[test]
+fn test_read_crafted() -> Result<()> {
+    let path = get_random_tt_path();
+    let mut track = vec![6, 0, 0, 0];
+    track.extend([0i32, 0, 0].iter().flat_map(|c| c.to_le_bytes()));
+    track.extend([1, 0, 0]);
+    write_raw_tt(&path, [10, 10, 10], &track)?;
+    assert_eq!(read_tt(&path)?.1.streamlines.len(), 1);
+
+    write_raw_tt(&path, [i16::MIN as i32, 10, 10], &track)?;
+    assert!(read_tt(&path).is_err());
+
+    // The delta overflows the first coordinate
+    let mut track = vec![6, 0, 0, 0];
+    track.extend([i32::MAX, 0, 0].iter().flat_map(|c| c.to_le_bytes()));
+    track.extend([1, 0, 0]);
+    write_raw_tt(&path, [10, 10, 10], &track)?;
+    assert!(read_tt(&path).is_err());
+    Ok(())
+}
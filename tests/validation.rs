@@ -0,0 +1,103 @@
+mod test;
+
+use anyhow::Result;
+
+use test::load_trk;
+use trk_io::{
+    validation::{validate, validate_and_fix, BoundingBox, ValidationMode},
+    ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+/// Returns the header of simple.trk, along with 4 streamlines: valid, out of bounds, NaN and
+/// valid. The points are built in trackvis voxmm space, then moved to RAS+ mm.
+fn get_tractogram() -> (Header, Tractogram) {
+    let (header, _) = load_trk("data/simple.trk");
+    let c_header = header.raw_header();
+    let extent = [
+        c_header.dim[0] as f32 * c_header.voxel_size[0],
+        c_header.dim[1] as f32 * c_header.voxel_size[1],
+        c_header.dim[2] as f32 * c_header.voxel_size[2],
+    ];
+    let to_rasmm = |x, y, z| header.affine4_to_rasmm.transform_point(&Point::new(x, y, z));
+    let streamlines = Streamlines::new(
+        vec![2, 3, 2, 1],
+        vec![
+            to_rasmm(0.0, 0.0, 0.0),
+            to_rasmm(extent[0], extent[1], extent[2]),
+            to_rasmm(1.0, 1.0, 1.0),
+            to_rasmm(-2.0, 1.0, 1.0),
+            to_rasmm(1.0, extent[1] + 3.0, 1.0),
+            to_rasmm(1.0, 1.0, 1.0),
+            Point::new(f32::NAN, 0.0, 0.0),
+            to_rasmm(0.5, 0.5, 0.5),
+        ],
+    );
+    let properties = ArraySequence::new(vec![1, 1, 1, 1], vec![0.0, 1.0, 2.0, 3.0]);
+    (header, Tractogram::new(streamlines, ArraySequence::empty(), properties))
+}
+
+#[test]
+fn test_validate() -> Result<()> {
+    let (header, tractogram) = get_tractogram();
+    let report = validate(&tractogram, &header)?;
+    assert!(!report.is_valid());
+    assert_eq!(report.out_of_bounds, [1]);
+    assert_eq!(report.non_finite, [2]);
+    assert_eq!(report.nb_points_out_of_bounds, 2);
+    assert_eq!(report.invalid(), [1, 2]);
+
+    let (header, tractogram) = load_trk("data/standard.trk");
+    assert!(validate(&tractogram, &header)?.is_valid());
+    Ok(())
+}
+
+#[test]
+fn test_reject() -> Result<()> {
+    let (header, mut tractogram) = get_tractogram();
+    let report = validate_and_fix(&mut tractogram, &header, ValidationMode::Reject)?;
+    assert_eq!(report.invalid(), [1, 2]);
+    assert_eq!(tractogram.streamlines.len(), 2);
+    assert_eq!(tractogram.properties.data, [0.0, 3.0]);
+    assert!(validate(&tractogram, &header)?.is_valid());
+    Ok(())
+}
+
+#[test]
+fn test_clip() -> Result<()> {
+    let (header, mut tractogram) = get_tractogram();
+    let original = tractogram.clone();
+    validate_and_fix(&mut tractogram, &header, ValidationMode::Clip)?;
+    assert_eq!(tractogram.streamlines.len(), 3);
+    assert_eq!(tractogram.properties.data, [0.0, 1.0, 3.0]);
+    assert!(validate(&tractogram, &header)?.is_valid());
+
+    // Only the points outside the volume are moved
+    assert_eq!(tractogram.streamlines[0], original.streamlines[0]);
+    assert_eq!(tractogram.streamlines[1][0], original.streamlines[1][0]);
+    let to_voxmm = header.affine4_to_rasmm.try_inverse().unwrap();
+    let clipped = to_voxmm.transform_point(&tractogram.streamlines[1][1]);
+    assert!((clipped - Point::new(0.0, 1.0, 1.0)).norm() < 1e-3);
+    Ok(())
+}
+
+#[test]
+fn test_ignore() -> Result<()> {
+    let (header, mut tractogram) = get_tractogram();
+    let original = tractogram.clone();
+    let report = validate_and_fix(&mut tractogram, &header, ValidationMode::Ignore)?;
+    assert_eq!(report.invalid(), [1, 2]);
+    assert_eq!(tractogram.streamlines.len(), 4);
+    assert_eq!(tractogram.properties.data, original.properties.data);
+    Ok(())
+}
+
+#[test]
+fn test_bounding_box() -> Result<()> {
+    let (header, tractogram) = get_tractogram();
+    let bbox = BoundingBox::from_header(&header)?;
+    assert!(bbox.contains(&tractogram.streamlines[0][1]));
+    assert!(!bbox.contains(&tractogram.streamlines[1][1]));
+    assert!(!bbox.contains(&tractogram.streamlines[2][1]));
+    assert_eq!(bbox.clip(&tractogram.streamlines[0][0]), tractogram.streamlines[0][0]);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+mod test;
+
+use std::fs::write;
+
+use anyhow::Result;
+
+use test::load_trk;
+use trk_io::{
+    vtk::{read_vtk, write_vtk, VtkEncoding},
+    ArraySequence, Header, Point, Streamlines, Tractogram,
+};
+
+fn get_random_vtk_path() -> String {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.keep().join("out.vtk");
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_round_trip() -> Result<()> {
+    for path in ["data/simple.trk", "data/complex.trk", "data/empty.trk"] {
+        let (header, tractogram) = load_trk(path);
+        for encoding in [VtkEncoding::Ascii, VtkEncoding::Binary] {
+            let write_to = get_random_vtk_path();
+            write_vtk(&write_to, &header, &tractogram, encoding)?;
+            let (vtk_header, vtk_tractogram) = read_vtk(&write_to)?;
+            assert_eq!(vtk_header.nb_streamlines, tractogram.streamlines.len());
+            assert_eq!(vtk_header.scalars_name, header.scalars_name);
+            assert_eq!(vtk_header.properties_name, header.properties_name);
+            assert!(vtk_tractogram == tractogram);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_grouped_scalars() -> Result<()> {
+    let mut header = Header::default();
+    header.add_scalar_n("color", 3)?;
+    header.add_scalar_n("tensor", 6)?;
+    header.add_property("weight")?;
+    let streamlines = Streamlines::new(vec![1, 2], vec![Point::new(0.0, 1.0, 2.0); 3]);
+    let scalars = ArraySequence::new(vec![9, 18], (0..27).map(|v| v as f32).collect());
+    let properties = ArraySequence::new(vec![1, 1], vec![0.5, 1.5]);
+    let tractogram = Tractogram::new(streamlines, scalars, properties);
+
+    for encoding in [VtkEncoding::Ascii, VtkEncoding::Binary] {
+        let write_to = get_random_vtk_path();
+        write_vtk(&write_to, &header, &tractogram, encoding)?;
+        let (vtk_header, vtk_tractogram) = read_vtk(&write_to)?;
+        assert_eq!(vtk_header.scalars_groups(), header.scalars_groups());
+        assert_eq!(vtk_header.properties_groups(), header.properties_groups());
+        assert!(vtk_tractogram == tractogram);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_read_vtk5() -> Result<()> {
+    // Format written by VTK >= 9, with OFFSETS/CONNECTIVITY, FIELD data, multi-components arrays
+    // and lines that don't use the points in order.
+    let path = get_random_vtk_path();
+    write(
+        &path,
+        "# vtk DataFile Version 5.1
+vtk output
+ASCII
+DATASET POLYDATA
+POINTS 4 float
+0 0 0 1 0 0 2 0 0
+3 0 0
+METADATA
+INFORMATION 0
+
+LINES 3 5
+OFFSETS vtktypeint64
+0 3 5
+CONNECTIVITY vtktypeint64
+3 2 1 0 1
+POINT_DATA 4
+FIELD FieldData 1
+fa 1 4 float
+0.1 0.2 0.3 0.4
+CELL_DATA 2
+SCALARS my%20color float 3
+LOOKUP_TABLE default
+1 2 3
+4 5 6
+",
+    )?;
+
+    let (header, tractogram) = read_vtk(&path)?;
+    assert_eq!(header.nb_streamlines, 2);
+    assert_eq!(header.scalars_name, ["fa"]);
+    assert_eq!(header.properties_name, ["my color", "my color", "my color"]);
+    assert_eq!(header.properties_groups(), [("my color".to_string(), 0..3)]);
+    assert_eq!(
+        tractogram.streamlines[0],
+        [Point::new(3.0, 0.0, 0.0), Point::new(2.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0)]
+    );
+    assert_eq!(tractogram.streamlines[1], [Point::new(0.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0)]);
+    assert_eq!(tractogram.scalars.data, [0.4, 0.3, 0.2, 0.1, 0.2]);
+    assert_eq!(tractogram.properties.data, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    Ok(())
+}
+
+#[test]
+fn test_read_invalid() -> Result<()> {
+    let path = get_random_vtk_path();
+    write(&path, "# vtk DataFile Version 3.0\n\nASCII\nDATASET STRUCTURED_POINTS\n")?;
+    assert!(read_vtk(&path).is_err());
+
+    write(&path, "# vtk DataFile Version 3.0\n\nASCII\nDATASET POLYDATA\nPOINTS 2 float\n0 0 0\n")?;
+    assert!(read_vtk(&path).is_err());
+
+    write(
+        &path,
+        "# vtk DataFile Version 3.0\n\nASCII\nDATASET POLYDATA\nPOINTS 1 float\n0 0 0\nLINES 1 3\n2 0 1\n",
+    )?;
+    assert!(read_vtk(&path).is_err());
+
+    // Inconsistent counts in the cells sections
+    let header =
+        "# vtk DataFile Version 3.0\n\nASCII\nDATASET POLYDATA\nPOINTS 2 float\n0 0 0 1 0 0\n";
+    write(&path, format!("{}LINES 1 3\n2 0 1\n", header))?;
+    assert!(read_vtk(&path).is_ok());
+    for cells in [
+        "LINES 3 2\n2 0 1\n",
+        "LINES 1 18446744073709551615\n2 0 1\n",
+        "LINES 1 4\n2 0 1 0\n",
+        "LINES 1 3\n99999999999 0 1\n",
+        "LINES 2 2\nOFFSETS vtktypeint64\n0 3\nCONNECTIVITY vtktypeint64\n0 1\n",
+        "LINES 3 2\nOFFSETS vtktypeint64\n0 2 1\nCONNECTIVITY vtktypeint64\n0 1\n",
+    ] {
+        write(&path, format!("{}{}", header, cells))?;
+        assert!(read_vtk(&path).is_err(), "{:?} should be rejected", cells);
+    }
+    write(&path, format!("{}POINT_DATA 2\nSCALARS fa float 18446744073709551615\n0 1\n", header))?;
+    assert!(read_vtk(&path).is_err());
+
+    // Sections without a name
+    for section in
+        ["SCALARS", "VECTORS", "NORMALS", "TENSORS", "TEXTURE_COORDINATES", "COLOR_SCALARS"]
+    {
+        write(&path, format!("{}POINT_DATA 2\n{}\n0 1\n", header, section))?;
+        assert!(read_vtk(&path).is_err(), "{} without a name should be rejected", section);
+    }
+    assert!(read_vtk("data/simple.trk").is_err());
+    Ok(())
+}
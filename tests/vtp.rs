@@ -0,0 +1,131 @@
+mod test;
+
+use std::fs::{read, write};
+
+use anyhow::Result;
+
+use test::load_trk;
+use trk_io::{
+    vtp::{read_vtp, write_vtp, VtpEncoding},
+    Point,
+};
+
+fn get_random_vtp_path() -> String {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.keep().join("out.vtp");
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_round_trip() -> Result<()> {
+    for path in ["data/simple.trk", "data/complex.trk", "data/empty.trk"] {
+        let (header, tractogram) = load_trk(path);
+        for encoding in [VtpEncoding::Raw, VtpEncoding::Base64] {
+            for compressed in [false, true] {
+                let write_to = get_random_vtp_path();
+                write_vtp(&write_to, &header, &tractogram, encoding, compressed)?;
+                let (vtp_header, vtp_tractogram) = read_vtp(&write_to)?;
+                assert_eq!(vtp_header.nb_streamlines, tractogram.streamlines.len());
+                assert_eq!(vtp_header.scalars_name, header.scalars_name);
+                assert_eq!(vtp_header.properties_name, header.properties_name);
+                assert!(vtp_tractogram == tractogram);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compressed_multiple_blocks() -> Result<()> {
+    // More than 32768 bytes of points, so that the data is split in many compressed blocks
+    let (header, tractogram) = load_trk("data/standard.trk");
+    let mut big = tractogram.clone();
+    while big.streamlines.data.len() * 12 < 100_000 {
+        for streamline in &tractogram.streamlines {
+            big.streamlines.extend_from_slice(streamline);
+        }
+    }
+    let write_to = get_random_vtp_path();
+    write_vtp(&write_to, &header, &big, VtpEncoding::Base64, true)?;
+    let (_, vtp_tractogram) = read_vtp(&write_to)?;
+    assert!(vtp_tractogram == big);
+    Ok(())
+}
+
+#[test]
+fn test_base64_is_valid_xml() -> Result<()> {
+    let (header, tractogram) = load_trk("data/complex.trk");
+    let write_to = get_random_vtp_path();
+    write_vtp(&write_to, &header, &tractogram, VtpEncoding::Base64, true)?;
+    assert!(String::from_utf8(read(&write_to)?).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_read_ascii_and_inline_binary() -> Result<()> {
+    // Big endian, UInt32 headers, inline base64 connectivity and ascii offsets. The connectivity
+    // [2, 1, 0] is encoded as Int32 with a 12 bytes header.
+    let path = get_random_vtp_path();
+    write(
+        &path,
+        r#"<?xml version="1.0"?>
+<VTKFile type="PolyData" version="0.1" byte_order="BigEndian">
+  <PolyData>
+    <Piece NumberOfPoints="3" NumberOfLines="1">
+      <PointData>
+        <DataArray type="Float64" Name="fa" format="ascii">0.5 0.25 1</DataArray>
+      </PointData>
+      <Points>
+        <DataArray type="Float32" NumberOfComponents="3" format="ascii">
+          0 0 0 1 0 0 2 0 0
+        </DataArray>
+      </Points>
+      <Lines>
+        <DataArray type="Int32" Name="connectivity" format="binary">
+          AAAADAAAAAIAAAABAAAAAA==
+        </DataArray>
+        <DataArray type="Int32" Name="offsets" format="ascii">3</DataArray>
+      </Lines>
+    </Piece>
+  </PolyData>
+</VTKFile>
+"#,
+    )?;
+
+    let (header, tractogram) = read_vtp(&path)?;
+    assert_eq!(header.scalars_name, ["fa"]);
+    assert_eq!(
+        tractogram.streamlines[0],
+        [Point::new(2.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0), Point::new(0.0, 0.0, 0.0)]
+    );
+    assert_eq!(tractogram.scalars.data, [1.0, 0.25, 0.5]);
+    Ok(())
+}
+
+#[test]
+fn test_read_invalid() {
+    assert!(read_vtp("data/simple.trk").is_err());
+    let path = get_random_vtp_path();
+    write(&path, r#"<VTKFile type="UnstructuredGrid"></VTKFile>"#).unwrap();
+    assert!(read_vtp(&path).is_err());
+
+    // Compressed block headers with absurd sizes
+    let xml = r#"<VTKFile type="PolyData" header_type="UInt64" compressor="vtkZLibDataCompressor">
+<PolyData><Piece><Points>
+<DataArray type="Float32" NumberOfComponents="3" format="appended" offset="0"/>
+</Points></Piece></PolyData>
+<AppendedData encoding="raw">_"#;
+    for header in [
+        [u64::MAX - 1, 12, 12, 8],
+        [2, 12, 12, u64::MAX],
+        [1, u64::MAX, 0, 8],
+        [1, 12, 12, u64::MAX - 100],
+    ] {
+        let mut bytes = xml.as_bytes().to_vec();
+        bytes.extend(header.iter().flat_map(|h| h.to_le_bytes()));
+        bytes.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"</AppendedData></VTKFile>");
+        write(&path, bytes).unwrap();
+        assert!(read_vtp(&path).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+mod test;
+
+#[cfg(feature = "nifti_images")]
+mod nifti_tests {
+    use anyhow::Result;
+    use ndarray::{ArrayD, IxDyn};
+    use nifti::{writer::WriterOptions, NiftiHeader};
+    use trk_io::{
+        warp::{warp_tractogram, DeformationField, WarpConvention},
+        Affine4, ArraySequence, Header, Point, Streamlines, Tractogram, Translation,
+    };
+
+    use crate::test::get_random_trk_path;
+
+    fn get_nifti_header(affine: &Affine4) -> NiftiHeader {
+        let mut header = NiftiHeader { dim: [3, 4, 4, 4, 1, 1, 1, 1], ..NiftiHeader::default() };
+        header.set_affine(affine);
+        header
+    }
+
+    /// Field with the same displacement in all voxels.
+    fn get_constant_field(shape: &[usize], displacement: [f32; 3]) -> ArrayD<f32> {
+        ArrayD::from_shape_fn(IxDyn(shape), |idx| displacement[idx[shape.len() - 1]])
+    }
+
+    #[test]
+    fn test_itk_field() -> Result<()> {
+        let header = get_nifti_header(&Affine4::identity());
+        let data = get_constant_field(&[4, 4, 4, 1, 3], [1.0, 2.0, 3.0]);
+        let field = DeformationField::new(&header, data, WarpConvention::Itk)?;
+        assert_eq!(field.apply(&Point::new(1.0, 1.5, 2.0)), Point::new(0.0, -0.5, 5.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fsl_field() -> Result<()> {
+        // Neurological order: the first FSL axis is flipped
+        let affine = Affine4::new_scaling(2.0);
+        let header = get_nifti_header(&affine);
+        let data = get_constant_field(&[4, 4, 4, 3], [1.0, 2.0, 3.0]);
+        let field = DeformationField::new(&header, data, WarpConvention::Fsl)?;
+        assert_eq!(
+            field.displacement(&Point::new(1.0, 1.0, 1.0)),
+            Translation::new(-1.0, 2.0, 3.0)
+        );
+
+        // Radiological order
+        let mut affine = Affine4::new_scaling(2.0);
+        affine[(0, 0)] = -2.0;
+        let header = get_nifti_header(&affine);
+        let data = get_constant_field(&[4, 4, 4, 3], [1.0, 2.0, 3.0]);
+        let field = DeformationField::new(&header, data, WarpConvention::Fsl)?;
+        assert_eq!(
+            field.displacement(&Point::new(-1.0, 1.0, 1.0)),
+            Translation::new(-1.0, 2.0, 3.0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolated_field() -> Result<()> {
+        let header = get_nifti_header(&Affine4::identity());
+        let data = ArrayD::from_shape_fn(IxDyn(&[4, 4, 4, 3]), |idx| {
+            if idx[3] == 2 {
+                idx[0] as f32
+            } else {
+                0.0
+            }
+        });
+        let field = DeformationField::new(&header, data, WarpConvention::Fsl)?;
+        assert_eq!(field.apply(&Point::new(1.5, 0.0, 0.0)), Point::new(1.5, 0.0, 1.5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_shape() {
+        let header = get_nifti_header(&Affine4::identity());
+        let data = get_constant_field(&[4, 4, 4, 2], [1.0, 2.0, 3.0]);
+        assert!(DeformationField::new(&header, data, WarpConvention::Fsl).is_err());
+    }
+
+    #[test]
+    fn test_warp_tractogram() -> Result<()> {
+        let nifti_header = get_nifti_header(&Affine4::identity());
+        let path = get_random_trk_path().replace(".trk", ".nii.gz");
+        let data = get_constant_field(&[4, 4, 4, 1, 3], [-1.0, 0.0, 0.5]);
+        WriterOptions::new(&path).reference_header(&nifti_header).write_nifti(&data)?;
+        let field = DeformationField::from_nifti(&path, WarpConvention::Itk)?;
+
+        let mut header = Header::default();
+        header.add_property("id")?;
+        let streamlines = Streamlines::new(
+            vec![2, 1],
+            vec![Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0), Point::new(2.0, 2.0, 2.0)],
+        );
+        let properties = ArraySequence::new(vec![1, 1], vec![1.0, 2.0]);
+        let tractogram = Tractogram::new(streamlines, ArraySequence::empty(), properties);
+
+        let pre = Affine4::new_translation(&[1.0, 0.0, 0.0].into());
+        let post = Affine4::new_scaling(2.0);
+        let (new_header, warped) =
+            warp_tractogram(&tractogram, &header, &field, Some(&pre), Some(&post));
+        assert_eq!(new_header.raw_header().dim, [4, 4, 4]);
+        assert_eq!(new_header.properties_name, vec!["id".to_string()]);
+        assert_eq!(warped.streamlines.offsets, vec![0, 2, 3]);
+        assert_eq!(warped.streamlines[0][0], Point::new(4.0, 0.0, 1.0));
+        assert_eq!(warped.streamlines[1][0], Point::new(8.0, 4.0, 5.0));
+        assert!(warped.properties == tractogram.properties);
+        Ok(())
+    }
+}